@@ -0,0 +1,304 @@
+//! Derive macros for [`daprs`](https://docs.rs/daprs).
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, Ident, LitStr};
+
+/// Derives `Process` for a struct whose inputs and outputs are `f64` fields, processed one sample at a time.
+///
+/// Fields marked `#[input]` hold the current sample of each input, and fields marked `#[output]` the sample to write to each output,
+/// both in the order they're declared. The struct must implement `SampleProcess`, whose `tick` method is called once per sample
+/// after the inputs have been read, and before the outputs are written.
+///
+/// The struct also gets a `StaticPorts` impl (and `SingleOutput`, if it has exactly one output) for use with `TypedNode`s,
+/// and an associated constant per port named after its field in upper case, e.g. `Gain::GAIN` for the `gain` input below.
+///
+/// Its `Process::name` is the struct's path, e.g. `my_crate::effects::Gain`, which is what it should be registered under in a `ProcessorRegistry`.
+///
+/// Both attributes take optional `name = "..."` (the field name by default), `default = ...`, `min = ...` and `max = ...` arguments
+/// that end up in the port's `SignalSpec`, and an optional `kind = ...` naming its `SignalKind` (e.g. `kind = Control`).
+/// Ports without `min` or `max` are unbounded, like `SignalSpec::unbounded`.
+///
+/// ```
+/// use daprs::prelude::*;
+///
+/// #[derive(Clone, Default, Process)]
+/// struct Gain {
+///     #[input(name = "in")]
+///     input: f64,
+///     #[input(default = 1.0, min = 0.0)]
+///     gain: f64,
+///     #[output]
+///     out: f64,
+/// }
+///
+/// impl SampleProcess for Gain {
+///     fn tick(&mut self) {
+///         self.out = self.input * self.gain;
+///     }
+/// }
+///
+/// let inputs = Gain::default().input_spec();
+/// assert_eq!(inputs[0].name, "in");
+/// assert_eq!(inputs[1], SignalSpec::new("gain", 0.0, f64::MAX, 1.0));
+/// ```
+///
+/// Malformed attributes are reported as compile errors, such as unknown arguments:
+///
+/// ```compile_fail
+/// use daprs::prelude::*;
+///
+/// #[derive(Clone, Default, Process)]
+/// struct Gain {
+///     #[input(gain = 1.0)]
+///     gain: f64,
+/// }
+///
+/// impl SampleProcess for Gain {
+///     fn tick(&mut self) {}
+/// }
+/// ```
+///
+/// or arguments without a value:
+///
+/// ```compile_fail
+/// use daprs::prelude::*;
+///
+/// #[derive(Clone, Default, Process)]
+/// struct Gain {
+///     #[input(default)]
+///     gain: f64,
+/// }
+///
+/// impl SampleProcess for Gain {
+///     fn tick(&mut self) {}
+/// }
+/// ```
+#[proc_macro_derive(Process, attributes(input, output))]
+pub fn derive_process(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// An `#[input]` or `#[output]` field.
+struct Port {
+    field: Ident,
+    name: LitStr,
+    default: TokenStream2,
+    min: TokenStream2,
+    max: TokenStream2,
+    kind: TokenStream2,
+}
+
+impl Port {
+    fn parse(field: &Ident, attr: &syn::Attribute) -> syn::Result<Self> {
+        let mut port = Self {
+            field: field.clone(),
+            name: LitStr::new(&field.to_string(), field.span()),
+            default: quote!(0.0),
+            min: quote!(f64::MIN),
+            max: quote!(f64::MAX),
+            kind: quote!(Audio),
+        };
+
+        // bare `#[input]` has no arguments to parse
+        if matches!(attr.meta, syn::Meta::Path(_)) {
+            return Ok(port);
+        }
+
+        attr.parse_nested_meta(|meta| {
+            let value = meta.value()?;
+            if meta.path.is_ident("name") {
+                port.name = value.parse()?;
+            } else if meta.path.is_ident("default") {
+                let expr: syn::Expr = value.parse()?;
+                port.default = quote!((#expr) as f64);
+            } else if meta.path.is_ident("min") {
+                let expr: syn::Expr = value.parse()?;
+                port.min = quote!((#expr) as f64);
+            } else if meta.path.is_ident("max") {
+                let expr: syn::Expr = value.parse()?;
+                port.max = quote!((#expr) as f64);
+            } else if meta.path.is_ident("kind") {
+                let kind: Ident = value.parse()?;
+                port.kind = quote!(#kind);
+            } else {
+                return Err(meta.error("expected `name`, `default`, `min`, `max` or `kind`"));
+            }
+            Ok(())
+        })?;
+
+        Ok(port)
+    }
+
+    fn spec(&self) -> TokenStream2 {
+        let Self {
+            name,
+            default,
+            min,
+            max,
+            kind,
+            ..
+        } = self;
+        quote! {
+            ::daprs::processor::SignalSpec::new(#name, #min, #max, #default)
+                .with_kind(::daprs::processor::SignalKind::#kind)
+        }
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+            input.span(),
+            "`Process` can only be derived for structs",
+        ));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new(
+            data.fields.span(),
+            "`Process` can only be derived for structs with named fields",
+        ));
+    };
+
+    let mut inputs = Vec::new();
+    let mut outputs = Vec::new();
+    for field in fields.named.iter() {
+        let ident = field.ident.as_ref().expect("named fields have names");
+        for attr in field.attrs.iter() {
+            if attr.path().is_ident("input") {
+                inputs.push(Port::parse(ident, attr)?);
+            } else if attr.path().is_ident("output") {
+                outputs.push(Port::parse(ident, attr)?);
+            }
+        }
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let input_specs = inputs.iter().map(Port::spec);
+    let output_specs = outputs.iter().map(Port::spec);
+
+    let num_inputs = inputs.len();
+    let num_outputs = outputs.len();
+
+    let port_consts = inputs
+        .iter()
+        .enumerate()
+        .map(|(index, port)| (index, port, quote!(InputPort)))
+        .chain(
+            outputs
+                .iter()
+                .enumerate()
+                .map(|(index, port)| (index, port, quote!(OutputPort))),
+        )
+        .map(|(index, port, kind)| {
+            let index = index as u32;
+            let name = Ident::new(&port.field.to_string().to_uppercase(), port.field.span());
+            let doc = format!("The `{}` port.", port.name.value());
+            quote! {
+                #[doc = #doc]
+                pub const #name: ::daprs::builder::typed_node::#kind<Self, #index> =
+                    ::daprs::builder::typed_node::#kind::new();
+            }
+        });
+
+    let single_output = (num_outputs == 1).then(|| {
+        quote! {
+            impl #impl_generics ::daprs::builder::typed_node::SingleOutput for #name #ty_generics #where_clause {}
+        }
+    });
+
+    let read_inputs = inputs.iter().enumerate().map(|(index, port)| {
+        let field = &port.field;
+        quote!(self.#field = inputs[#index][i].value();)
+    });
+    let write_outputs = outputs.iter().enumerate().map(|(index, port)| {
+        let field = &port.field;
+        quote!(outputs[#index][i] = ::daprs::signal::Sample::new(self.#field);)
+    });
+
+    Ok(quote! {
+        impl #impl_generics #name #ty_generics #where_clause {
+            #(#port_consts)*
+        }
+
+        impl #impl_generics ::daprs::builder::typed_node::StaticPorts for #name #ty_generics #where_clause {
+            const NUM_INPUTS: usize = #num_inputs;
+            const NUM_OUTPUTS: usize = #num_outputs;
+        }
+
+        #single_output
+
+        impl #impl_generics ::daprs::processor::Process for #name #ty_generics #where_clause {
+            fn name(&self) -> &str {
+                ::std::concat!(::std::module_path!(), "::", ::std::stringify!(#name))
+            }
+
+            fn input_spec(&self) -> ::std::vec::Vec<::daprs::processor::SignalSpec> {
+                ::std::vec![#(#input_specs),*]
+            }
+
+            fn output_spec(&self) -> ::std::vec::Vec<::daprs::processor::SignalSpec> {
+                ::std::vec![#(#output_specs),*]
+            }
+
+            fn params(&self) -> ::daprs::processor::Params {
+                ::daprs::processor::SampleProcess::params(self)
+            }
+
+            fn prepare(&mut self) {
+                ::daprs::processor::SampleProcess::prepare(self)
+            }
+
+            fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+                ::daprs::processor::SampleProcess::resize_buffers(self, sample_rate, block_size)
+            }
+
+            fn reset(&mut self) {
+                ::daprs::processor::SampleProcess::reset(self)
+            }
+
+            fn tail_length(&self) -> usize {
+                ::daprs::processor::SampleProcess::tail_length(self)
+            }
+
+            fn latency(&self) -> usize {
+                ::daprs::processor::SampleProcess::latency(self)
+            }
+
+            fn save_state(&self) -> ::std::option::Option<::daprs::processor::Param> {
+                ::daprs::processor::SampleProcess::save_state(self)
+            }
+
+            fn load_state(
+                &mut self,
+                state: &::daprs::processor::Param,
+            ) -> ::std::result::Result<(), ::daprs::registry::ParamError> {
+                ::daprs::processor::SampleProcess::load_state(self, state)
+            }
+
+            fn process(
+                &mut self,
+                inputs: &[::daprs::signal::Buffer],
+                outputs: &mut [::daprs::signal::Buffer],
+            ) {
+                let len = outputs
+                    .iter()
+                    .chain(inputs)
+                    .map(|buffer| buffer.len())
+                    .next()
+                    .unwrap_or(0);
+                for i in 0..len {
+                    #(#read_inputs)*
+                    ::daprs::processor::SampleProcess::tick(self);
+                    #(#write_outputs)*
+                }
+            }
+        }
+    })
+}
@@ -0,0 +1,201 @@
+//! The `daprs` command line tool, built with the `cli` feature, e.g. `cargo run --features cli -- play patch.json`.
+
+use std::{error::Error, path::PathBuf, time::Duration};
+
+use clap::{builder::RangedU64ValueParser, Parser, Subcommand};
+use daprs::{
+    available_backends,
+    graph::Graph,
+    patch::Patch,
+    runtime::{devices::enumerate_devices, Backend, DeviceDirection, Runtime},
+};
+
+/// Render, play and inspect daprs patches.
+#[derive(Parser)]
+#[command(name = "daprs", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// List the available audio backends.
+    Backends,
+    /// List the audio devices of a backend.
+    Devices {
+        /// The backend to list devices for.
+        #[arg(long, default_value = "default")]
+        backend: Backend,
+        /// List input (capture) devices instead of output devices.
+        #[arg(long)]
+        inputs: bool,
+    },
+    /// Render a patch offline to a WAV file.
+    Render {
+        /// The patch to render (`.json` for patch files, anything else for patch programs).
+        patch: PathBuf,
+        /// The WAV file to write.
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How many seconds to render.
+        #[arg(short, long, default_value = "1", value_parser = parse_duration)]
+        duration: Duration,
+        #[arg(short, long, default_value_t = 48_000.0, value_parser = parse_sample_rate)]
+        sample_rate: f64,
+        #[arg(short, long, default_value_t = 512, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        block_size: usize,
+    },
+    /// Play a patch in real-time on an audio device.
+    Play {
+        /// The patch to play (`.json` for patch files, anything else for patch programs).
+        patch: PathBuf,
+        #[arg(long, default_value = "default")]
+        backend: Backend,
+        /// The device to play on: `default`, a device index, or part of a device name.
+        #[arg(long, default_value = "default")]
+        device: daprs::runtime::Device,
+        /// How many seconds to play for. If not given, plays until Enter is pressed.
+        #[arg(short, long, value_parser = parse_duration)]
+        duration: Option<Duration>,
+        /// Also record everything that's played to this WAV file.
+        #[arg(long)]
+        record: Option<PathBuf>,
+    },
+    /// Process a WAV file through a patch, feeding each channel into the corresponding graph input.
+    Process {
+        /// The WAV file to process.
+        input: PathBuf,
+        /// The patch to process it with (`.json` for patch files, anything else for patch programs).
+        patch: PathBuf,
+        /// The WAV file to write.
+        #[arg(short, long)]
+        output: PathBuf,
+        #[arg(short, long, default_value_t = 512, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        block_size: usize,
+    },
+}
+
+/// Parses a duration given in (possibly fractional) seconds, rejecting negative, non-finite and out of range values.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let seconds = s.parse::<f64>().map_err(|err| err.to_string())?;
+    Duration::try_from_secs_f64(seconds).map_err(|err| err.to_string())
+}
+
+/// Parses a sample rate in Hz, rejecting zero, negative and non-finite values.
+fn parse_sample_rate(s: &str) -> Result<f64, String> {
+    let sample_rate = s.parse::<f64>().map_err(|err| err.to_string())?;
+    if sample_rate.is_finite() && sample_rate > 0.0 {
+        Ok(sample_rate)
+    } else {
+        Err("the sample rate must be positive and finite".to_owned())
+    }
+}
+
+fn load_graph(path: &PathBuf) -> Result<Graph, Box<dyn Error>> {
+    if path.extension().is_some_and(|ext| ext == "json") {
+        return Ok(Patch::load(path)?.to_graph()?);
+    }
+
+    let source = std::fs::read_to_string(path)?;
+    daprs::dsl::compile(&source).map_err(|err| {
+        format!(
+            "failed to compile {}:\n{}",
+            path.display(),
+            err.render(&source)
+        )
+        .into()
+    })
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    match cli.command {
+        Command::Backends => {
+            for (i, backend) in available_backends().into_iter().enumerate() {
+                println!("{}: {:?}", i, backend);
+            }
+        }
+        Command::Devices { backend, inputs } => {
+            let direction = if inputs {
+                DeviceDirection::Input
+            } else {
+                DeviceDirection::Output
+            };
+            for device in enumerate_devices(backend, direction)? {
+                let default = if device.is_default { " (default)" } else { "" };
+                println!("{}: {}{}", device.index, device.name, default);
+                println!(
+                    "    channels: {} in, {} out",
+                    device.input_channels, device.output_channels
+                );
+                println!("    sample rates: {:?}", device.sample_rates);
+                if let Some(range) = device.buffer_size {
+                    println!("    buffer sizes: {}..={}", range.min, range.max);
+                }
+                println!("    sample formats: {:?}", device.sample_formats);
+            }
+        }
+        Command::Render {
+            patch,
+            output,
+            duration,
+            sample_rate,
+            block_size,
+        } => {
+            let mut runtime = Runtime::new(load_graph(&patch)?);
+            runtime.run_offline_to_file(output, duration, sample_rate, block_size)?;
+        }
+        Command::Play {
+            patch,
+            backend,
+            device,
+            duration,
+            record,
+        } => {
+            let runtime = Runtime::new(load_graph(&patch)?);
+            let mut handle = runtime.run(backend, device)?;
+
+            if let Some(record) = record {
+                handle.start_recording(record)?;
+            }
+
+            match duration {
+                Some(duration) => std::thread::sleep(duration),
+                None => {
+                    println!("Playing; press Enter to stop.");
+                    std::io::stdin().read_line(&mut String::new())?;
+                }
+            }
+
+            if handle.is_recording() {
+                let stats = handle.stop_recording()?;
+                println!(
+                    "Recorded {} frames ({} blocks dropped)",
+                    stats.frames_written, stats.dropped_blocks
+                );
+            }
+
+            handle.stop()?;
+        }
+        Command::Process {
+            input,
+            patch,
+            output,
+            block_size,
+        } => {
+            let mut runtime = Runtime::new(load_graph(&patch)?);
+            runtime.process_file(input, output, block_size)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("warn"));
+
+    if let Err(err) = run(Cli::parse()) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+}
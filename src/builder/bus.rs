@@ -0,0 +1,403 @@
+//! Multichannel signals for the graph builder.
+
+use std::f64::consts::FRAC_PI_4;
+
+use super::{
+    graph_builder::GraphBuilder,
+    node_builder::{IntoNode, Node},
+};
+use crate::{
+    builtins::math::IdentityProc,
+    graph::{GraphConstructionError, GraphConstructionResult},
+    processor::Process,
+};
+
+/// A multichannel signal made of one single-output [`Node`] per channel.
+///
+/// Operations on a [`Bus`] are expanded per channel, SuperCollider-style: each channel gets its own copy of the processor.
+/// When two buses with different numbers of channels are combined, the result has as many channels as the larger one, and the smaller one's channels are repeated cyclically.
+/// In particular, a mono signal (a single [`Node`], or a plain number) is broadcast to every channel.
+#[derive(Clone, Debug)]
+pub struct Bus<'a> {
+    channels: Vec<Node<'a>>,
+}
+
+impl<'a> Bus<'a> {
+    /// Creates a new [`Bus`] from the given channels.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `channels` is empty, or if any of the nodes doesn't have exactly one output.
+    pub fn new(channels: Vec<Node<'a>>) -> Self {
+        assert!(!channels.is_empty(), "A bus needs at least one channel");
+        for channel in channels.iter() {
+            channel.assert_single_output();
+        }
+        Self { channels }
+    }
+
+    /// Creates a new [`Bus`] from the given channels, returning an error if `channels` is empty or any of the nodes doesn't have exactly one output.
+    pub fn try_new(channels: Vec<Node<'a>>) -> GraphConstructionResult<Self> {
+        if channels.is_empty() {
+            return Err(GraphConstructionError::EmptyBus);
+        }
+        for channel in channels.iter() {
+            channel.try_single_output("bus")?;
+        }
+        Ok(Self { channels })
+    }
+
+    /// Creates a [`Bus`] with one channel per output of the given node.
+    ///
+    /// Nodes with more than one output have each output passed through its own [`IdentityProc`] node, so that every channel is a single-output node.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the node has no outputs.
+    pub fn split(node: Node<'a>) -> Self {
+        Self::try_split(node).unwrap()
+    }
+
+    /// Creates a [`Bus`] with one channel per output of the given node, or returns an error if the node has no outputs. See [`Bus::split`].
+    pub fn try_split(node: Node<'a>) -> GraphConstructionResult<Self> {
+        let graph = node.graph();
+        let num_outputs = graph.try_with_graph(|graph| {
+            graph
+                .digraph()
+                .node_weight(node.id())
+                .map(|weight| weight.outputs().len())
+                .ok_or(GraphConstructionError::UnknownNode(node.id()))
+        })??;
+        match num_outputs {
+            0 => Err(GraphConstructionError::EmptyBus),
+            1 => Ok(Self {
+                channels: vec![node],
+            }),
+            _ => {
+                let channels = (0..num_outputs as u32)
+                    .map(|output| {
+                        graph
+                            .try_add(IdentityProc)?
+                            .try_connect_input(node, output, 0)
+                    })
+                    .collect::<GraphConstructionResult<_>>()?;
+                Ok(Self { channels })
+            }
+        }
+    }
+
+    /// Creates one copy of the given processor per channel, and connects the given buses to its inputs in order.
+    ///
+    /// The result has as many channels as the largest of the input buses (or one, if there are no inputs).
+    ///
+    /// # Panics
+    ///
+    /// Panics if the processor doesn't have exactly one output.
+    pub fn expand(graph: &'a GraphBuilder, processor: impl Process, inputs: &[Bus<'a>]) -> Self {
+        let num_channels = inputs.iter().map(Bus::num_channels).max().unwrap_or(1);
+        let channels = (0..num_channels)
+            .map(|channel| {
+                let node = graph.add_processor_object(processor.processor());
+                for (input, bus) in inputs.iter().enumerate() {
+                    node.connect_input(bus.channel(channel), 0, input as u32);
+                }
+                node
+            })
+            .collect();
+        Self::new(channels)
+    }
+
+    /// Returns the number of channels.
+    #[inline]
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    /// Returns the channel at the given index, wrapping around if the index is out of bounds.
+    #[inline]
+    pub fn channel(&self, index: usize) -> Node<'a> {
+        self.channels[index % self.channels.len()]
+    }
+
+    /// Returns the channels of this bus.
+    #[inline]
+    pub fn channels(&self) -> &[Node<'a>] {
+        &self.channels
+    }
+
+    #[inline]
+    pub fn graph(&self) -> &'a GraphBuilder {
+        self.channels[0].graph()
+    }
+
+    /// Applies the given function to each channel, returning a new [`Bus`] of the results.
+    pub fn map<F>(&self, f: F) -> Self
+    where
+        F: FnMut(Node<'a>) -> Node<'a>,
+    {
+        Self::new(self.channels.iter().copied().map(f).collect())
+    }
+
+    /// Applies the given function to each pair of corresponding channels of this bus and another, broadcasting as described in the [`Bus`] documentation.
+    pub fn zip_with<F>(&self, other: impl IntoBus<'a>, mut f: F) -> Self
+    where
+        F: FnMut(Node<'a>, Node<'a>) -> Node<'a>,
+    {
+        let other = other.into_bus(self.graph());
+        let num_channels = self.num_channels().max(other.num_channels());
+        Self::new(
+            (0..num_channels)
+                .map(|channel| f(self.channel(channel), other.channel(channel)))
+                .collect(),
+        )
+    }
+
+    /// Creates one copy of the given processor per channel, with the channel connected to its first input.
+    ///
+    /// This is the usual way to run a mono effect on every channel of a bus.
+    pub fn apply(&self, processor: impl Process) -> Self {
+        Self::expand(self.graph(), processor, std::slice::from_ref(self))
+    }
+
+    /// Connects each channel to the first input of the corresponding channel of the given bus.
+    ///
+    /// A bus with fewer channels than `targets` is broadcast as described in the [`Bus`] documentation,
+    /// and one with more channels is first mixed down with [`Bus::mix_to`], since an input can only be driven by a single source.
+    pub fn connect_to(&self, targets: &Bus<'a>) -> &Self {
+        let num_targets = targets.num_channels();
+        let sources = if self.num_channels() > num_targets {
+            self.mix_to(num_targets)
+        } else {
+            self.clone()
+        };
+        for (channel, &target) in targets.channels.iter().enumerate() {
+            sources.channel(channel).connect_output(0, target, 0);
+        }
+        self
+    }
+
+    /// Sums all channels into a single mono signal.
+    pub fn mix(&self) -> Node<'a> {
+        let mut channels = self.channels.iter().copied();
+        let first = channels.next().expect("A bus has at least one channel");
+        channels.fold(first, |sum, channel| sum.add(channel))
+    }
+
+    /// Mixes this bus down (or up) to the given number of channels, summing every `num_channels`-th channel into the same output channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is zero.
+    pub fn mix_to(&self, num_channels: usize) -> Self {
+        assert!(num_channels > 0, "A bus needs at least one channel");
+        Self::new(
+            (0..num_channels)
+                .map(|target| {
+                    let mut sources = self
+                        .channels
+                        .iter()
+                        .copied()
+                        .skip(target)
+                        .step_by(num_channels);
+                    match sources.next() {
+                        Some(first) => sources.fold(first, |sum, channel| sum.add(channel)),
+                        // upmixing repeats the channels cyclically
+                        None => self.channel(target),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// Pans each channel to the given stereo position with an equal-power pan law, and sums the results into a stereo bus.
+    ///
+    /// Positions range from `-1.0` (hard left) to `1.0` (hard right), and are broadcast as described in the [`Bus`] documentation, so each channel can be given its own position.
+    pub fn pan(&self, position: impl IntoBus<'a>) -> Self {
+        let position = position.into_bus(self.graph());
+        let num_channels = self.num_channels().max(position.num_channels());
+        let panned = (0..num_channels)
+            .map(|channel| self.channel(channel).pan(position.channel(channel)))
+            .collect::<Vec<_>>();
+
+        let mut panned = panned.into_iter();
+        let first = panned.next().expect("A bus has at least one channel");
+        panned.fold(first, |sum, stereo| sum + stereo)
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Pans this mono signal to the given stereo position with an equal-power pan law, returning a two-channel [`Bus`].
+    ///
+    /// Positions range from `-1.0` (hard left) to `1.0` (hard right).
+    pub fn pan(self, position: impl IntoNode<'a>) -> Bus<'a> {
+        let position = position.into_node(self.graph());
+        let angle = (position + 1.0) * FRAC_PI_4;
+        Bus::new(vec![self * angle.cos(), self * angle.sin()])
+    }
+
+    /// Splits the outputs of this node into a [`Bus`]. See [`Bus::split`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node has no outputs.
+    pub fn split(self) -> Bus<'a> {
+        Bus::split(self)
+    }
+
+    /// Splits the outputs of this node into a [`Bus`], or returns an error if this node has no outputs. See [`Bus::split`].
+    pub fn try_split(self) -> GraphConstructionResult<Bus<'a>> {
+        Bus::try_split(self)
+    }
+}
+
+impl GraphBuilder {
+    /// Adds the given number of graph inputs, returning them as a [`Bus`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is zero.
+    pub fn add_input_bus(&self, num_channels: usize) -> Bus<'_> {
+        self.try_add_input_bus(num_channels).unwrap()
+    }
+
+    /// Adds the given number of graph inputs, returning them as a [`Bus`], or returns an error without adding any if `num_channels` is zero.
+    pub fn try_add_input_bus(&self, num_channels: usize) -> GraphConstructionResult<Bus<'_>> {
+        if num_channels == 0 {
+            return Err(GraphConstructionError::EmptyBus);
+        }
+        let channels = (0..num_channels)
+            .map(|_| self.try_add_input())
+            .collect::<GraphConstructionResult<_>>()?;
+        Ok(Bus { channels })
+    }
+
+    /// Adds the given number of graph outputs, returning them as a [`Bus`] to connect signals to with [`Bus::connect_to`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_channels` is zero.
+    pub fn add_output_bus(&self, num_channels: usize) -> Bus<'_> {
+        self.try_add_output_bus(num_channels).unwrap()
+    }
+
+    /// Adds the given number of graph outputs, returning them as a [`Bus`], or returns an error without adding any if `num_channels` is zero.
+    pub fn try_add_output_bus(&self, num_channels: usize) -> GraphConstructionResult<Bus<'_>> {
+        if num_channels == 0 {
+            return Err(GraphConstructionError::EmptyBus);
+        }
+        let channels = (0..num_channels)
+            .map(|_| self.try_add_output())
+            .collect::<GraphConstructionResult<_>>()?;
+        Ok(Bus { channels })
+    }
+}
+
+/// Conversion into a [`Bus`], used for the arguments of [`Bus`] operations.
+pub trait IntoBus<'a> {
+    fn into_bus(self, graph_builder: &'a GraphBuilder) -> Bus<'a>;
+}
+
+impl<'a> IntoBus<'a> for Bus<'a> {
+    fn into_bus(self, _graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        self
+    }
+}
+
+impl<'a> IntoBus<'a> for &Bus<'a> {
+    fn into_bus(self, _graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        self.clone()
+    }
+}
+
+impl<'a> IntoBus<'a> for Node<'a> {
+    fn into_bus(self, _graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        Bus::split(self)
+    }
+}
+
+impl<'a> IntoBus<'a> for f64 {
+    fn into_bus(self, graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        Bus::new(vec![graph_builder.add_constant(self)])
+    }
+}
+
+impl<'a> IntoBus<'a> for Vec<Node<'a>> {
+    fn into_bus(self, _graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        Bus::new(self)
+    }
+}
+
+impl<'a, const N: usize> IntoBus<'a> for [f64; N] {
+    fn into_bus(self, graph_builder: &'a GraphBuilder) -> Bus<'a> {
+        Bus::new(
+            self.iter()
+                .map(|&value| graph_builder.add_constant(value))
+                .collect(),
+        )
+    }
+}
+
+macro_rules! impl_binary_bus_ops {
+    ($($name:ident $(: $std_op:ident)?),* $(,)?) => {
+        impl<'a> Bus<'a> {
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $name(self, other: impl IntoBus<'a>) -> Bus<'a> {
+                    self.zip_with(other, Node::$name)
+                }
+            )*
+        }
+
+        $($(
+            impl<'a, T> std::ops::$std_op<T> for Bus<'a>
+            where
+                T: IntoBus<'a>,
+            {
+                type Output = Bus<'a>;
+
+                fn $name(self, other: T) -> Self::Output {
+                    Bus::$name(self, other)
+                }
+            }
+        )?)*
+    };
+}
+
+impl_binary_bus_ops!(
+    add: Add,
+    sub: Sub,
+    mul: Mul,
+    div: Div,
+    rem: Rem,
+    powf,
+    atan2,
+    hypot,
+    max,
+    min,
+);
+
+macro_rules! impl_unary_bus_ops {
+    ($($name:ident),* $(,)?) => {
+        impl<'a> Bus<'a> {
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $name(self) -> Bus<'a> {
+                    self.map(Node::$name)
+                }
+            )*
+        }
+    };
+}
+
+impl_unary_bus_ops!(
+    neg, abs, sqrt, cbrt, ceil, floor, round, trunc, sin, cos, tan, asin, acos, atan, sinh, cosh,
+    tanh, exp, exp2, exp_m1, ln, log2, log10, recip, signum, fract,
+);
+
+impl<'a> std::ops::Neg for Bus<'a> {
+    type Output = Bus<'a>;
+
+    fn neg(self) -> Self::Output {
+        Bus::neg(self)
+    }
+}
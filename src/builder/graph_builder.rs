@@ -1,12 +1,19 @@
 use std::sync::Mutex;
 
 use crate::{
-    graph::{Graph, NodeIndex},
-    prelude::{ConstantProc, Process},
+    builtins::func::{FnContext, FnProc, MapProc, PortNames, SampleFn},
+    graph::{
+        subgraph::SubGraph, Graph, GraphConstructionError, GraphConstructionResult, NodeIndex,
+    },
+    prelude::{Buffer, ConstantProc, Process, Processor},
 };
 
 use super::node_builder::Node;
 
+/// A builder for [`Graph`]s, handing out [`Node`]s that can be connected and combined with operators.
+///
+/// Most methods panic on invalid use (e.g. connecting ports that don't exist, or modifying the graph after [`build`](GraphBuilder::build) was called).
+/// Each of them has a `try_` variant that returns a [`GraphConstructionError`] instead, for building graphs from user input.
 pub struct GraphBuilder {
     graph: Mutex<Option<Graph>>,
 }
@@ -30,22 +37,56 @@ impl GraphBuilder {
         }
     }
 
+    /// Returns the finished [`Graph`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the graph has already been built.
     pub fn build(&self) -> Graph {
-        self.graph.lock().unwrap().take().unwrap()
+        self.try_build().unwrap()
+    }
+
+    /// Returns the finished [`Graph`], or [`GraphConstructionError::AlreadyBuilt`] if it has already been built.
+    pub fn try_build(&self) -> GraphConstructionResult<Graph> {
+        self.graph
+            .lock()
+            .unwrap()
+            .take()
+            .ok_or(GraphConstructionError::AlreadyBuilt)
     }
 
     pub fn with_graph<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&Graph) -> R,
     {
-        f(self.graph.lock().unwrap().as_ref().unwrap())
+        self.try_with_graph(f).unwrap()
+    }
+
+    /// Calls the given closure with the graph being built, or returns [`GraphConstructionError::AlreadyBuilt`] if it has already been built.
+    pub fn try_with_graph<F, R>(&self, f: F) -> GraphConstructionResult<R>
+    where
+        F: FnOnce(&Graph) -> R,
+    {
+        let graph = self.graph.lock().unwrap();
+        let graph = graph.as_ref().ok_or(GraphConstructionError::AlreadyBuilt)?;
+        Ok(f(graph))
     }
 
     pub fn with_graph_mut<F, R>(&self, f: F) -> R
     where
         F: FnOnce(&mut Graph) -> R,
     {
-        f(self.graph.lock().unwrap().as_mut().unwrap())
+        self.try_with_graph_mut(f).unwrap()
+    }
+
+    /// Calls the given closure with the graph being built, or returns [`GraphConstructionError::AlreadyBuilt`] if it has already been built.
+    pub fn try_with_graph_mut<F, R>(&self, f: F) -> GraphConstructionResult<R>
+    where
+        F: FnOnce(&mut Graph) -> R,
+    {
+        let mut graph = self.graph.lock().unwrap();
+        let graph = graph.as_mut().ok_or(GraphConstructionError::AlreadyBuilt)?;
+        Ok(f(graph))
     }
 
     pub fn connect(
@@ -55,57 +96,130 @@ impl GraphBuilder {
         target: NodeIndex,
         target_input: u32,
     ) -> &Self {
-        self.graph
-            .lock()
-            .unwrap()
-            .as_mut()
+        self.try_connect(source, source_output, target, target_input)
             .unwrap()
-            .connect(source, source_output, target, target_input)
-            .unwrap();
-        self
     }
 
-    pub fn add_input(&self) -> Node<'_> {
-        let index = self.graph.lock().unwrap().as_mut().unwrap().add_input();
-        Node {
+    /// Connects the given output of the `source` node to the given input of the `target` node, returning an error if either port doesn't exist.
+    pub fn try_connect(
+        &self,
+        source: NodeIndex,
+        source_output: u32,
+        target: NodeIndex,
+        target_input: u32,
+    ) -> GraphConstructionResult<&Self> {
+        self.try_with_graph_mut(|graph| {
+            graph.connect(source, source_output, target, target_input)
+        })??;
+        Ok(self)
+    }
+
+    fn try_add_node<F>(&self, f: F) -> GraphConstructionResult<Node<'_>>
+    where
+        F: FnOnce(&mut Graph) -> NodeIndex,
+    {
+        let index = self.try_with_graph_mut(f)?;
+        Ok(Node {
             graph_builder: self,
             node_id: index,
-        }
+        })
+    }
+
+    pub fn add_input(&self) -> Node<'_> {
+        self.try_add_input().unwrap()
+    }
+
+    pub fn try_add_input(&self) -> GraphConstructionResult<Node<'_>> {
+        self.try_add_node(Graph::add_input)
     }
 
     pub fn add_output(&self) -> Node<'_> {
-        let index = self.graph.lock().unwrap().as_mut().unwrap().add_output();
-        Node {
-            graph_builder: self,
-            node_id: index,
-        }
+        self.try_add_output().unwrap()
+    }
+
+    pub fn try_add_output(&self) -> GraphConstructionResult<Node<'_>> {
+        self.try_add_node(Graph::add_output)
     }
 
     pub fn add_constant(&self, value: f64) -> Node<'_> {
-        let index = self
-            .graph
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .add_processor(ConstantProc::new(value));
-        Node {
-            graph_builder: self,
-            node_id: index,
-        }
+        self.try_add_constant(value).unwrap()
+    }
+
+    pub fn try_add_constant(&self, value: f64) -> GraphConstructionResult<Node<'_>> {
+        self.try_add(ConstantProc::new(value))
     }
 
     pub fn add(&self, processor: impl Process) -> Node<'_> {
-        let index = self
-            .graph
-            .lock()
-            .unwrap()
-            .as_mut()
-            .unwrap()
-            .add_processor(processor);
-        Node {
-            graph_builder: self,
-            node_id: index,
-        }
+        self.try_add(processor).unwrap()
+    }
+
+    pub fn try_add(&self, processor: impl Process) -> GraphConstructionResult<Node<'_>> {
+        self.try_add_node(|graph| graph.add_processor(processor))
+    }
+
+    pub fn add_processor_object(&self, processor: Processor) -> Node<'_> {
+        self.try_add_processor_object(processor).unwrap()
+    }
+
+    pub fn try_add_processor_object(
+        &self,
+        processor: Processor,
+    ) -> GraphConstructionResult<Node<'_>> {
+        self.try_add_node(|graph| graph.add_processor_object(processor))
+    }
+
+    /// Adds a [`SubGraph`] node running the given graph, e.g. a [`Graph`] built by another [`GraphBuilder`].
+    ///
+    /// The returned node has one input per input of the inner graph and one output per output, in order.
+    pub fn add_subgraph(&self, subgraph: impl Into<SubGraph>) -> Node<'_> {
+        self.try_add_subgraph(subgraph).unwrap()
+    }
+
+    pub fn try_add_subgraph(
+        &self,
+        subgraph: impl Into<SubGraph>,
+    ) -> GraphConstructionResult<Node<'_>> {
+        self.try_add(subgraph.into())
+    }
+
+    /// Adds an [`FnProc`] node calling the given closure with whole blocks of input and output buffers.
+    ///
+    /// `inputs` and `outputs` are either numbers of ports or lists of port names.
+    pub fn add_fn<F>(&self, inputs: impl PortNames, outputs: impl PortNames, f: F) -> Node<'_>
+    where
+        F: FnMut(&FnContext, &[Buffer], &mut [Buffer]) + Clone + Send + Sync + 'static,
+    {
+        self.try_add_fn(inputs, outputs, f).unwrap()
+    }
+
+    pub fn try_add_fn<F>(
+        &self,
+        inputs: impl PortNames,
+        outputs: impl PortNames,
+        f: F,
+    ) -> GraphConstructionResult<Node<'_>>
+    where
+        F: FnMut(&FnContext, &[Buffer], &mut [Buffer]) + Clone + Send + Sync + 'static,
+    {
+        self.try_add(FnProc::new(inputs, outputs, f))
+    }
+
+    /// Adds a [`MapProc`] node computing each output sample from one sample of each input with the given closure, e.g. `|a, b| a * b`.
+    ///
+    /// The node has one input per closure argument.
+    pub fn map_samples<F, Args>(&self, f: F) -> Node<'_>
+    where
+        F: SampleFn<Args>,
+        Args: 'static,
+    {
+        self.try_map_samples(f).unwrap()
+    }
+
+    pub fn try_map_samples<F, Args>(&self, f: F) -> GraphConstructionResult<Node<'_>>
+    where
+        F: SampleFn<Args>,
+        Args: 'static,
+    {
+        self.try_add(MapProc::new(f))
     }
 }
@@ -1,2 +1,4 @@
+pub mod bus;
 pub mod graph_builder;
 pub mod node_builder;
+pub mod typed_node;
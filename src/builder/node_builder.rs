@@ -1,6 +1,9 @@
+use petgraph::Direction;
+
 use super::graph_builder::GraphBuilder;
 use crate::builtins::*;
-use crate::graph::NodeIndex;
+use crate::graph::{GraphConstructionError, GraphConstructionResult, NodeIndex, PortDirection};
+use crate::processor::{BypassHandle, Process, Rate};
 
 #[derive(Clone, Copy)]
 pub struct Node<'a> {
@@ -44,6 +47,30 @@ impl<'a> Node<'a> {
         self
     }
 
+    /// Returns this node if it has exactly one output, or an error naming the operation `op` that needed it otherwise.
+    pub fn try_single_output(self, op: &str) -> GraphConstructionResult<Self> {
+        let (num_outputs, kind) = self.graph().try_with_graph(|graph| {
+            graph
+                .digraph()
+                .node_weight(self.id())
+                .map(|node| (node.outputs().len(), node.name().to_owned()))
+                .ok_or(GraphConstructionError::UnknownNode(self.id()))
+        })??;
+        match num_outputs {
+            1 => Ok(self),
+            0 => Err(GraphConstructionError::PortOutOfRange {
+                node: self.id(),
+                direction: PortDirection::Output,
+                index: 0,
+                num_ports: 0,
+            }),
+            _ => Err(GraphConstructionError::NodeHasMultipleOutputs {
+                op: op.to_owned(),
+                kind,
+            }),
+        }
+    }
+
     #[inline]
     pub fn connect_input(
         self,
@@ -51,12 +78,23 @@ impl<'a> Node<'a> {
         source_output: impl IntoOutputIdx,
         input: impl IntoInputIdx,
     ) -> Self {
+        self.try_connect_input(source, source_output, input)
+            .unwrap()
+    }
+
+    /// Connects the given output of `source` to the given input of this node, returning an error if either port doesn't exist.
+    pub fn try_connect_input(
+        self,
+        source: impl IntoNode<'a>,
+        source_output: impl IntoOutputIdx,
+        input: impl IntoInputIdx,
+    ) -> GraphConstructionResult<Self> {
         let source = source.into_node(self.graph_builder);
-        let source_output = source_output.into_output_idx(source);
-        let target_input = input.into_input_idx(self);
+        let source_output = source_output.try_into_output_idx(source)?;
+        let target_input = input.try_into_input_idx(self)?;
         self.graph_builder
-            .connect(source.id(), source_output, self.id(), target_input);
-        self
+            .try_connect(source.id(), source_output, self.id(), target_input)?;
+        Ok(self)
     }
 
     #[inline]
@@ -66,12 +104,133 @@ impl<'a> Node<'a> {
         target: impl IntoNode<'a>,
         target_input: impl IntoInputIdx,
     ) -> Self {
+        self.try_connect_output(output, target, target_input)
+            .unwrap()
+    }
+
+    /// Connects the given output of this node to the given input of `target`, returning an error if either port doesn't exist.
+    pub fn try_connect_output(
+        self,
+        output: impl IntoOutputIdx,
+        target: impl IntoNode<'a>,
+        target_input: impl IntoInputIdx,
+    ) -> GraphConstructionResult<Self> {
         let target = target.into_node(self.graph_builder);
-        let output_index = output.into_output_idx(self);
-        let target_input = target_input.into_input_idx(target);
+        let output_index = output.try_into_output_idx(self)?;
+        let target_input = target_input.try_into_input_idx(target)?;
         self.graph_builder
-            .connect(self.id(), output_index, target.id(), target_input);
-        self
+            .try_connect(self.id(), output_index, target.id(), target_input)?;
+        Ok(self)
+    }
+
+    /// Sets the [`Rate`] this node runs at, e.g. [`Rate::Divided(16)`](Rate::Divided) for a modulation source, or a whole subgraph.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is a graph input or output.
+    #[inline]
+    pub fn with_rate(self, rate: Rate) -> Self {
+        self.try_with_rate(rate).unwrap()
+    }
+
+    /// Sets the [`Rate`] this node runs at, returning an error if this node is a graph input or output.
+    pub fn try_with_rate(self, rate: Rate) -> GraphConstructionResult<Self> {
+        self.graph_builder
+            .try_with_graph_mut(|graph| graph.set_rate(self.id(), rate))??;
+        Ok(self)
+    }
+
+    /// Returns a [`BypassHandle`] for bypassing or muting this node while the graph is running.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node is a graph input or output.
+    #[inline]
+    pub fn bypass_handle(self) -> BypassHandle {
+        self.try_bypass_handle().unwrap()
+    }
+
+    /// Returns a [`BypassHandle`] for bypassing or muting this node, or an error if this node is a graph input or output.
+    pub fn try_bypass_handle(self) -> GraphConstructionResult<BypassHandle> {
+        self.graph_builder
+            .try_with_graph(|graph| graph.bypass_handle(self.id()))?
+    }
+
+    /// Makes this node run once per block, with its outputs interpolated over the block where they feed audio-rate inputs.
+    ///
+    /// This is shorthand for [`Node::with_rate(Rate::Block)`](Node::with_rate).
+    #[inline]
+    pub fn control_rate(self) -> Self {
+        self.with_rate(Rate::Block)
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Adds a node running the given single-input processor, with this node connected to its input.
+    ///
+    /// Returns an error naming the operation `op` if this node doesn't have exactly one output.
+    pub fn try_unary(self, op: &str, processor: impl Process) -> GraphConstructionResult<Node<'a>> {
+        self.try_single_output(op)?;
+
+        let node = self.graph().try_add(processor)?;
+        node.try_connect_input(self, 0, 0)
+    }
+
+    /// Adds a node running the given two-input processor, with this node and `other` connected to its inputs.
+    ///
+    /// Returns an error naming the operation `op` if either node doesn't have exactly one output.
+    /// If connecting either input fails, the added node is removed again before returning the error.
+    pub fn try_binary(
+        self,
+        op: &str,
+        other: impl IntoNode<'a>,
+        processor: impl Process,
+    ) -> GraphConstructionResult<Node<'a>> {
+        let other = other.into_node(self.graph());
+        self.try_single_output(op)?;
+        other.try_single_output(op)?;
+
+        let node = self.graph().try_add(processor)?;
+        let connected = node
+            .try_connect_input(self, 0, 0)
+            .and_then(|node| node.try_connect_input(other, 0, 1));
+        if connected.is_err() {
+            // anything else feeding the new node is a converter inserted while connecting it
+            self.graph().try_with_graph_mut(|graph| {
+                let converters = graph
+                    .digraph()
+                    .neighbors_directed(node.id(), Direction::Incoming)
+                    .filter(|&source| source != self.id() && source != other.id())
+                    .collect::<Vec<_>>();
+                for source in converters.into_iter().chain([node.id()]) {
+                    graph.remove_processor(source)?;
+                }
+                Ok::<_, GraphConstructionError>(())
+            })??;
+        }
+        connected
+    }
+}
+
+impl<'a> Node<'a> {
+    /// Adds a node applying the given closure to each sample of this node's output.
+    ///
+    /// # Panics
+    ///
+    /// Panics if this node doesn't have exactly one output.
+    pub fn map<F>(self, f: F) -> Node<'a>
+    where
+        F: FnMut(f64) -> f64 + Clone + Send + Sync + 'static,
+    {
+        self.try_map(f).unwrap()
+    }
+
+    /// Adds a node applying the given closure to each sample of this node's output, or returns an error if this node doesn't have exactly one output.
+    pub fn try_map<F>(self, f: F) -> GraphConstructionResult<Node<'a>>
+    where
+        F: FnMut(f64) -> f64 + Clone + Send + Sync + 'static,
+    {
+        self.try_unary("map", func::MapProc::new(f))
     }
 }
 
@@ -80,6 +239,7 @@ mod sealed {
     pub trait Sealed {}
     impl Sealed for crate::graph::NodeIndex {}
     impl<'a> Sealed for super::Node<'a> {}
+    impl<'a, P> Sealed for crate::builder::typed_node::TypedNode<'a, P> {}
     impl Sealed for f64 {}
     impl Sealed for u32 {}
     impl Sealed for &str {}
@@ -111,50 +271,82 @@ impl<'a> IntoNode<'a> for f64 {
 }
 
 pub trait IntoInputIdx: sealed::Sealed {
-    fn into_input_idx(self, node: Node) -> u32;
+    /// Returns the input index this refers to on the given node, or an error if the node has no such input.
+    fn try_into_input_idx(self, node: Node) -> GraphConstructionResult<u32>;
+
+    #[track_caller]
+    fn into_input_idx(self, node: Node) -> u32
+    where
+        Self: Sized,
+    {
+        self.try_into_input_idx(node).unwrap()
+    }
 }
 
 impl IntoInputIdx for u32 {
     #[inline]
-    fn into_input_idx(self, _node: Node) -> u32 {
-        self
+    fn try_into_input_idx(self, _node: Node) -> GraphConstructionResult<u32> {
+        // the index itself is range-checked when connecting
+        Ok(self)
     }
 }
 
 impl IntoInputIdx for &str {
     #[inline]
-    fn into_input_idx(self, node: Node) -> u32 {
-        node.graph().with_graph(|graph| {
-            graph.digraph()[node.id()]
-                .input_spec()
-                .iter()
-                .position(|s| s.name == self)
-                .unwrap_or_else(|| panic!("no input with name {self}")) as u32
-        })
+    fn try_into_input_idx(self, node: Node) -> GraphConstructionResult<u32> {
+        node.graph()
+            .try_with_graph(|graph| {
+                graph
+                    .digraph()
+                    .node_weight(node.id())
+                    .ok_or(GraphConstructionError::UnknownNode(node.id()))
+                    .map(|weight| weight.input_spec().iter().position(|s| s.name == self))
+            })??
+            .map(|index| index as u32)
+            .ok_or_else(|| GraphConstructionError::UnknownPort {
+                node: node.id(),
+                name: self.to_owned(),
+            })
     }
 }
 
 pub trait IntoOutputIdx: sealed::Sealed {
-    fn into_output_idx(self, node: Node) -> u32;
+    /// Returns the output index this refers to on the given node, or an error if the node has no such output.
+    fn try_into_output_idx(self, node: Node) -> GraphConstructionResult<u32>;
+
+    #[track_caller]
+    fn into_output_idx(self, node: Node) -> u32
+    where
+        Self: Sized,
+    {
+        self.try_into_output_idx(node).unwrap()
+    }
 }
 
 impl IntoOutputIdx for u32 {
     #[inline]
-    fn into_output_idx(self, _node: Node) -> u32 {
-        self
+    fn try_into_output_idx(self, _node: Node) -> GraphConstructionResult<u32> {
+        // the index itself is range-checked when connecting
+        Ok(self)
     }
 }
 
 impl IntoOutputIdx for &str {
     #[inline]
-    fn into_output_idx(self, node: Node) -> u32 {
-        node.graph().with_graph(|graph| {
-            graph.digraph()[node.id()]
-                .output_spec()
-                .iter()
-                .position(|s| s.name == self)
-                .unwrap_or_else(|| panic!("no output with name {self}")) as u32
-        })
+    fn try_into_output_idx(self, node: Node) -> GraphConstructionResult<u32> {
+        node.graph()
+            .try_with_graph(|graph| {
+                graph
+                    .digraph()
+                    .node_weight(node.id())
+                    .ok_or(GraphConstructionError::UnknownNode(node.id()))
+                    .map(|weight| weight.output_spec().iter().position(|s| s.name == self))
+            })??
+            .map(|index| index as u32)
+            .ok_or_else(|| GraphConstructionError::UnknownPort {
+                node: node.id(),
+                name: self.to_owned(),
+            })
     }
 }
 
@@ -163,16 +355,8 @@ macro_rules! impl_binary_node_ops {
         impl<'a> Node<'a> {
             #[allow(clippy::should_implement_trait)]
             pub fn $name(self, other: impl IntoNode<'a>) -> Node<'a> {
-                let other = other.into_node(self.graph());
-                self.assert_single_output();
-                other.assert_single_output();
-
-                let processor = <$proc>::default();
-                let node = self.graph().add(processor);
-                node.connect_input(self, 0, 0);
-                node.connect_input(other, 0, 1);
-
-                node
+                self.try_binary(stringify!($name), other, <$proc>::default())
+                    .unwrap()
             }
         }
     };
@@ -180,16 +364,8 @@ macro_rules! impl_binary_node_ops {
         impl<'a> Node<'a> {
             #[allow(clippy::should_implement_trait)]
             pub fn $name(self, other: impl IntoNode<'a>) -> Node<'a> {
-                let other = other.into_node(self.graph());
-                self.assert_single_output();
-                other.assert_single_output();
-
-                let processor = <$proc>::default();
-                let node = self.graph().add(processor);
-                node.connect_input(self, 0, 0);
-                node.connect_input(other, 0, 1);
-
-                node
+                self.try_binary(stringify!($name), other, <$proc>::default())
+                    .unwrap()
             }
         }
 
@@ -244,13 +420,8 @@ macro_rules! impl_unary_node_ops {
         impl<'a> Node<'a> {
             #[allow(clippy::should_implement_trait)]
             pub fn $name(self) -> Node<'a> {
-                self.assert_single_output();
-
-                let processor = <$proc>::default();
-                let node = self.graph().add(processor);
-                node.connect_input(self, 0, 0);
-
-                node
+                self.try_unary(stringify!($name), <$proc>::default())
+                    .unwrap()
             }
         }
     };
@@ -341,3 +512,53 @@ impl_unary_node_ops!(
     math::FractProc,
     "Returns the fractional part of the input signal."
 );
+impl_unary_node_ops!(
+    trunc,
+    math::TruncProc,
+    "Rounds the input signal towards zero."
+);
+impl_unary_node_ops!(
+    sinh,
+    math::SinhProc,
+    "Calculates the hyperbolic sine of the input signal."
+);
+impl_unary_node_ops!(
+    cosh,
+    math::CoshProc,
+    "Calculates the hyperbolic cosine of the input signal."
+);
+impl_unary_node_ops!(
+    tanh,
+    math::TanhProc,
+    "Calculates the hyperbolic tangent of the input signal."
+);
+impl_unary_node_ops!(
+    exp,
+    math::ExpProc,
+    "Calculates the exponential of the input signal."
+);
+impl_unary_node_ops!(
+    exp2,
+    math::Exp2Proc,
+    "Calculates 2 raised to the power of the input signal."
+);
+impl_unary_node_ops!(
+    exp_m1,
+    math::ExpM1Proc,
+    "Calculates the exponential of the input signal minus 1."
+);
+impl_unary_node_ops!(
+    ln,
+    math::LnProc,
+    "Calculates the natural logarithm of the input signal."
+);
+impl_unary_node_ops!(
+    log2,
+    math::Log2Proc,
+    "Calculates the base-2 logarithm of the input signal."
+);
+impl_unary_node_ops!(
+    log10,
+    math::Log10Proc,
+    "Calculates the base-10 logarithm of the input signal."
+);
@@ -0,0 +1,351 @@
+//! Node handles that know their processor type, so port mistakes are caught at compile time.
+//!
+//! A [`TypedNode<P>`] is created with [`GraphBuilder::add_typed`] for processors implementing [`StaticPorts`], which is done by
+//! [`#[derive(Process)]`](derive@crate::processor::Process) and for the builtin processors with a fixed number of ports.
+//! Ports are referred to by associated constants of the processor type, e.g. [`SineOscillator::FREQUENCY`](crate::builtins::oscillators::SineOscillator::FREQUENCY),
+//! which only exist for ports that do, and can only be used with nodes of that type.
+//! The math operators are only available on nodes with a single output.
+//!
+//! ```
+//! use daprs::prelude::*;
+//!
+//! let graph = GraphBuilder::new();
+//! let out = graph.add_output();
+//! let sine = graph.add_typed(SineOscillator::default());
+//! sine.connect_input(SineOscillator::FREQUENCY, 440.0);
+//! let quiet = sine * 0.5;
+//! quiet.untyped().connect_output(0, out, 0);
+//! ```
+//!
+//! Referring to a port the processor doesn't have fails to compile:
+//!
+//! ```compile_fail
+//! use daprs::prelude::*;
+//!
+//! let graph = GraphBuilder::new();
+//! let sine = graph.add_typed(SineOscillator::default());
+//! sine.connect_input(SineOscillator::AMPLITUDE, 0.5);
+//! ```
+//!
+//! As does using a node with several outputs as a single signal, without picking one of them with [`TypedNode::output`]:
+//!
+//! ```compile_fail
+//! use daprs::prelude::*;
+//!
+//! #[derive(Clone, Default, Process)]
+//! struct Split {
+//!     #[input]
+//!     input: f64,
+//!     #[output]
+//!     low: f64,
+//!     #[output]
+//!     high: f64,
+//! }
+//!
+//! impl SampleProcess for Split {
+//!     fn tick(&mut self) {
+//!         self.low = self.input.min(0.0);
+//!         self.high = self.input.max(0.0);
+//!     }
+//! }
+//!
+//! let graph = GraphBuilder::new();
+//! let split = graph.add_typed(Split::default());
+//! let abs = graph.add_typed(AbsProc);
+//! abs.connect_input(AbsProc::IN, split);
+//! ```
+
+use std::marker::PhantomData;
+
+use super::{
+    graph_builder::GraphBuilder,
+    node_builder::{IntoInputIdx, IntoNode, Node},
+};
+use crate::{builtins::math, graph::NodeIndex, processor::Process};
+
+/// A [`Process`] with a number of inputs and outputs that is known at compile time.
+pub trait StaticPorts: Process {
+    const NUM_INPUTS: usize;
+    const NUM_OUTPUTS: usize;
+}
+
+/// A [`StaticPorts`] processor with exactly one output, which can be used as a signal in math operations on [`TypedNode`]s.
+pub trait SingleOutput: StaticPorts {}
+
+/// The input with index `I` of processors of type `P`.
+pub struct InputPort<P, const I: u32>(PhantomData<fn() -> P>);
+
+impl<P: StaticPorts, const I: u32> InputPort<P, I> {
+    /// Refers to input `I` of `P`, failing to compile if `P` doesn't have that many inputs.
+    pub const fn new() -> Self {
+        const {
+            assert!((I as usize) < P::NUM_INPUTS, "input index out of range");
+        }
+        Self(PhantomData)
+    }
+}
+
+impl<P: StaticPorts, const I: u32> Default for InputPort<P, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, const I: u32> Clone for InputPort<P, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P, const I: u32> Copy for InputPort<P, I> {}
+
+/// The output with index `I` of processors of type `P`.
+pub struct OutputPort<P, const I: u32>(PhantomData<fn() -> P>);
+
+impl<P: StaticPorts, const I: u32> OutputPort<P, I> {
+    /// Refers to output `I` of `P`, failing to compile if `P` doesn't have that many outputs.
+    pub const fn new() -> Self {
+        const {
+            assert!((I as usize) < P::NUM_OUTPUTS, "output index out of range");
+        }
+        Self(PhantomData)
+    }
+}
+
+impl<P: StaticPorts, const I: u32> Default for OutputPort<P, I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P, const I: u32> Clone for OutputPort<P, I> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P, const I: u32> Copy for OutputPort<P, I> {}
+
+/// A [`Node`] running a processor of type `P`.
+pub struct TypedNode<'a, P> {
+    node: Node<'a>,
+    _processor: PhantomData<fn() -> P>,
+}
+
+impl<'a, P> Clone for TypedNode<'a, P> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, P> Copy for TypedNode<'a, P> {}
+
+impl<'a, P> std::fmt::Debug for TypedNode<'a, P> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(&self.node, f)
+    }
+}
+
+impl<'a, P: StaticPorts> TypedNode<'a, P> {
+    fn new(node: Node<'a>) -> Self {
+        Self {
+            node,
+            _processor: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn id(self) -> NodeIndex {
+        self.node.id()
+    }
+
+    #[inline]
+    pub fn graph(self) -> &'a GraphBuilder {
+        self.node.graph()
+    }
+
+    /// Returns the untyped [`Node`], e.g. for connecting to graph outputs.
+    #[inline]
+    pub fn untyped(self) -> Node<'a> {
+        self.node
+    }
+
+    /// Returns the given output of this node, for connecting it to a typed input.
+    #[inline]
+    pub fn output<const I: u32>(self, _port: OutputPort<P, I>) -> TypedOutput<'a> {
+        TypedOutput {
+            node: self.node,
+            index: I,
+        }
+    }
+
+    /// Connects the given source to the given input of this node.
+    pub fn connect_input<const I: u32>(
+        self,
+        _port: InputPort<P, I>,
+        source: impl TypedSource<'a>,
+    ) -> Self {
+        let source = source.into_output(self.graph());
+        self.graph()
+            .connect(source.node.id(), source.index, self.id(), I);
+        self
+    }
+
+    /// Connects the given output of this node to an input of an untyped node, such as a graph output.
+    ///
+    /// The input is checked when connecting, as for [`Node::connect_output`].
+    pub fn connect_output<const O: u32>(
+        self,
+        _port: OutputPort<P, O>,
+        target: impl IntoNode<'a>,
+        target_input: impl IntoInputIdx,
+    ) -> Self {
+        self.node.connect_output(O, target, target_input);
+        self
+    }
+}
+
+impl<'a, P> From<TypedNode<'a, P>> for Node<'a> {
+    fn from(node: TypedNode<'a, P>) -> Self {
+        node.node
+    }
+}
+
+impl<'a, P> IntoNode<'a> for TypedNode<'a, P> {
+    fn into_node(self, _graph_builder: &'a GraphBuilder) -> Node<'a> {
+        self.node
+    }
+}
+
+/// A specific output of a [`TypedNode`], as returned by [`TypedNode::output`].
+#[derive(Clone, Copy, Debug)]
+pub struct TypedOutput<'a> {
+    node: Node<'a>,
+    index: u32,
+}
+
+/// Something that can be connected to a typed input: a single-output [`TypedNode`], a [`TypedOutput`], or a constant.
+pub trait TypedSource<'a> {
+    fn into_output(self, graph_builder: &'a GraphBuilder) -> TypedOutput<'a>;
+}
+
+impl<'a> TypedSource<'a> for TypedOutput<'a> {
+    fn into_output(self, _graph_builder: &'a GraphBuilder) -> TypedOutput<'a> {
+        self
+    }
+}
+
+impl<'a, P: SingleOutput> TypedSource<'a> for TypedNode<'a, P> {
+    fn into_output(self, _graph_builder: &'a GraphBuilder) -> TypedOutput<'a> {
+        TypedOutput {
+            node: self.node,
+            index: 0,
+        }
+    }
+}
+
+impl<'a> TypedSource<'a> for f64 {
+    fn into_output(self, graph_builder: &'a GraphBuilder) -> TypedOutput<'a> {
+        graph_builder
+            .add_typed(math::ConstantProc::new(self))
+            .into_output(graph_builder)
+    }
+}
+
+impl GraphBuilder {
+    /// Adds a node running the given processor, returning a [`TypedNode`] whose ports are checked at compile time.
+    pub fn add_typed<P: StaticPorts>(&self, processor: P) -> TypedNode<'_, P> {
+        TypedNode::new(self.add(processor))
+    }
+}
+
+macro_rules! impl_binary_typed_ops {
+    ($($name:ident: $proc:ident $(: $std_op:ident)?),* $(,)?) => {
+        impl<'a, P: SingleOutput> TypedNode<'a, P> {
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $name(self, other: impl TypedSource<'a>) -> TypedNode<'a, math::$proc> {
+                    self.graph()
+                        .add_typed(math::$proc)
+                        .connect_input(math::$proc::A, self)
+                        .connect_input(math::$proc::B, other)
+                }
+            )*
+        }
+
+        $($(
+            impl<'a, P: SingleOutput, T: TypedSource<'a>> std::ops::$std_op<T> for TypedNode<'a, P> {
+                type Output = TypedNode<'a, math::$proc>;
+
+                fn $name(self, other: T) -> Self::Output {
+                    TypedNode::$name(self, other)
+                }
+            }
+        )?)*
+    };
+}
+
+impl_binary_typed_ops!(
+    add: AddProc: Add,
+    sub: SubProc: Sub,
+    mul: MulProc: Mul,
+    div: DivProc: Div,
+    rem: RemProc: Rem,
+    powf: PowfProc,
+    atan2: Atan2Proc,
+    hypot: HypotProc,
+    max: MaxProc,
+    min: MinProc,
+);
+
+macro_rules! impl_unary_typed_ops {
+    ($($name:ident: $proc:ident),* $(,)?) => {
+        impl<'a, P: SingleOutput> TypedNode<'a, P> {
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $name(self) -> TypedNode<'a, math::$proc> {
+                    self.graph()
+                        .add_typed(math::$proc)
+                        .connect_input(math::$proc::IN, self)
+                }
+            )*
+        }
+    };
+}
+
+impl_unary_typed_ops!(
+    neg: NegProc,
+    abs: AbsProc,
+    sqrt: SqrtProc,
+    cbrt: CbrtProc,
+    ceil: CeilProc,
+    floor: FloorProc,
+    round: RoundProc,
+    trunc: TruncProc,
+    fract: FractProc,
+    recip: RecipProc,
+    signum: SignumProc,
+    sin: SinProc,
+    cos: CosProc,
+    tan: TanProc,
+    asin: AsinProc,
+    acos: AcosProc,
+    atan: AtanProc,
+    sinh: SinhProc,
+    cosh: CoshProc,
+    tanh: TanhProc,
+    exp: ExpProc,
+    exp2: Exp2Proc,
+    exp_m1: ExpM1Proc,
+    ln: LnProc,
+    log2: Log2Proc,
+    log10: Log10Proc,
+);
+
+impl<'a, P: SingleOutput> std::ops::Neg for TypedNode<'a, P> {
+    type Output = TypedNode<'a, math::NegProc>;
+
+    fn neg(self) -> Self::Output {
+        TypedNode::neg(self)
+    }
+}
@@ -0,0 +1,193 @@
+//! Conversions between [`SignalKind`]s, inserted automatically when connecting ports of different kinds.
+
+use crate::prelude::*;
+
+/// How a signal is converted when connected to an input of a different [`SignalKind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Coercion {
+    /// The signal is used as-is.
+    Direct,
+    /// The signal is turned into a gate that is on while it's above zero, with a [`ThresholdProc`].
+    Threshold,
+    /// The signal is turned into a trigger at each rise above zero, with an [`EdgeDetectProc`].
+    EdgeDetect,
+    /// The signal is rounded to the nearest whole number, with a [`ToIndexProc`].
+    Round,
+}
+
+impl Coercion {
+    /// Returns how a `source` signal is converted for an input of kind `target`, or `None` if it can't be.
+    ///
+    /// Anything can be used as an audio or control signal. Triggers can't be turned into gates, since they don't say how long the gate should stay on.
+    pub fn between(source: SignalKind, target: SignalKind) -> Option<Self> {
+        use SignalKind::*;
+
+        match (source, target) {
+            (_, Audio | Control) => Some(Self::Direct),
+            (Gate, Gate) | (Trigger, Trigger) => Some(Self::Direct),
+            (Trigger, Gate) => None,
+            (Audio | Control | Index, Gate) => Some(Self::Threshold),
+            (Audio | Control | Index | Gate, Trigger) => Some(Self::EdgeDetect),
+            (Index | Gate | Trigger, Index) => Some(Self::Direct),
+            (Audio | Control, Index) => Some(Self::Round),
+        }
+    }
+
+    /// Returns the processor performing the conversion, or `None` for [`Coercion::Direct`].
+    pub fn processor(self) -> Option<Processor> {
+        match self {
+            Self::Direct => None,
+            Self::Threshold => Some(Processor::new(ThresholdProc)),
+            Self::EdgeDetect => Some(Processor::new(EdgeDetectProc::default())),
+            Self::Round => Some(Processor::new(ToIndexProc)),
+        }
+    }
+}
+
+/// A processor that turns a signal into a gate that is on (`1.0`) while the signal is above zero.
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `0` | `in` | `0.0` | The input signal. |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The gate signal. |
+#[derive(Clone, Debug, Default)]
+pub struct ThresholdProc;
+
+impl ThresholdProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::ThresholdProc");
+}
+
+impl Process for ThresholdProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::new("out", 0.0, 1.0, 0.0).with_kind(SignalKind::Gate)]
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (out, input) in itertools::izip!(&mut outputs[0], &inputs[0]) {
+            *out = if **input > 0.0 { 1.0 } else { 0.0 }.into();
+        }
+    }
+}
+
+/// A processor that outputs a single-sample trigger (`1.0`) each time its input rises above zero.
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `0` | `in` | `0.0` | The input signal. |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The trigger signal. |
+#[derive(Clone, Debug, Default)]
+pub struct EdgeDetectProc {
+    was_high: bool,
+}
+
+impl EdgeDetectProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::EdgeDetectProc");
+}
+
+impl Process for EdgeDetectProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::new("out", 0.0, 1.0, 0.0).with_kind(SignalKind::Trigger)]
+    }
+
+    fn reset(&mut self) {
+        self.was_high = false;
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        Some(Param::Map(Params::from([(
+            "was_high".to_owned(),
+            Param::from(self.was_high),
+        )])))
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("state", "expected a map"))?;
+        self.was_high = state
+            .get_bool("was_high")?
+            .ok_or_else(|| ParamError::missing("was_high"))?;
+        Ok(())
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (out, input) in itertools::izip!(&mut outputs[0], &inputs[0]) {
+            let is_high = **input > 0.0;
+            *out = if is_high && !self.was_high { 1.0 } else { 0.0 }.into();
+            self.was_high = is_high;
+        }
+    }
+}
+
+/// A processor that rounds its input to the nearest whole number, for use as an index.
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `0` | `in` | `0.0` | The input signal. |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The rounded input signal. |
+#[derive(Clone, Debug, Default)]
+pub struct ToIndexProc;
+
+impl ToIndexProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::ToIndexProc");
+}
+
+impl Process for ToIndexProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0).with_kind(SignalKind::Index)]
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (out, input) in itertools::izip!(&mut outputs[0], &inputs[0]) {
+            *out = input.round().into();
+        }
+    }
+}
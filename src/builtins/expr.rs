@@ -0,0 +1,667 @@
+//! Per-sample math expressions evaluated by a single node.
+
+use std::fmt;
+
+use crate::{
+    dsl::{
+        self,
+        parser::{self, ExprKind},
+        DslError, DslResult,
+    },
+    prelude::*,
+    registry::{ParamError, ParamsExt},
+};
+
+use super::math;
+
+macro_rules! math_ops {
+    (
+        $(#[$unary_meta:meta])* unary: { $($unary:ident => $unary_proc:ident, $unary_method:ident, $unary_name:literal;)* }
+        $(#[$binary_meta:meta])* binary: { $($binary:ident => $binary_proc:ident, $binary_method:ident, $binary_name:literal;)* }
+    ) => {
+        $(#[$unary_meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum UnaryOp {
+            $($unary,)*
+        }
+
+        impl UnaryOp {
+            /// Applies the operation to the given value.
+            #[inline]
+            pub fn apply(self, x: f64) -> f64 {
+                match self {
+                    $(Self::$unary => f64::$unary_method(x),)*
+                }
+            }
+
+            /// Returns the name of the function performing this operation in expressions, e.g. `sin`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$unary => $unary_name,)*
+                }
+            }
+
+            /// Returns the operation performed by the function with the given name in expressions, if any.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($unary_name => Some(Self::$unary),)*
+                    _ => None,
+                }
+            }
+
+            /// Returns the operation performed by the [`builtins::math`](crate::builtins::math) processor with the given type name, if any.
+            pub fn from_processor_name(name: &str) -> Option<Self> {
+                $(
+                    if name == math::$unary_proc::NAME {
+                        return Some(Self::$unary);
+                    }
+                )*
+                None
+            }
+        }
+
+        $(#[$binary_meta])*
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum BinaryOp {
+            $($binary,)*
+        }
+
+        impl BinaryOp {
+            /// Applies the operation to the given values.
+            #[inline]
+            pub fn apply(self, a: f64, b: f64) -> f64 {
+                match self {
+                    $(Self::$binary => f64::$binary_method(a, b),)*
+                }
+            }
+
+            /// Returns the name of the function performing this operation in expressions, e.g. `max`.
+            pub fn name(self) -> &'static str {
+                match self {
+                    $(Self::$binary => $binary_name,)*
+                }
+            }
+
+            /// Returns the operation performed by the function with the given name in expressions, if any.
+            pub fn from_name(name: &str) -> Option<Self> {
+                match name {
+                    $($binary_name => Some(Self::$binary),)*
+                    _ => None,
+                }
+            }
+
+            /// Returns the operation performed by the [`builtins::math`](crate::builtins::math) processor with the given type name, if any.
+            pub fn from_processor_name(name: &str) -> Option<Self> {
+                $(
+                    if name == math::$binary_proc::NAME {
+                        return Some(Self::$binary);
+                    }
+                )*
+                None
+            }
+        }
+
+        impl Expr {
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $unary_method(self) -> Self {
+                    Self::Unary(UnaryOp::$unary, Box::new(self))
+                }
+            )*
+            $(
+                #[allow(clippy::should_implement_trait)]
+                pub fn $binary_method(self, other: impl Into<Expr>) -> Self {
+                    Self::Binary(BinaryOp::$binary, Box::new(self), Box::new(other.into()))
+                }
+            )*
+        }
+    };
+}
+
+use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
+
+math_ops! {
+    /// A single-argument math operation, matching one of the unary processors in [`builtins::math`](crate::builtins::math).
+    unary: {
+        Neg => NegProc, neg, "neg";
+        Abs => AbsProc, abs, "abs";
+        Sqrt => SqrtProc, sqrt, "sqrt";
+        Cbrt => CbrtProc, cbrt, "cbrt";
+        Ceil => CeilProc, ceil, "ceil";
+        Floor => FloorProc, floor, "floor";
+        Round => RoundProc, round, "round";
+        Trunc => TruncProc, trunc, "trunc";
+        Fract => FractProc, fract, "fract";
+        Recip => RecipProc, recip, "recip";
+        Signum => SignumProc, signum, "signum";
+        Sin => SinProc, sin, "sin";
+        Cos => CosProc, cos, "cos";
+        Tan => TanProc, tan, "tan";
+        Asin => AsinProc, asin, "asin";
+        Acos => AcosProc, acos, "acos";
+        Atan => AtanProc, atan, "atan";
+        Sinh => SinhProc, sinh, "sinh";
+        Cosh => CoshProc, cosh, "cosh";
+        Tanh => TanhProc, tanh, "tanh";
+        Exp => ExpProc, exp, "exp";
+        Exp2 => Exp2Proc, exp2, "exp2";
+        ExpM1 => ExpM1Proc, exp_m1, "expm1";
+        Ln => LnProc, ln, "ln";
+        Log2 => Log2Proc, log2, "log2";
+        Log10 => Log10Proc, log10, "log10";
+    }
+    /// A two-argument math operation, matching one of the binary processors in [`builtins::math`](crate::builtins::math).
+    binary: {
+        Add => AddProc, add, "add";
+        Sub => SubProc, sub, "sub";
+        Mul => MulProc, mul, "mul";
+        Div => DivProc, div, "div";
+        Rem => RemProc, rem, "rem";
+        Powf => PowfProc, powf, "pow";
+        Atan2 => Atan2Proc, atan2, "atan2";
+        Hypot => HypotProc, hypot, "hypot";
+        Max => MaxProc, max, "max";
+        Min => MinProc, min, "min";
+    }
+}
+
+/// A tree of math operations over the inputs of an [`ExprProc`].
+///
+/// Expressions can be parsed from strings with [`ExprProc::parse`], or built up in code from [`Expr::input`]s and numbers
+/// with the usual operators and the methods named after each operation, e.g. `Expr::input(0).sin() * 0.5 + 0.5`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A constant value.
+    Const(f64),
+    /// The current sample of the input with the given index.
+    Input(usize),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Returns an expression for the current sample of the input with the given index.
+    pub fn input(index: usize) -> Self {
+        Self::Input(index)
+    }
+
+    /// Evaluates the expression with the given input values.
+    pub fn eval(&self, inputs: &[f64]) -> f64 {
+        match self {
+            Self::Const(value) => *value,
+            Self::Input(index) => inputs[*index],
+            Self::Unary(op, x) => op.apply(x.eval(inputs)),
+            Self::Binary(op, a, b) => op.apply(a.eval(inputs), b.eval(inputs)),
+        }
+    }
+
+    /// Returns the number of inputs the expression refers to, i.e. one more than the highest input index.
+    pub fn num_inputs(&self) -> usize {
+        match self {
+            Self::Const(_) => 0,
+            Self::Input(index) => index + 1,
+            Self::Unary(_, x) => x.num_inputs(),
+            Self::Binary(_, a, b) => a.num_inputs().max(b.num_inputs()),
+        }
+    }
+
+    /// Returns the number of operations in the expression.
+    pub fn num_ops(&self) -> usize {
+        match self {
+            Self::Const(_) | Self::Input(_) => 0,
+            Self::Unary(_, x) => 1 + x.num_ops(),
+            Self::Binary(_, a, b) => 1 + a.num_ops() + b.num_ops(),
+        }
+    }
+
+    /// Returns a value that displays the expression in the syntax accepted by [`ExprProc::parse`], using the given names for its inputs.
+    ///
+    /// # Panics
+    ///
+    /// Panics when displayed if the expression refers to an input without a name.
+    pub fn display<'a>(&'a self, input_names: &'a [String]) -> impl fmt::Display + 'a {
+        DisplayExpr {
+            expr: self,
+            input_names,
+        }
+    }
+
+    /// Appends the instructions evaluating this expression to `code`, returning the maximum stack depth they need.
+    fn compile(&self, code: &mut Vec<Instr>) -> usize {
+        match self {
+            Self::Const(value) => {
+                code.push(Instr::Const(*value));
+                1
+            }
+            Self::Input(index) => {
+                code.push(Instr::Input(*index));
+                1
+            }
+            Self::Unary(op, x) => {
+                let depth = x.compile(code);
+                code.push(Instr::Unary(*op));
+                depth
+            }
+            Self::Binary(op, a, b) => {
+                let depth = a.compile(code).max(1 + b.compile(code));
+                code.push(Instr::Binary(*op));
+                depth
+            }
+        }
+    }
+
+    /// How tightly the expression binds when displayed, for deciding where parentheses are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            Self::Binary(BinaryOp::Add | BinaryOp::Sub, _, _) => 1,
+            Self::Binary(BinaryOp::Mul | BinaryOp::Div | BinaryOp::Rem, _, _) => 2,
+            Self::Unary(UnaryOp::Neg, _) => 3,
+            _ => 4,
+        }
+    }
+}
+
+impl From<f64> for Expr {
+    fn from(value: f64) -> Self {
+        Self::Const(value)
+    }
+}
+
+impl fmt::Display for Expr {
+    /// Displays the expression with its inputs named `in0`, `in1` and so on, as in [`ExprProc::new`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let input_names = default_input_names(self.num_inputs());
+        let result = self.display(&input_names).fmt(f);
+        result
+    }
+}
+
+struct DisplayExpr<'a> {
+    expr: &'a Expr,
+    input_names: &'a [String],
+}
+
+impl DisplayExpr<'_> {
+    fn write(&self, f: &mut fmt::Formatter<'_>, expr: &Expr, parenthesize: bool) -> fmt::Result {
+        if parenthesize {
+            f.write_str("(")?;
+            self.write(f, expr, false)?;
+            return f.write_str(")");
+        }
+
+        match expr {
+            // the parser has no literals for these, so spell out a division that produces them
+            Expr::Const(value) if value.is_nan() => f.write_str("(0 / 0)"),
+            Expr::Const(value) if value.is_infinite() => {
+                write!(f, "({} / 0)", value.signum())
+            }
+            Expr::Const(value) => write!(f, "{value}"),
+            Expr::Input(index) => f.write_str(&self.input_names[*index]),
+            Expr::Unary(UnaryOp::Neg, x) => {
+                f.write_str("-")?;
+                let negative_literal = matches!(**x, Expr::Const(value) if value < 0.0);
+                self.write(f, x, x.precedence() < 3 || negative_literal)
+            }
+            Expr::Unary(op, x) => {
+                write!(f, "{}(", op.name())?;
+                self.write(f, x, false)?;
+                f.write_str(")")
+            }
+            Expr::Binary(op, a, b) => {
+                let symbol = match op {
+                    BinaryOp::Add => "+",
+                    BinaryOp::Sub => "-",
+                    BinaryOp::Mul => "*",
+                    BinaryOp::Div => "/",
+                    BinaryOp::Rem => "%",
+                    _ => {
+                        write!(f, "{}(", op.name())?;
+                        self.write(f, a, false)?;
+                        f.write_str(", ")?;
+                        self.write(f, b, false)?;
+                        return f.write_str(")");
+                    }
+                };
+                // all operators are left-associative
+                let precedence = expr.precedence();
+                self.write(f, a, a.precedence() < precedence)?;
+                write!(f, " {symbol} ")?;
+                self.write(f, b, b.precedence() <= precedence)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DisplayExpr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, self.expr, false)
+    }
+}
+
+macro_rules! impl_expr_ops {
+    ($($std_op:ident :: $name:ident),* $(,)?) => {
+        $(
+            impl<T: Into<Expr>> std::ops::$std_op<T> for Expr {
+                type Output = Expr;
+
+                fn $name(self, other: T) -> Expr {
+                    Expr::$name(self, other)
+                }
+            }
+
+            impl std::ops::$std_op<Expr> for f64 {
+                type Output = Expr;
+
+                fn $name(self, other: Expr) -> Expr {
+                    Expr::$name(Expr::Const(self), other)
+                }
+            }
+        )*
+    };
+}
+
+impl_expr_ops!(Add::add, Sub::sub, Mul::mul, Div::div, Rem::rem);
+
+impl std::ops::Neg for Expr {
+    type Output = Expr;
+
+    fn neg(self) -> Expr {
+        Expr::neg(self)
+    }
+}
+
+/// A single step of a compiled [`Expr`], operating on a stack of values.
+#[derive(Debug, Clone, Copy)]
+enum Instr {
+    Const(f64),
+    Input(usize),
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+}
+
+fn default_input_names(num_inputs: usize) -> Vec<String> {
+    (0..num_inputs).map(|i| format!("in{i}")).collect()
+}
+
+/// Converts a parsed expression into an [`Expr`], looking up (or, unless `fixed` is set, adding) input names as variables are encountered.
+fn lower_expr(expr: &parser::Expr, input_names: &mut Vec<String>, fixed: bool) -> DslResult<Expr> {
+    match &expr.kind {
+        ExprKind::Number(value) => Ok(Expr::Const(*value)),
+        ExprKind::Var(name) => {
+            if let Some(index) = input_names.iter().position(|input| input == name) {
+                return Ok(Expr::Input(index));
+            }
+            if fixed {
+                return Err(DslError::new(format!("unknown input `{name}`"), expr.span));
+            }
+            input_names.push(name.clone());
+            Ok(Expr::Input(input_names.len() - 1))
+        }
+        ExprKind::Input(_) => Err(DslError::new(
+            "`in[...]` refers to graph inputs; use named variables in expressions",
+            expr.span,
+        )),
+        ExprKind::Unary { op, expr } => match op {
+            parser::UnaryOp::Neg => Ok(lower_expr(expr, input_names, fixed)?.neg()),
+        },
+        ExprKind::Binary { op, lhs, rhs } => {
+            let op = match op {
+                parser::BinaryOp::Add => BinaryOp::Add,
+                parser::BinaryOp::Sub => BinaryOp::Sub,
+                parser::BinaryOp::Mul => BinaryOp::Mul,
+                parser::BinaryOp::Div => BinaryOp::Div,
+                parser::BinaryOp::Rem => BinaryOp::Rem,
+            };
+            Ok(Expr::Binary(
+                op,
+                Box::new(lower_expr(lhs, input_names, fixed)?),
+                Box::new(lower_expr(rhs, input_names, fixed)?),
+            ))
+        }
+        ExprKind::Call {
+            function,
+            params,
+            args,
+        } => {
+            if let Some(param) = params.first() {
+                return Err(DslError::new(
+                    "math functions don't take parameters",
+                    param.span,
+                ));
+            }
+            if let Some(name) = args.iter().find_map(|arg| arg.name.as_ref()) {
+                return Err(DslError::new(
+                    "math functions only take positional arguments",
+                    name.span,
+                ));
+            }
+
+            let num_args = if UnaryOp::from_name(&function.name).is_some() {
+                1
+            } else if BinaryOp::from_name(&function.name).is_some() {
+                2
+            } else {
+                return Err(DslError::new(
+                    format!("unknown math function `{}`", function.name),
+                    function.span,
+                ));
+            };
+            if args.len() != num_args {
+                return Err(DslError::new(
+                    format!(
+                        "`{}` takes {num_args} argument(s), but {} were given",
+                        function.name,
+                        args.len()
+                    ),
+                    expr.span,
+                ));
+            }
+
+            let mut args = args
+                .iter()
+                .map(|arg| lower_expr(&arg.value, input_names, fixed))
+                .collect::<DslResult<Vec<_>>>()?
+                .into_iter();
+            let a = Box::new(args.next().unwrap());
+            match (UnaryOp::from_name(&function.name), args.next()) {
+                (Some(op), None) => Ok(Expr::Unary(op, a)),
+                (_, Some(b)) => Ok(Expr::Binary(
+                    BinaryOp::from_name(&function.name).unwrap(),
+                    a,
+                    Box::new(b),
+                )),
+                (None, None) => unreachable!(),
+            }
+        }
+    }
+}
+
+/// A processor that evaluates an [`Expr`] over its inputs once per sample.
+///
+/// This does the work of a whole chain of [`builtins::math`](crate::builtins::math) processors in a single node, without the buffers and copies in between.
+/// The expression is compiled to a flat list of stack instructions, so evaluating it doesn't recurse or allocate.
+/// The graph optimizer also creates these when fusing chains of math nodes.
+///
+/// Expressions can be written as strings using the math syntax of the [`dsl`](crate::dsl), e.g. `a * sin(b) + 0.5`,
+/// where each variable becomes a named input.
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `i` | the `i`th input name (`in{i}` by default) | `0.0` | The values of [`Expr::Input(i)`](Expr::Input). |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The value of the expression. |
+#[derive(Clone, Debug)]
+pub struct ExprProc {
+    expr: Expr,
+    input_names: Vec<String>,
+    code: Vec<Instr>,
+    // reused across samples, with enough capacity for the deepest point of the expression
+    stack: Vec<f64>,
+}
+
+impl ExprProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::ExprProc");
+
+    /// Creates a new [`ExprProc`] evaluating the given expression, with as many inputs as it refers to, named `in0`, `in1` and so on.
+    pub fn new(expr: impl Into<Expr>) -> Self {
+        let expr = expr.into();
+        let input_names = default_input_names(expr.num_inputs());
+        Self::with_input_names(expr, input_names)
+    }
+
+    /// Creates a new [`ExprProc`] evaluating the given expression, with one input per given name.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the expression refers to more inputs than there are names.
+    pub fn with_input_names<S: Into<String>>(
+        expr: impl Into<Expr>,
+        input_names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let expr = expr.into();
+        let input_names = input_names.into_iter().map(Into::into).collect::<Vec<_>>();
+        assert!(
+            expr.num_inputs() <= input_names.len(),
+            "Expression refers to {} inputs, but only {} names were given",
+            expr.num_inputs(),
+            input_names.len()
+        );
+
+        let mut code = Vec::new();
+        let depth = expr.compile(&mut code);
+        Self {
+            expr,
+            input_names,
+            code,
+            stack: Vec::with_capacity(depth),
+        }
+    }
+
+    /// Parses an expression such as `a * sin(b) + 0.5`, with one input per variable in the order they first appear.
+    ///
+    /// Numbers, `+`, `-`, `*`, `/`, `%`, unary `-`, parentheses, and the math functions of the [`dsl`](crate::dsl) (e.g. `sin`, `tanh`, `pow` or `max`) are supported.
+    pub fn parse(source: &str) -> DslResult<Self> {
+        let mut input_names = Vec::new();
+        let expr = Self::lower(source, &mut input_names, false)?;
+        Ok(Self::with_input_names(expr, input_names))
+    }
+
+    /// Parses an expression like [`ExprProc::parse`], but with the given inputs in the given order. Any other variable is an error.
+    pub fn parse_with_inputs<S: Into<String>>(
+        source: &str,
+        input_names: impl IntoIterator<Item = S>,
+    ) -> DslResult<Self> {
+        let mut input_names = input_names.into_iter().map(Into::into).collect();
+        let expr = Self::lower(source, &mut input_names, true)?;
+        Ok(Self::with_input_names(expr, input_names))
+    }
+
+    fn lower(source: &str, input_names: &mut Vec<String>, fixed: bool) -> DslResult<Expr> {
+        let parsed = dsl::parse_expr(source)?;
+        lower_expr(&parsed, input_names, fixed).map_err(|err| err.locate(source))
+    }
+
+    pub(crate) fn from_params(params: &Params) -> Result<Self, ParamError> {
+        params.expect_only(&["expr", "inputs"])?;
+        let source = params
+            .get_str("expr")?
+            .ok_or_else(|| ParamError::missing("expr"))?;
+
+        let result = match params.get("inputs") {
+            Some(Param::List(inputs)) => {
+                let input_names = inputs
+                    .iter()
+                    .map(|input| input.as_str().map(str::to_owned))
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(|| ParamError::new("inputs", "expected a list of strings"))?;
+                Self::parse_with_inputs(source, input_names)
+            }
+            Some(_) => return Err(ParamError::new("inputs", "expected a list of strings")),
+            None => Self::parse(source),
+        };
+        result.map_err(|err| ParamError::new("expr", err.to_string()))
+    }
+
+    /// Returns the expression this processor evaluates.
+    pub fn expr(&self) -> &Expr {
+        &self.expr
+    }
+
+    /// Returns the names of the inputs, in order.
+    pub fn input_names(&self) -> &[String] {
+        &self.input_names
+    }
+}
+
+impl From<Expr> for ExprProc {
+    fn from(expr: Expr) -> Self {
+        Self::new(expr)
+    }
+}
+
+impl fmt::Display for ExprProc {
+    /// Displays the expression in the syntax accepted by [`ExprProc::parse`].
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.expr.display(&self.input_names).fmt(f)
+    }
+}
+
+impl Process for ExprProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.input_names
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn params(&self) -> Params {
+        let inputs = self
+            .input_names
+            .iter()
+            .map(|name| Param::String(name.clone()))
+            .collect();
+        Params::from([
+            ("expr".to_owned(), Param::String(self.to_string())),
+            ("inputs".to_owned(), Param::List(inputs)),
+        ])
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        let stack = &mut self.stack;
+
+        for (i, sample) in outputs[0].iter_mut().enumerate() {
+            stack.clear();
+            for instr in self.code.iter() {
+                match *instr {
+                    Instr::Const(value) => stack.push(value),
+                    Instr::Input(index) => stack.push(inputs[index][i].value()),
+                    Instr::Unary(op) => {
+                        let x = stack.last_mut().unwrap();
+                        *x = op.apply(*x);
+                    }
+                    Instr::Binary(op) => {
+                        let b = stack.pop().unwrap();
+                        let a = stack.last_mut().unwrap();
+                        *a = op.apply(*a, b);
+                    }
+                }
+            }
+            *sample = Sample::new(stack[0]);
+        }
+    }
+}
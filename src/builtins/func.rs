@@ -0,0 +1,223 @@
+//! Processors wrapping closures, for prototyping without writing a [`Process`] impl.
+//!
+//! Closures are `FnMut`, so they can keep state in the variables they capture by value. Each copy of a processor
+//! (e.g. each voice of a [`Poly`](crate::graph::poly::Poly)) gets its own clone of the closure and its state.
+//!
+//! Since closures can't be serialized, graphs containing these processors can't be saved as [`Patch`](crate::patch::Patch)es;
+//! [`Patch::from_graph`](crate::patch::Patch::from_graph) returns an error for them.
+
+use std::marker::PhantomData;
+
+use crate::prelude::*;
+
+/// Information about the current processing setup, passed to [`FnProc`] closures.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FnContext {
+    pub sample_rate: f64,
+    pub block_size: usize,
+}
+
+/// The names of the inputs or outputs of a closure-based processor: either a number of ports named `in0`/`out0` and so on, or a list of names.
+pub trait PortNames {
+    fn into_port_names(self, prefix: &str) -> Vec<String>;
+}
+
+impl PortNames for usize {
+    fn into_port_names(self, prefix: &str) -> Vec<String> {
+        (0..self).map(|i| format!("{prefix}{i}")).collect()
+    }
+}
+
+impl PortNames for Vec<String> {
+    fn into_port_names(self, _prefix: &str) -> Vec<String> {
+        self
+    }
+}
+
+impl PortNames for &[&str] {
+    fn into_port_names(self, _prefix: &str) -> Vec<String> {
+        self.iter().map(|&name| name.to_owned()).collect()
+    }
+}
+
+impl<const N: usize> PortNames for [&str; N] {
+    fn into_port_names(self, _prefix: &str) -> Vec<String> {
+        self.iter().map(|&name| name.to_owned()).collect()
+    }
+}
+
+/// A processor that calls a closure with whole blocks of input and output buffers.
+///
+/// The closure is called as `f(ctx, inputs, outputs)` once per block, with one buffer per input and output.
+///
+/// # Inputs
+///
+/// As given to [`FnProc::new`], all unbounded with a default of `0.0`.
+///
+/// # Outputs
+///
+/// As given to [`FnProc::new`].
+#[derive(Clone)]
+pub struct FnProc<F> {
+    input_names: Vec<String>,
+    output_names: Vec<String>,
+    context: FnContext,
+    f: F,
+}
+
+impl<F> FnProc<F>
+where
+    F: FnMut(&FnContext, &[Buffer], &mut [Buffer]) + Clone + Send + Sync + 'static,
+{
+    /// Creates a new [`FnProc`] with the given inputs and outputs, given either as numbers or lists of names.
+    pub fn new(inputs: impl PortNames, outputs: impl PortNames, f: F) -> Self {
+        Self {
+            input_names: inputs.into_port_names("in"),
+            output_names: outputs.into_port_names("out"),
+            context: FnContext::default(),
+            f,
+        }
+    }
+}
+
+impl<F> Process for FnProc<F>
+where
+    F: FnMut(&FnContext, &[Buffer], &mut [Buffer]) + Clone + Send + Sync + 'static,
+{
+    fn name(&self) -> &str {
+        concat!(module_path!(), "::FnProc")
+    }
+
+    fn is_serializable(&self) -> bool {
+        false
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.input_names
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        self.output_names
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        self.context = FnContext {
+            sample_rate,
+            block_size,
+        };
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        (self.f)(&self.context, inputs, outputs);
+    }
+}
+
+/// A closure computing one output sample from one sample of each input, for use with [`MapProc`].
+///
+/// This is implemented for `FnMut` closures taking up to four [`f64`] arguments and returning an [`f64`];
+/// `Args` is the tuple of argument types, and only serves to tell the implementations apart.
+pub trait SampleFn<Args>: Clone + Send + Sync + 'static {
+    /// The number of arguments, i.e. the number of inputs of the processor.
+    const NUM_INPUTS: usize;
+
+    /// Calls the closure with the `index`th sample of each input.
+    fn call(&mut self, inputs: &[Buffer], index: usize) -> f64;
+}
+
+macro_rules! impl_sample_fn {
+    ($num_inputs:literal; $($arg:ident: $input:literal),*) => {
+        impl<F> SampleFn<($($arg,)*)> for F
+        where
+            F: FnMut($($arg),*) -> f64 + Clone + Send + Sync + 'static,
+        {
+            const NUM_INPUTS: usize = $num_inputs;
+
+            #[allow(unused_variables)]
+            #[inline]
+            fn call(&mut self, inputs: &[Buffer], index: usize) -> f64 {
+                self($(inputs[$input][index].value()),*)
+            }
+        }
+    };
+}
+
+impl_sample_fn!(0;);
+impl_sample_fn!(1; f64: 0);
+impl_sample_fn!(2; f64: 0, f64: 1);
+impl_sample_fn!(3; f64: 0, f64: 1, f64: 2);
+impl_sample_fn!(4; f64: 0, f64: 1, f64: 2, f64: 3);
+
+/// A processor that maps each sample of its inputs to an output sample with a closure, e.g. `|a, b| a * b.tanh()`.
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `i` | `in{i}` | `0.0` | The `i`th argument of the closure. |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The value returned by the closure. |
+pub struct MapProc<F, Args> {
+    f: F,
+    _args: PhantomData<fn(Args)>,
+}
+
+impl<F, Args> MapProc<F, Args>
+where
+    F: SampleFn<Args>,
+{
+    pub fn new(f: F) -> Self {
+        Self {
+            f,
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<F: Clone, Args> Clone for MapProc<F, Args> {
+    fn clone(&self) -> Self {
+        Self {
+            f: self.f.clone(),
+            _args: PhantomData,
+        }
+    }
+}
+
+impl<F, Args> Process for MapProc<F, Args>
+where
+    F: SampleFn<Args>,
+    Args: 'static,
+{
+    fn name(&self) -> &str {
+        concat!(module_path!(), "::MapProc")
+    }
+
+    fn is_serializable(&self) -> bool {
+        false
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        (0..F::NUM_INPUTS)
+            .map(|i| SignalSpec::unbounded(format!("in{i}"), 0.0))
+            .collect()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (i, sample) in outputs[0].iter_mut().enumerate() {
+            *sample = Sample::new(self.f.call(inputs, i));
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::builder::typed_node::{InputPort, OutputPort, SingleOutput, StaticPorts};
 use crate::prelude::*;
 use std::ops::*;
 
@@ -17,8 +18,27 @@ impl ConstantProc {
     pub fn new(value: f64) -> Self {
         Self { value }
     }
+
+    /// Returns the value this processor outputs.
+    pub fn value(&self) -> f64 {
+        self.value
+    }
+}
+
+impl ConstantProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::ConstantProc");
+
+    pub const OUT: OutputPort<Self, 0> = OutputPort::new();
+}
+
+impl StaticPorts for ConstantProc {
+    const NUM_INPUTS: usize = 0;
+    const NUM_OUTPUTS: usize = 1;
 }
 
+impl SingleOutput for ConstantProc {}
+
 impl Default for ConstantProc {
     fn default() -> Self {
         Self { value: 0.0 }
@@ -26,12 +46,20 @@ impl Default for ConstantProc {
 }
 
 impl Process for ConstantProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
     fn input_spec(&self) -> Vec<SignalSpec> {
         vec![]
     }
 
     fn output_spec(&self) -> Vec<SignalSpec> {
-        vec![SignalSpec::unbounded("out", self.value)]
+        vec![SignalSpec::unbounded("out", self.value).with_kind(SignalKind::Control)]
+    }
+
+    fn params(&self) -> Params {
+        Params::from([("value".to_owned(), Param::from(self.value))])
     }
 
     fn process(&mut self, _inputs: &[Buffer], outputs: &mut [Buffer]) {
@@ -41,13 +69,84 @@ impl Process for ConstantProc {
     }
 }
 
+/// A processor that passes its input through unchanged.
+///
+/// This is mostly useful for giving each output of a multi-output node its own single-output node, e.g. when splitting it into a [`Bus`](crate::builder::bus::Bus).
+///
+/// # Inputs
+///
+/// | Index | Name | Default | Description |
+/// | --- | --- | --- | --- |
+/// | `0` | `in` | `0.0` | The input signal. |
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The unchanged input signal. |
+#[derive(Clone, Debug, Default)]
+pub struct IdentityProc;
+
+impl IdentityProc {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::IdentityProc");
+
+    pub const IN: InputPort<Self, 0> = InputPort::new();
+    pub const OUT: OutputPort<Self, 0> = OutputPort::new();
+}
+
+impl StaticPorts for IdentityProc {
+    const NUM_INPUTS: usize = 1;
+    const NUM_OUTPUTS: usize = 1;
+}
+
+impl SingleOutput for IdentityProc {}
+
+impl Process for IdentityProc {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        outputs[0].copy_from_slice(&inputs[0]);
+    }
+}
+
 macro_rules! impl_binary_proc {
     ($name:ident, $method:ident, $doc:expr) => {
         #[doc = $doc]
         #[derive(Clone, Debug, Default)]
         pub struct $name;
 
+        impl $name {
+            /// The name this processor is saved and registered under.
+            pub const NAME: &'static str = concat!(module_path!(), "::", stringify!($name));
+
+            pub const A: InputPort<Self, 0> = InputPort::new();
+            pub const B: InputPort<Self, 1> = InputPort::new();
+            pub const OUT: OutputPort<Self, 0> = OutputPort::new();
+        }
+
+        impl StaticPorts for $name {
+            const NUM_INPUTS: usize = 2;
+            const NUM_OUTPUTS: usize = 1;
+        }
+
+        impl SingleOutput for $name {}
+
         impl Process for $name {
+            fn name(&self) -> &str {
+                Self::NAME
+            }
+
             fn input_spec(&self) -> Vec<SignalSpec> {
                 vec![
                     SignalSpec::unbounded("a", 0.0),
@@ -283,7 +382,26 @@ macro_rules! impl_unary_proc {
         #[derive(Clone, Debug, Default)]
         pub struct $name;
 
+        impl $name {
+            /// The name this processor is saved and registered under.
+            pub const NAME: &'static str = concat!(module_path!(), "::", stringify!($name));
+
+            pub const IN: InputPort<Self, 0> = InputPort::new();
+            pub const OUT: OutputPort<Self, 0> = OutputPort::new();
+        }
+
+        impl StaticPorts for $name {
+            const NUM_INPUTS: usize = 1;
+            const NUM_OUTPUTS: usize = 1;
+        }
+
+        impl SingleOutput for $name {}
+
         impl Process for $name {
+            fn name(&self) -> &str {
+                Self::NAME
+            }
+
             fn input_spec(&self) -> Vec<SignalSpec> {
                 vec![SignalSpec::unbounded("in", 0.0)]
             }
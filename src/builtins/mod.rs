@@ -1,2 +1,87 @@
+use crate::{
+    graph::{oversample::Oversample, poly::Poly, subgraph::SubGraph},
+    processor::{Params, Process},
+    registry::{ParamsExt, ProcessorRegistry},
+};
+
+pub mod coerce;
+pub mod expr;
+pub mod func;
 pub mod math;
 pub mod oscillators;
+
+macro_rules! register_default_builtins {
+    ($registry:ident, $($proc:ty),* $(,)?) => {
+        $(
+            $registry.register_default::<$proc>();
+        )*
+    };
+}
+
+/// Registers all builtin processors with the given [`ProcessorRegistry`].
+pub(crate) fn register_builtins(registry: &mut ProcessorRegistry) {
+    registry.register(math::ConstantProc::NAME, |params: &Params| {
+        params.expect_only(&["value"])?;
+        let value = params.get_f64("value")?.unwrap_or_default();
+        Ok(math::ConstantProc::new(value))
+    });
+
+    registry.register(
+        oscillators::NoiseOscillator::NAME,
+        oscillators::NoiseOscillator::from_params,
+    );
+
+    registry.register(expr::ExprProc::NAME, expr::ExprProc::from_params);
+
+    registry.register_nested(SubGraph::NAME, SubGraph::from_params);
+    registry.register_nested(Poly::NAME, Poly::from_params);
+    registry.register_nested(
+        Oversample::<Box<dyn Process>>::NAME,
+        Oversample::from_params,
+    );
+
+    register_default_builtins!(
+        registry,
+        oscillators::SineOscillator,
+        math::IdentityProc,
+        math::AddProc,
+        math::SubProc,
+        math::MulProc,
+        math::DivProc,
+        math::RemProc,
+        math::PowfProc,
+        math::Atan2Proc,
+        math::HypotProc,
+        math::MaxProc,
+        math::MinProc,
+        math::NegProc,
+        math::AbsProc,
+        math::SqrtProc,
+        math::CbrtProc,
+        math::CeilProc,
+        math::FloorProc,
+        math::RoundProc,
+        math::TruncProc,
+        math::FractProc,
+        math::RecipProc,
+        math::SignumProc,
+        math::SinProc,
+        math::CosProc,
+        math::TanProc,
+        math::AsinProc,
+        math::AcosProc,
+        math::AtanProc,
+        math::SinhProc,
+        math::CoshProc,
+        math::TanhProc,
+        math::ExpProc,
+        math::Exp2Proc,
+        math::ExpM1Proc,
+        math::LnProc,
+        math::Log2Proc,
+        math::Log10Proc,
+        coerce::ThresholdProc,
+        coerce::EdgeDetectProc,
+        coerce::ToIndexProc,
+    );
+}
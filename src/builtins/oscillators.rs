@@ -1,3 +1,4 @@
+use crate::builder::typed_node::{OutputPort, SingleOutput, StaticPorts};
 use crate::prelude::*;
 
 /// A free-running sine wave oscillator.
@@ -13,43 +14,197 @@ use crate::prelude::*;
 /// | Index | Name | Description |
 /// | --- | --- | --- |
 /// | `0` | `out` | The output sine wave signal. |
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Default, Process)]
 pub struct SineOscillator {
+    #[input(default = 440.0)]
+    frequency: f64,
+    #[output]
+    out: f64,
     t: f64,
     t_step: f64,
 }
 
-impl Default for SineOscillator {
-    fn default() -> Self {
+impl SineOscillator {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::SineOscillator");
+}
+
+impl SampleProcess for SineOscillator {
+    fn resize_buffers(&mut self, sample_rate: f64, _block_size: usize) {
+        self.t_step = sample_rate.recip();
+    }
+
+    fn reset(&mut self) {
+        self.t = 0.0;
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        Some(Param::Map(Params::from([(
+            "t".to_owned(),
+            Param::from(self.t),
+        )])))
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("state", "expected a map"))?;
+        self.t = state
+            .get_f64("t")?
+            .ok_or_else(|| ParamError::missing("t"))?;
+        Ok(())
+    }
+
+    fn tick(&mut self) {
+        self.out = (self.t * self.frequency * 2.0 * std::f64::consts::PI).sin();
+        self.t += self.t_step;
+    }
+}
+
+/// A white noise generator producing uniformly distributed samples in the range `[-1.0, 1.0]`.
+///
+/// The noise is generated by a deterministic pseudo-random number generator, so two generators with the same seed produce identical output.
+///
+/// # Outputs
+///
+/// | Index | Name | Description |
+/// | --- | --- | --- |
+/// | `0` | `out` | The output noise signal. |
+#[derive(Clone, Debug)]
+pub struct NoiseOscillator {
+    seed: u64,
+    state: u64,
+}
+
+impl NoiseOscillator {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::NoiseOscillator");
+
+    pub const OUT: OutputPort<Self, 0> = OutputPort::new();
+
+    /// Creates a new noise generator with the given seed.
+    pub fn new(seed: u64) -> Self {
         Self {
-            t: 0.0,
-            t_step: 0.0,
+            seed,
+            state: initial_state(seed),
         }
     }
+
+    /// Returns the seed this generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Constructs a [`NoiseOscillator`] from its `seed` parameter, as used by the [`ProcessorRegistry`].
+    ///
+    /// The seed is saved as a string, but integral numbers are accepted too, e.g. in patches written by hand.
+    pub(crate) fn from_params(params: &Params) -> Result<Self, ParamError> {
+        params.expect_only(&["seed"])?;
+        let seed = match params.get("seed") {
+            None => 1,
+            Some(Param::String(seed)) => seed
+                .parse::<u64>()
+                .map_err(|err| ParamError::new("seed", err.to_string()))?,
+            Some(seed) => match seed.as_f64() {
+                Some(seed) if seed >= 0.0 && seed.fract() == 0.0 && seed < u64::MAX as f64 => {
+                    seed as u64
+                }
+                _ => return Err(ParamError::new("seed", "expected a non-negative integer")),
+            },
+        };
+        Ok(Self::new(seed))
+    }
+
+    #[inline]
+    fn next_sample(&mut self) -> f64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        let bits = self.state.wrapping_mul(0x2545_f491_4f6c_dd1d) >> 11;
+        (bits as f64 / (1u64 << 53) as f64) * 2.0 - 1.0
+    }
+}
+
+/// Returns the xorshift state a [`NoiseOscillator`] with the given seed starts from.
+///
+/// The seed is scrambled with splitmix64, so that similar seeds (and zero) still give unrelated sequences.
+fn initial_state(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^= z >> 31;
+
+    // the xorshift state must never be zero, which only a single seed maps to
+    if z == 0 {
+        0x9e37_79b9_7f4a_7c15
+    } else {
+        z
+    }
+}
+
+impl Default for NoiseOscillator {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl StaticPorts for NoiseOscillator {
+    const NUM_INPUTS: usize = 0;
+    const NUM_OUTPUTS: usize = 1;
 }
 
-impl Process for SineOscillator {
+impl SingleOutput for NoiseOscillator {}
+
+impl Process for NoiseOscillator {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
     fn input_spec(&self) -> Vec<SignalSpec> {
-        vec![SignalSpec::unbounded("frequency", 440.0)]
+        vec![]
     }
 
     fn output_spec(&self) -> Vec<SignalSpec> {
-        vec![SignalSpec::unbounded("out", 0.0)]
+        vec![SignalSpec::new("out", -1.0, 1.0, 0.0)]
     }
 
-    fn resize_buffers(&mut self, sample_rate: f64, _block_size: usize) {
-        self.t_step = sample_rate.recip();
+    fn params(&self) -> Params {
+        // stored as a string, since not every 64-bit seed is exactly representable as a float
+        Params::from([("seed".to_owned(), Param::from(self.seed.to_string()))])
+    }
+
+    fn reset(&mut self) {
+        self.state = initial_state(self.seed);
     }
 
-    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
-        let frequency = &inputs[0];
-        let out = &mut outputs[0];
+    fn save_state(&self) -> Option<Param> {
+        // stored as a string, since not every 64-bit state is exactly representable as a float
+        Some(Param::Map(Params::from([(
+            "state".to_owned(),
+            Param::from(self.state.to_string()),
+        )])))
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("state", "expected a map"))?;
+        let value = state
+            .get_str("state")?
+            .ok_or_else(|| ParamError::missing("state"))?
+            .parse::<u64>()
+            .map_err(|err| ParamError::new("state", err.to_string()))?;
+        if value == 0 {
+            return Err(ParamError::new("state", "expected a non-zero state"));
+        }
+        self.state = value;
+        Ok(())
+    }
 
-        for (out, frequency) in itertools::izip!(out, frequency) {
-            *out = (self.t * frequency.value() * 2.0 * std::f64::consts::PI)
-                .sin()
-                .into();
-            self.t += self.t_step;
+    fn process(&mut self, _inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for out in outputs[0].iter_mut() {
+            *out = self.next_sample().into();
         }
     }
 }
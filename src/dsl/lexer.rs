@@ -0,0 +1,198 @@
+use super::{DslError, DslResult, Span};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Number(f64),
+    String(String),
+    Ident(String),
+    Let,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    Percent,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    Comma,
+    Colon,
+    Eq,
+    /// A statement separator: either a `;` or a newline outside of any brackets.
+    Separator,
+    Eof,
+}
+
+impl TokenKind {
+    /// Returns a short description of the token for use in error messages.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Number(value) => format!("number `{value}`"),
+            Self::String(value) => format!("string {value:?}"),
+            Self::Ident(name) => format!("identifier `{name}`"),
+            Self::Let => "`let`".to_owned(),
+            Self::Plus => "`+`".to_owned(),
+            Self::Minus => "`-`".to_owned(),
+            Self::Star => "`*`".to_owned(),
+            Self::Slash => "`/`".to_owned(),
+            Self::Percent => "`%`".to_owned(),
+            Self::LParen => "`(`".to_owned(),
+            Self::RParen => "`)`".to_owned(),
+            Self::LBracket => "`[`".to_owned(),
+            Self::RBracket => "`]`".to_owned(),
+            Self::LBrace => "`{`".to_owned(),
+            Self::RBrace => "`}`".to_owned(),
+            Self::Comma => "`,`".to_owned(),
+            Self::Colon => "`:`".to_owned(),
+            Self::Eq => "`=`".to_owned(),
+            Self::Separator => "end of statement".to_owned(),
+            Self::Eof => "end of input".to_owned(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+/// Splits the source text into [`Token`]s, always ending with a [`TokenKind::Eof`] token.
+pub fn tokenize(source: &str) -> DslResult<Vec<Token>> {
+    let bytes = source.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    // newlines inside brackets don't end statements
+    let mut depth = 0usize;
+
+    while pos < bytes.len() {
+        let start = pos;
+        let c = bytes[pos];
+
+        let kind = match c {
+            b'\n' => {
+                pos += 1;
+                if depth > 0 {
+                    continue;
+                }
+                TokenKind::Separator
+            }
+            c if c.is_ascii_whitespace() => {
+                pos += 1;
+                continue;
+            }
+            b'#' => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b'/' if bytes.get(pos + 1) == Some(&b'/') => {
+                while pos < bytes.len() && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                continue;
+            }
+            b'0'..=b'9' | b'.' => {
+                while pos < bytes.len() && (bytes[pos].is_ascii_digit() || bytes[pos] == b'.') {
+                    pos += 1;
+                }
+                // exponent, e.g. `1e-3`
+                if pos < bytes.len() && (bytes[pos] == b'e' || bytes[pos] == b'E') {
+                    let mut end = pos + 1;
+                    if end < bytes.len() && (bytes[end] == b'+' || bytes[end] == b'-') {
+                        end += 1;
+                    }
+                    if end < bytes.len() && bytes[end].is_ascii_digit() {
+                        pos = end;
+                        while pos < bytes.len() && bytes[pos].is_ascii_digit() {
+                            pos += 1;
+                        }
+                    }
+                }
+                let text = &source[start..pos];
+                let value = text.parse().map_err(|_| {
+                    DslError::new(format!("invalid number `{text}`"), Span::new(start, pos))
+                })?;
+                TokenKind::Number(value)
+            }
+            b'"' => {
+                pos += 1;
+                while pos < bytes.len() && bytes[pos] != b'"' && bytes[pos] != b'\n' {
+                    pos += 1;
+                }
+                if bytes.get(pos) != Some(&b'"') {
+                    return Err(DslError::new("unterminated string", Span::new(start, pos)));
+                }
+                pos += 1;
+                TokenKind::String(source[start + 1..pos - 1].to_owned())
+            }
+            c if c.is_ascii_alphabetic() || c == b'_' => {
+                while pos < bytes.len()
+                    && (bytes[pos].is_ascii_alphanumeric()
+                        || bytes[pos] == b'_'
+                        || (bytes[pos] == b':' && bytes.get(pos + 1) == Some(&b':')))
+                {
+                    // paths like `daprs::builtins::math::AddProc` are single identifiers
+                    pos += if bytes[pos] == b':' { 2 } else { 1 };
+                }
+                match &source[start..pos] {
+                    "let" => TokenKind::Let,
+                    ident => TokenKind::Ident(ident.to_owned()),
+                }
+            }
+            _ => {
+                pos += 1;
+                match c {
+                    b'+' => TokenKind::Plus,
+                    b'-' => TokenKind::Minus,
+                    b'*' => TokenKind::Star,
+                    b'/' => TokenKind::Slash,
+                    b'%' => TokenKind::Percent,
+                    b'(' | b'[' | b'{' => {
+                        depth += 1;
+                        match c {
+                            b'(' => TokenKind::LParen,
+                            b'[' => TokenKind::LBracket,
+                            _ => TokenKind::LBrace,
+                        }
+                    }
+                    b')' | b']' | b'}' => {
+                        depth = depth.saturating_sub(1);
+                        match c {
+                            b')' => TokenKind::RParen,
+                            b']' => TokenKind::RBracket,
+                            _ => TokenKind::RBrace,
+                        }
+                    }
+                    b',' => TokenKind::Comma,
+                    b':' => TokenKind::Colon,
+                    b'=' => TokenKind::Eq,
+                    b';' => TokenKind::Separator,
+                    _ => {
+                        // report the whole (possibly multi-byte) character
+                        let ch = source[start..].chars().next().unwrap_or('?');
+                        return Err(DslError::new(
+                            format!("unexpected character `{ch}`"),
+                            Span::new(start, start + ch.len_utf8()),
+                        ));
+                    }
+                }
+            }
+        };
+
+        tokens.push(Token {
+            kind,
+            span: Span::new(start, pos),
+        });
+    }
+
+    tokens.push(Token {
+        kind: TokenKind::Eof,
+        span: Span::new(source.len(), source.len()),
+    });
+
+    Ok(tokens)
+}
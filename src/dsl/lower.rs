@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+
+use super::{
+    parser::{BinaryOp, Expr, ExprKind, ParamArg, Program, Statement, UnaryOp},
+    DslError, DslResult, Span,
+};
+use crate::{
+    builder::{graph_builder::GraphBuilder, node_builder::Node},
+    builtins::{math, oscillators},
+    processor::Params,
+    registry::{ProcessorRegistry, RegistryError},
+};
+
+/// Returns the full processor name a builtin function name refers to.
+fn builtin_function(name: &str) -> Option<&'static str> {
+    let name = match name {
+        "sine" => oscillators::SineOscillator::NAME,
+        "noise" => oscillators::NoiseOscillator::NAME,
+        "neg" => math::NegProc::NAME,
+        "abs" => math::AbsProc::NAME,
+        "sqrt" => math::SqrtProc::NAME,
+        "cbrt" => math::CbrtProc::NAME,
+        "ceil" => math::CeilProc::NAME,
+        "floor" => math::FloorProc::NAME,
+        "round" => math::RoundProc::NAME,
+        "trunc" => math::TruncProc::NAME,
+        "fract" => math::FractProc::NAME,
+        "recip" => math::RecipProc::NAME,
+        "signum" => math::SignumProc::NAME,
+        "sin" => math::SinProc::NAME,
+        "cos" => math::CosProc::NAME,
+        "tan" => math::TanProc::NAME,
+        "asin" => math::AsinProc::NAME,
+        "acos" => math::AcosProc::NAME,
+        "atan" => math::AtanProc::NAME,
+        "sinh" => math::SinhProc::NAME,
+        "cosh" => math::CoshProc::NAME,
+        "tanh" => math::TanhProc::NAME,
+        "exp" => math::ExpProc::NAME,
+        "exp2" => math::Exp2Proc::NAME,
+        "expm1" => math::ExpM1Proc::NAME,
+        "ln" => math::LnProc::NAME,
+        "log2" => math::Log2Proc::NAME,
+        "log10" => math::Log10Proc::NAME,
+        "pow" => math::PowfProc::NAME,
+        "atan2" => math::Atan2Proc::NAME,
+        "hypot" => math::HypotProc::NAME,
+        "min" => math::MinProc::NAME,
+        "max" => math::MaxProc::NAME,
+        _ => return None,
+    };
+
+    Some(name)
+}
+
+/// Lowers a [`Program`] into calls on a [`GraphBuilder`].
+pub struct Lowerer<'a, 'g> {
+    graph: &'g GraphBuilder,
+    registry: &'a ProcessorRegistry,
+    vars: HashMap<String, Node<'g>>,
+    inputs: Vec<Node<'g>>,
+    outputs: Vec<Node<'g>>,
+    // where each output was assigned, if it was
+    assigned: Vec<Option<Span>>,
+}
+
+impl<'a, 'g> Lowerer<'a, 'g> {
+    pub fn new(graph: &'g GraphBuilder, registry: &'a ProcessorRegistry) -> Self {
+        Self {
+            graph,
+            registry,
+            vars: HashMap::new(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            assigned: Vec::new(),
+        }
+    }
+
+    pub fn lower_program(&mut self, program: &Program) -> DslResult<()> {
+        // create the graph's inputs and outputs up front so they're in index order
+        let mut num_inputs = 0;
+        let mut num_outputs = 0;
+        for statement in program.statements.iter() {
+            match statement {
+                Statement::Let { value, .. } => count_inputs(value, &mut num_inputs),
+                Statement::Output { index, value, .. } => {
+                    num_outputs = num_outputs.max(index + 1);
+                    count_inputs(value, &mut num_inputs);
+                }
+            }
+        }
+        self.inputs = (0..num_inputs).map(|_| self.graph.add_input()).collect();
+        self.outputs = (0..num_outputs).map(|_| self.graph.add_output()).collect();
+        self.assigned = vec![None; num_outputs];
+
+        for statement in program.statements.iter() {
+            match statement {
+                Statement::Let { name, value } => {
+                    let node = self.lower_expr(value)?;
+                    self.vars.insert(name.name.clone(), node);
+                }
+                Statement::Output {
+                    index,
+                    target_span,
+                    value,
+                } => {
+                    if self.assigned[*index].is_some() {
+                        return Err(DslError::new(
+                            format!("`out[{index}]` is already assigned"),
+                            *target_span,
+                        ));
+                    }
+                    let node = self.lower_single(value)?;
+                    node.try_connect_output(0, self.outputs[*index], 0)
+                        .map_err(|err| {
+                            DslError::new(
+                                format!("cannot connect to `out[{index}]`: {err}"),
+                                value.span,
+                            )
+                        })?;
+                    self.assigned[*index] = Some(*target_span);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lowers an expression that must produce exactly one output.
+    fn lower_single(&mut self, expr: &Expr) -> DslResult<Node<'g>> {
+        let node = self.lower_expr(expr)?;
+        let num_outputs = node.num_outputs();
+        if num_outputs != 1 {
+            return Err(DslError::new(
+                format!("expected an expression with a single output, but this has {num_outputs} outputs"),
+                expr.span,
+            ));
+        }
+        Ok(node)
+    }
+
+    fn lower_expr(&mut self, expr: &Expr) -> DslResult<Node<'g>> {
+        match &expr.kind {
+            ExprKind::Number(value) => Ok(self.graph.add_constant(*value)),
+            ExprKind::Var(name) => self
+                .vars
+                .get(name)
+                .copied()
+                .ok_or_else(|| DslError::new(format!("unknown variable `{name}`"), expr.span)),
+            ExprKind::Input(index) => Ok(self.inputs[*index]),
+            ExprKind::Unary { op, expr } => {
+                let node = self.lower_single(expr)?;
+                Ok(match op {
+                    UnaryOp::Neg => node.neg(),
+                })
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                let lhs = self.lower_single(lhs)?;
+                let rhs = self.lower_single(rhs)?;
+                Ok(match op {
+                    BinaryOp::Add => lhs.add(rhs),
+                    BinaryOp::Sub => lhs.sub(rhs),
+                    BinaryOp::Mul => lhs.mul(rhs),
+                    BinaryOp::Div => lhs.div(rhs),
+                    BinaryOp::Rem => lhs.rem(rhs),
+                })
+            }
+            ExprKind::Call {
+                function,
+                params,
+                args,
+            } => {
+                let name = builtin_function(&function.name).unwrap_or(&function.name);
+
+                let mut param_map = Params::new();
+                for param in params.iter() {
+                    if param_map
+                        .insert(param.name.name.clone(), param.value.clone())
+                        .is_some()
+                    {
+                        return Err(DslError::new(
+                            format!("parameter `{}` is given more than once", param.name.name),
+                            param.span,
+                        ));
+                    }
+                }
+
+                let processor = self
+                    .registry
+                    .create(name, &param_map)
+                    .map_err(|err| registry_error(err, &function.name, function.span, params))?;
+
+                let input_spec = processor.input_spec();
+                let node = self.graph.add_processor_object(processor);
+                let mut connected = vec![false; input_spec.len()];
+
+                for (position, arg) in args.iter().enumerate() {
+                    let input = match &arg.name {
+                        Some(name) => input_spec
+                            .iter()
+                            .position(|spec| spec.name == name.name)
+                            .ok_or_else(|| {
+                                DslError::new(
+                                    format!(
+                                        "`{}` has no input named `{}`",
+                                        function.name, name.name
+                                    ),
+                                    name.span,
+                                )
+                            })?,
+                        None if position < input_spec.len() => position,
+                        None => {
+                            return Err(DslError::new(
+                                format!(
+                                    "`{}` takes {} input(s), but more were given",
+                                    function.name,
+                                    input_spec.len()
+                                ),
+                                arg.value.span,
+                            ))
+                        }
+                    };
+
+                    if connected[input] {
+                        return Err(DslError::new(
+                            format!(
+                                "input `{}` of `{}` is given more than once",
+                                input_spec[input].name, function.name
+                            ),
+                            arg.name.as_ref().map_or(arg.value.span, |name| name.span),
+                        ));
+                    }
+                    connected[input] = true;
+
+                    let source = self.lower_single(&arg.value)?;
+                    node.try_connect_input(source, 0, input as u32)
+                        .map_err(|err| {
+                            DslError::new(
+                                format!(
+                                    "cannot connect to input `{}` of `{}`: {err}",
+                                    input_spec[input].name, function.name
+                                ),
+                                arg.value.span,
+                            )
+                        })?;
+                }
+
+                Ok(node)
+            }
+        }
+    }
+}
+
+fn count_inputs(expr: &Expr, num_inputs: &mut usize) {
+    match &expr.kind {
+        ExprKind::Number(_) | ExprKind::Var(_) => {}
+        ExprKind::Input(index) => *num_inputs = (*num_inputs).max(index + 1),
+        ExprKind::Unary { expr, .. } => count_inputs(expr, num_inputs),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            count_inputs(lhs, num_inputs);
+            count_inputs(rhs, num_inputs);
+        }
+        ExprKind::Call { args, .. } => {
+            for arg in args.iter() {
+                count_inputs(&arg.value, num_inputs);
+            }
+        }
+    }
+}
+
+fn registry_error(err: RegistryError, function: &str, span: Span, params: &[ParamArg]) -> DslError {
+    match err {
+        RegistryError::UnknownProcessor(_) => {
+            DslError::new(format!("unknown function `{function}`"), span)
+        }
+        RegistryError::InvalidParam { param, reason, .. } => {
+            // point at the offending parameter if it was given, otherwise at the function
+            let span = params
+                .iter()
+                .find(|p| p.name.name == param)
+                .map_or(span, |p| p.span);
+            DslError::new(
+                format!("invalid parameter `{param}` for `{function}`: {reason}"),
+                span,
+            )
+        }
+        err => DslError::new(err.to_string(), span),
+    }
+}
@@ -0,0 +1,158 @@
+//! A small expression-oriented language for writing patches.
+//!
+//! A program is a list of statements separated by newlines or `;`:
+//!
+//! ```text
+//! # comments start with `#` or `//`
+//! let lfo = sine(0.5) * 0.5 + 0.5
+//! out[0] = sine(440) * lfo + noise{seed: 42}() * 0.1
+//! out[1] = in[0] * 0.5
+//! ```
+//!
+//! - `let name = expr` binds the result of an expression to a name for later use.
+//! - `out[N] = expr` connects an expression to the graph's `N`th output. `in[N]` refers to the graph's `N`th input.
+//!   Indices go up to [`MAX_INDEX`](parser::MAX_INDEX).
+//! - Numbers become constants, and `+`, `-`, `*`, `/`, `%` and unary `-` work as in Rust.
+//! - `name(args)` creates a processor and connects the arguments to its inputs, either positionally or by name (e.g. `sine(frequency: 440)`).
+//!   Construction parameters go in braces before the arguments, e.g. `noise{seed: 42}()`.
+//!
+//! Function names are either one of the builtin shorthands (`sine`, `noise`, and the math functions such as `sin`, `tanh`, `pow` or `max`),
+//! or the name of any processor in the [`ProcessorRegistry`] used to compile the program (e.g. `SineOscillator`).
+//!
+//! Errors carry the span of the offending source text; see [`DslError::render`].
+
+use crate::{builder::graph_builder::GraphBuilder, graph::Graph, registry::ProcessorRegistry};
+
+pub mod lexer;
+pub mod lower;
+pub mod parser;
+
+pub use parser::Program;
+
+/// A byte range in the source text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns a span covering both this span and the given one.
+    pub fn to(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// An error in a patch program, pointing at the source text it was caused by.
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[error("{line}:{column}: {message}")]
+pub struct DslError {
+    pub message: String,
+    pub span: Span,
+    /// The 1-based line the error starts on. Only set once the error has been located in the source via [`DslError::locate`].
+    pub line: usize,
+    /// The 1-based column (in characters) the error starts on. Only set once the error has been located in the source via [`DslError::locate`].
+    pub column: usize,
+}
+
+pub type DslResult<T> = Result<T, DslError>;
+
+impl DslError {
+    pub fn new(message: impl Into<String>, span: Span) -> Self {
+        Self {
+            message: message.into(),
+            span,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    /// Fills in the line and column of this error from the source text it was produced for.
+    pub fn locate(mut self, source: &str) -> Self {
+        let start = self.span.start.min(source.len());
+        let before = &source[..start];
+        self.line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        self.column = before[line_start..].chars().count() + 1;
+        self
+    }
+
+    /// Renders the error with the offending line of source text and the span underlined, e.g.
+    ///
+    /// ```text
+    /// error: unknown function `sinee`
+    ///  --> 1:10
+    ///   |
+    /// 1 | out[0] = sinee(440)
+    ///   |          ^^^^^
+    /// ```
+    pub fn render(&self, source: &str) -> String {
+        let located = self.clone().locate(source);
+        let start = located.span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let line_text = &source[line_start..line_end];
+
+        let end = located.span.end.clamp(start, line_end);
+        let width = source[start..end].chars().count().max(1);
+
+        let number = located.line.to_string();
+        let gutter = " ".repeat(number.len());
+
+        format!(
+            "error: {message}\n{gutter}--> {line}:{column}\n{gutter} |\n{number} | {line_text}\n{gutter} | {pad}{carets}",
+            message = located.message,
+            line = located.line,
+            column = located.column,
+            pad = " ".repeat(located.column - 1),
+            carets = "^".repeat(width),
+        )
+    }
+}
+
+/// Parses the source text of a patch program.
+pub fn parse(source: &str) -> DslResult<Program> {
+    let tokens = lexer::tokenize(source).map_err(|err| err.locate(source))?;
+    parser::Parser::new(tokens)
+        .parse_program()
+        .map_err(|err| err.locate(source))
+}
+
+/// Parses the source text of a single expression, e.g. `a * sin(b) + 0.5`.
+pub fn parse_expr(source: &str) -> DslResult<parser::Expr> {
+    let tokens = lexer::tokenize(source).map_err(|err| err.locate(source))?;
+    parser::Parser::new(tokens)
+        .parse_single_expr()
+        .map_err(|err| err.locate(source))
+}
+
+/// Compiles the source text of a patch program into a [`Graph`], using the builtin processors.
+pub fn compile(source: &str) -> DslResult<Graph> {
+    compile_with(source, &ProcessorRegistry::default())
+}
+
+/// Compiles the source text of a patch program into a [`Graph`], creating processors from the given [`ProcessorRegistry`].
+pub fn compile_with(source: &str, registry: &ProcessorRegistry) -> DslResult<Graph> {
+    let program = parse(source)?;
+    let graph = GraphBuilder::new();
+    program
+        .lower(&graph, registry)
+        .map_err(|err| err.locate(source))?;
+    Ok(graph.build())
+}
+
+impl Program {
+    /// Adds the nodes described by this program to the given [`GraphBuilder`].
+    pub fn lower(&self, graph: &GraphBuilder, registry: &ProcessorRegistry) -> DslResult<()> {
+        lower::Lowerer::new(graph, registry).lower_program(self)
+    }
+}
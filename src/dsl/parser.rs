@@ -0,0 +1,410 @@
+use super::{
+    lexer::{Token, TokenKind},
+    DslError, DslResult, Span,
+};
+use crate::processor::Param;
+
+/// The largest index allowed in `in[N]` and `out[N]`, which limits the number of inputs and outputs a program's graph can have.
+pub const MAX_INDEX: usize = 1023;
+
+/// An identifier together with where it appears in the source.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Ident {
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Neg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+/// An input argument of a function call, e.g. `440` or `frequency: 440`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    /// The name of the input to connect to, or `None` for positional arguments.
+    pub name: Option<Ident>,
+    pub value: Expr,
+}
+
+/// A construction parameter of a function call, e.g. the `seed: 42` in `noise{seed: 42}()`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamArg {
+    pub name: Ident,
+    pub value: Param,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExprKind {
+    Number(f64),
+    Var(String),
+    /// A graph input, e.g. `in[0]`.
+    Input(usize),
+    Unary {
+        op: UnaryOp,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: BinaryOp,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    Call {
+        function: Ident,
+        params: Vec<ParamArg>,
+        args: Vec<Arg>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    /// `let name = value`
+    Let { name: Ident, value: Expr },
+    /// `out[index] = value`
+    Output {
+        index: usize,
+        target_span: Span,
+        value: Expr,
+    },
+}
+
+/// A parsed patch program: a list of statements, in order.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Program {
+    pub statements: Vec<Statement>,
+}
+
+pub struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.pos.min(self.tokens.len() - 1)]
+    }
+
+    fn peek_nth(&self, n: usize) -> &Token {
+        &self.tokens[(self.pos + n).min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind) -> bool {
+        if &self.peek().kind == kind {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, kind: TokenKind) -> DslResult<Token> {
+        if self.peek().kind == kind {
+            Ok(self.next())
+        } else {
+            Err(self.unexpected(&kind.describe()))
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> DslError {
+        let token = self.peek();
+        DslError::new(
+            format!("expected {expected}, found {}", token.kind.describe()),
+            token.span,
+        )
+    }
+
+    fn expect_ident(&mut self) -> DslResult<Ident> {
+        match self.peek().kind.clone() {
+            TokenKind::Ident(name) => {
+                let span = self.next().span;
+                Ok(Ident { name, span })
+            }
+            _ => Err(self.unexpected("identifier")),
+        }
+    }
+
+    fn expect_index(&mut self) -> DslResult<(usize, Span)> {
+        match self.peek().kind {
+            TokenKind::Number(value) if value >= 0.0 && value.fract() == 0.0 => {
+                let span = self.next().span;
+                if value > MAX_INDEX as f64 {
+                    return Err(DslError::new(
+                        format!("index {value} is out of range; indices go up to {MAX_INDEX}"),
+                        span,
+                    ));
+                }
+                Ok((value as usize, span))
+            }
+            _ => Err(self.unexpected("non-negative integer index")),
+        }
+    }
+
+    /// Parses the whole token stream into a [`Program`].
+    pub fn parse_program(&mut self) -> DslResult<Program> {
+        let mut program = Program::default();
+
+        loop {
+            while self.eat(&TokenKind::Separator) {}
+            if self.peek().kind == TokenKind::Eof {
+                break;
+            }
+
+            program.statements.push(self.parse_statement()?);
+
+            if !self.eat(&TokenKind::Separator) && self.peek().kind != TokenKind::Eof {
+                return Err(self.unexpected("end of statement"));
+            }
+        }
+
+        Ok(program)
+    }
+
+    /// Parses the whole token stream as a single expression.
+    pub fn parse_single_expr(&mut self) -> DslResult<Expr> {
+        while self.eat(&TokenKind::Separator) {}
+        let expr = self.parse_expr()?;
+        while self.eat(&TokenKind::Separator) {}
+        self.expect(TokenKind::Eof)?;
+        Ok(expr)
+    }
+
+    fn parse_statement(&mut self) -> DslResult<Statement> {
+        if self.eat(&TokenKind::Let) {
+            let name = self.expect_ident()?;
+            if matches!(name.name.as_str(), "in" | "out") {
+                return Err(DslError::new(
+                    format!("`{}` is reserved for graph inputs and outputs", name.name),
+                    name.span,
+                ));
+            }
+            self.expect(TokenKind::Eq)?;
+            let value = self.parse_expr()?;
+            return Ok(Statement::Let { name, value });
+        }
+
+        match &self.peek().kind {
+            TokenKind::Ident(name) if name == "out" => {
+                let start = self.next().span;
+                self.expect(TokenKind::LBracket)?;
+                let (index, _) = self.expect_index()?;
+                let end = self.expect(TokenKind::RBracket)?.span;
+                self.expect(TokenKind::Eq)?;
+                let value = self.parse_expr()?;
+                Ok(Statement::Output {
+                    index,
+                    target_span: start.to(end),
+                    value,
+                })
+            }
+            _ => Err(self.unexpected("`let` or `out[...]`")),
+        }
+    }
+
+    fn parse_expr(&mut self) -> DslResult<Expr> {
+        let mut lhs = self.parse_term()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Plus => BinaryOp::Add,
+                TokenKind::Minus => BinaryOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.parse_term()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn parse_term(&mut self) -> DslResult<Expr> {
+        let mut lhs = self.parse_unary()?;
+        loop {
+            let op = match self.peek().kind {
+                TokenKind::Star => BinaryOp::Mul,
+                TokenKind::Slash => BinaryOp::Div,
+                TokenKind::Percent => BinaryOp::Rem,
+                _ => return Ok(lhs),
+            };
+            self.next();
+            let rhs = self.parse_unary()?;
+            lhs = binary(op, lhs, rhs);
+        }
+    }
+
+    fn parse_unary(&mut self) -> DslResult<Expr> {
+        if self.peek().kind == TokenKind::Minus {
+            let start = self.next().span;
+            let expr = self.parse_unary()?;
+            // fold negative literals right away so they don't need a separate node
+            if let ExprKind::Number(value) = expr.kind {
+                return Ok(Expr {
+                    kind: ExprKind::Number(-value),
+                    span: start.to(expr.span),
+                });
+            }
+            return Ok(Expr {
+                span: start.to(expr.span),
+                kind: ExprKind::Unary {
+                    op: UnaryOp::Neg,
+                    expr: Box::new(expr),
+                },
+            });
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> DslResult<Expr> {
+        let token = self.peek().clone();
+        match token.kind {
+            TokenKind::Number(value) => {
+                self.next();
+                Ok(Expr {
+                    kind: ExprKind::Number(value),
+                    span: token.span,
+                })
+            }
+            TokenKind::LParen => {
+                self.next();
+                let mut expr = self.parse_expr()?;
+                let end = self.expect(TokenKind::RParen)?.span;
+                expr.span = token.span.to(end);
+                Ok(expr)
+            }
+            TokenKind::Ident(name) => {
+                self.next();
+                let ident = Ident {
+                    name,
+                    span: token.span,
+                };
+                match self.peek().kind {
+                    TokenKind::LBracket if ident.name == "in" => {
+                        self.next();
+                        let (index, _) = self.expect_index()?;
+                        let end = self.expect(TokenKind::RBracket)?.span;
+                        Ok(Expr {
+                            kind: ExprKind::Input(index),
+                            span: ident.span.to(end),
+                        })
+                    }
+                    TokenKind::LParen | TokenKind::LBrace => self.parse_call(ident),
+                    _ => Ok(Expr {
+                        span: ident.span,
+                        kind: ExprKind::Var(ident.name),
+                    }),
+                }
+            }
+            _ => Err(self.unexpected("expression")),
+        }
+    }
+
+    fn parse_call(&mut self, function: Ident) -> DslResult<Expr> {
+        let mut params = Vec::new();
+        if self.eat(&TokenKind::LBrace) {
+            while self.peek().kind != TokenKind::RBrace {
+                let name = self.expect_ident()?;
+                self.expect(TokenKind::Colon)?;
+                let (value, value_span) = self.parse_param_value()?;
+                params.push(ParamArg {
+                    span: name.span.to(value_span),
+                    name,
+                    value,
+                });
+                if !self.eat(&TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.expect(TokenKind::RBrace)?;
+        }
+
+        self.expect(TokenKind::LParen)?;
+        let mut args = Vec::new();
+        while self.peek().kind != TokenKind::RParen {
+            let is_named = matches!(self.peek().kind, TokenKind::Ident(_))
+                && self.peek_nth(1).kind == TokenKind::Colon;
+            let name = if is_named {
+                let name = self.expect_ident()?;
+                self.next();
+                Some(name)
+            } else {
+                None
+            };
+            let value = self.parse_expr()?;
+            args.push(Arg { name, value });
+            if !self.eat(&TokenKind::Comma) {
+                break;
+            }
+        }
+        let end = self.expect(TokenKind::RParen)?.span;
+
+        Ok(Expr {
+            span: function.span.to(end),
+            kind: ExprKind::Call {
+                function,
+                params,
+                args,
+            },
+        })
+    }
+
+    fn parse_param_value(&mut self) -> DslResult<(Param, Span)> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Number(value) => Ok((Param::Float(value), token.span)),
+            TokenKind::Minus => match self.peek().kind {
+                TokenKind::Number(value) => {
+                    let end = self.next().span;
+                    Ok((Param::Float(-value), token.span.to(end)))
+                }
+                _ => Err(self.unexpected("number")),
+            },
+            TokenKind::String(value) => Ok((Param::String(value), token.span)),
+            TokenKind::Ident(name) if name == "true" || name == "false" => {
+                Ok((Param::Bool(name == "true"), token.span))
+            }
+            kind => Err(DslError::new(
+                format!(
+                    "expected a number, string or boolean parameter value, found {}",
+                    kind.describe()
+                ),
+                token.span,
+            )),
+        }
+    }
+}
+
+fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Expr {
+    Expr {
+        span: lhs.span.to(rhs.span),
+        kind: ExprKind::Binary {
+            op,
+            lhs: Box::new(lhs),
+            rhs: Box::new(rhs),
+        },
+    }
+}
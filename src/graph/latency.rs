@@ -0,0 +1,204 @@
+//! Latency compensation: delaying the shorter of parallel paths through a [`Graph`], so that signals meeting at a node stay aligned.
+//!
+//! Each node's [`Process::latency`](crate::processor::Process::latency) delays everything downstream of it. When paths with different
+//! latencies meet at a node (e.g. a dry signal and a lookahead-limited copy of it being summed), the edges on the shorter paths are delayed
+//! to match the longest one. The graph outputs are aligned the same way, so the whole graph delays its inputs by [`Graph::latency`] samples.
+//!
+//! The delays are worked out whenever the graph is [`reset`](Graph::reset), and aren't part of the graph's structure (or its [`Patch`](crate::patch::Patch)).
+//! Only nodes running at the audio [`Rate`] take part: the latency of nodes running at a reduced rate is ignored, and their inputs aren't delayed.
+//! Edges closing a cycle aren't delayed either.
+//!
+//! The [`tail_length`](crate::processor::Process::tail_length)s of the nodes add up along paths in the same way, giving how long the graph
+//! keeps producing output after its inputs fall silent ([`Graph::tail_length`]).
+
+use std::collections::HashMap;
+
+use petgraph::prelude::{Direction, EdgeRef};
+
+use crate::{processor::Rate, signal::Sample};
+
+use super::{node::GraphNode, visit_order, EdgeIndex, Graph, NodeIndex};
+
+/// A fixed delay applied to the signal flowing along an edge, or to the inputs a bypassed [`Processor`](crate::processor::Processor) passes through.
+#[derive(Clone)]
+pub(crate) struct EdgeDelay {
+    buffer: Box<[Sample]>,
+    position: usize,
+}
+
+impl EdgeDelay {
+    pub(crate) fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![Sample::ZERO; len].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.buffer.len()
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buffer.fill(Sample::ZERO);
+        self.position = 0;
+    }
+
+    /// Returns the samples in the delay, from the oldest to the newest.
+    pub(crate) fn samples(&self) -> impl Iterator<Item = Sample> + '_ {
+        let (newest, oldest) = self.buffer.split_at(self.position);
+        oldest.iter().chain(newest.iter()).copied()
+    }
+
+    /// Replaces the samples in the delay with the given ones, from the oldest to the newest, unless their number doesn't match its length.
+    pub(crate) fn restore(&mut self, samples: &[f64]) {
+        if samples.len() != self.buffer.len() {
+            return;
+        }
+        for (sample, &value) in self.buffer.iter_mut().zip(samples) {
+            *sample = Sample::new(value);
+        }
+        self.position = 0;
+    }
+
+    /// Delays the given block of samples in place.
+    #[inline]
+    pub(crate) fn process(&mut self, block: &mut [Sample]) {
+        for sample in block.iter_mut() {
+            std::mem::swap(sample, &mut self.buffer[self.position]);
+            self.position = (self.position + 1) % self.buffer.len();
+        }
+    }
+}
+
+/// The compensating delays needed on a graph's edges, and the resulting latency of the whole graph.
+struct LatencyPlan {
+    delays: HashMap<EdgeIndex, usize>,
+    latency: usize,
+}
+
+impl Graph {
+    /// Returns the latency of the graph in samples, i.e. how much later its inputs arrive at its outputs due to the latency of its nodes.
+    ///
+    /// All outputs are delayed by this amount, so that they stay aligned with each other.
+    pub fn latency(&self) -> usize {
+        self.latency_plan().latency
+    }
+
+    /// Returns the number of samples the graph keeps producing output for after its inputs fall silent,
+    /// i.e. the longest sum of the [`tail_length`](crate::processor::Process::tail_length)s and latencies of the nodes along any path to an output.
+    pub fn tail_length(&self) -> usize {
+        let mut order = Vec::with_capacity(self.digraph.node_count());
+        visit_order(&self.digraph, &mut order);
+
+        let mut ends: HashMap<NodeIndex, usize> = HashMap::new();
+        for &node in order.iter() {
+            let (tail_length, latency) = match &self.digraph[node] {
+                GraphNode::Processor(processor) if processor.rate() != Rate::Audio => {
+                    (processor.tail_length(), 0)
+                }
+                GraphNode::Processor(processor) => (processor.tail_length(), processor.latency()),
+                GraphNode::Passthrough(_) => (0, 0),
+            };
+            let start = self
+                .digraph
+                .edges_directed(node, Direction::Incoming)
+                .filter_map(|edge| ends.get(&edge.source()))
+                .copied()
+                .max()
+                .unwrap_or(0);
+            ends.insert(
+                node,
+                start.saturating_add(tail_length).saturating_add(latency),
+            );
+        }
+
+        self.output_nodes
+            .iter()
+            .filter_map(|node| ends.get(node))
+            .copied()
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Returns the compensating delay on the given edge, in samples.
+    pub fn edge_delay(&self, edge: EdgeIndex) -> usize {
+        self.edge_delays.get(&edge).map_or(0, EdgeDelay::len)
+    }
+
+    /// Recreates the compensating delays, keeping the ones (and the samples in them) whose length hasn't changed.
+    pub(super) fn reset_edge_delays(&mut self) {
+        let plan = self.latency_plan();
+
+        let mut previous = std::mem::take(&mut self.edge_delays);
+        for (edge, len) in plan.delays {
+            let delay = match previous.remove(&edge) {
+                Some(delay) if delay.len() == len => delay,
+                _ => EdgeDelay::new(len),
+            };
+            self.edge_delays.insert(edge, delay);
+        }
+    }
+
+    fn latency_plan(&self) -> LatencyPlan {
+        let mut order = Vec::with_capacity(self.digraph.node_count());
+        visit_order(&self.digraph, &mut order);
+
+        // when each node's inputs arrive, and when its outputs leave, relative to the graph inputs
+        let mut arrivals: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut departures: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut delays = HashMap::new();
+
+        for &node in order.iter() {
+            let (reduced_rate, latency) = match &self.digraph[node] {
+                GraphNode::Processor(processor) if processor.rate() != Rate::Audio => (true, 0),
+                GraphNode::Processor(processor) => (false, processor.latency()),
+                GraphNode::Passthrough(_) => (false, 0),
+            };
+
+            // edges from nodes that haven't been visited yet close a cycle
+            let incoming = self
+                .digraph
+                .edges_directed(node, Direction::Incoming)
+                .filter_map(|edge| Some((edge.id(), *departures.get(&edge.source())?)))
+                .collect::<Vec<_>>();
+
+            let arrival = incoming
+                .iter()
+                .map(|&(_, departure)| departure)
+                .max()
+                .unwrap_or(0);
+            if !reduced_rate {
+                for &(edge, departure) in incoming.iter() {
+                    if departure < arrival {
+                        delays.insert(edge, arrival - departure);
+                    }
+                }
+            }
+
+            arrivals.insert(node, arrival);
+            departures.insert(node, arrival + latency);
+        }
+
+        // align the graph outputs with the latest one
+        let latency = self
+            .output_nodes
+            .iter()
+            .filter_map(|node| arrivals.get(node))
+            .copied()
+            .max()
+            .unwrap_or(0);
+        for node in self.output_nodes.iter() {
+            let Some(&arrival) = arrivals.get(node) else {
+                continue;
+            };
+            if arrival == latency {
+                continue;
+            }
+            for edge in self.digraph.edges_directed(*node, Direction::Incoming) {
+                *delays.entry(edge.id()).or_default() += latency - arrival;
+            }
+        }
+
+        LatencyPlan { delays, latency }
+    }
+}
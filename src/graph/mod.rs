@@ -1,4 +1,7 @@
+use std::collections::HashMap;
+
 use edge::Edge;
+use latency::EdgeDelay;
 use node::GraphNode;
 use petgraph::{
     prelude::{Direction, EdgeRef, StableDiGraph},
@@ -6,12 +9,20 @@ use petgraph::{
 };
 
 use crate::{
-    processor::{Process, Processor},
-    signal::Buffer,
+    builtins::coerce::Coercion,
+    processor::{BypassHandle, Process, Processor, Rate, SignalKind},
+    signal::{Buffer, Sample, Timing},
 };
 
 pub mod edge;
+pub mod latency;
 pub mod node;
+pub mod optimize;
+pub mod oversample;
+pub mod poly;
+pub mod snapshot;
+pub mod subgraph;
+pub mod validate;
 
 pub type GraphIx = u32;
 pub type NodeIndex = petgraph::graph::NodeIndex<GraphIx>;
@@ -45,6 +56,48 @@ pub enum GraphConstructionError {
     MismatchedGraphs,
     #[error("Operation `{op}` invalid: Node type `{kind}` has multiple outputs")]
     NodeHasMultipleOutputs { op: String, kind: String },
+    #[error("Node {node:?} has no port named `{name}`")]
+    UnknownPort { node: NodeIndex, name: String },
+    #[error("Port index {index} is out of range for node {node:?}, which has {num_ports} {direction} port(s)")]
+    PortOutOfRange {
+        node: NodeIndex,
+        direction: PortDirection,
+        index: u32,
+        num_ports: usize,
+    },
+    #[error("Node {0:?} does not exist in the graph")]
+    UnknownNode(NodeIndex),
+    #[error("The graph builder has already built its graph")]
+    AlreadyBuilt,
+    #[error("Node {0:?} is a graph input or output, not a processor")]
+    NotAProcessor(NodeIndex),
+    #[error("A bus needs at least one channel")]
+    EmptyBus,
+    #[error("Cannot connect {source_kind} output {source_output} of node {source_node:?} to {target_kind} input {target_input} of node {target:?}")]
+    IncompatibleSignals {
+        source_node: NodeIndex,
+        source_output: u32,
+        source_kind: SignalKind,
+        target: NodeIndex,
+        target_input: u32,
+        target_kind: SignalKind,
+    },
+}
+
+/// Whether a port is an input or an output of its node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortDirection {
+    Input,
+    Output,
+}
+
+impl std::fmt::Display for PortDirection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Input => f.write_str("input"),
+            Self::Output => f.write_str("output"),
+        }
+    }
 }
 
 pub type GraphRunResult<T> = Result<T, GraphRunError>;
@@ -70,7 +123,10 @@ pub struct Graph {
     needs_visitor_alloc: bool,
 
     // cached internal state to avoid allocations in `process()`
-    edge_cache: Vec<(NodeIndex, Edge)>,
+    edge_cache: Vec<(EdgeIndex, NodeIndex, Edge)>,
+
+    // delays compensating for the latency of other paths into the same node
+    edge_delays: HashMap<EdgeIndex, EdgeDelay>,
 
     // cached visitor state for graph traversal
     visit_path: Vec<NodeIndex>,
@@ -139,10 +195,58 @@ impl Graph {
         std::mem::replace(&mut self.digraph[node], GraphNode::new_processor(processor))
     }
 
+    /// Removes the processor at the given [`NodeIndex`] along with its edges, returning its [`GraphNode`].
+    ///
+    /// Graph inputs and outputs can't be removed, as the graph's inputs and outputs are referred to by position.
+    pub fn remove_processor(&mut self, node: NodeIndex) -> GraphConstructionResult<GraphNode> {
+        match self.digraph.node_weight(node) {
+            Some(GraphNode::Processor(_)) => {
+                self.needs_reset = true;
+                self.needs_prepare = true;
+                self.needs_visitor_alloc = true;
+                Ok(self
+                    .digraph
+                    .remove_node(node)
+                    .expect("the node was just found"))
+            }
+            Some(GraphNode::Passthrough(_)) => Err(GraphConstructionError::NotAProcessor(node)),
+            None => Err(GraphConstructionError::UnknownNode(node)),
+        }
+    }
+
+    /// Sets the [`Rate`] the processor at the given [`NodeIndex`] runs at.
+    ///
+    /// Nodes running at a reduced rate are processed with shorter buffers, and their outputs are interpolated where they feed
+    /// nodes running at a higher rate. Graph inputs and outputs always run at the audio rate.
+    pub fn set_rate(&mut self, node: NodeIndex, rate: Rate) -> GraphConstructionResult<()> {
+        match self.digraph.node_weight_mut(node) {
+            Some(GraphNode::Processor(processor)) => {
+                processor.set_rate(rate);
+                self.needs_reset = true;
+                self.needs_prepare = true;
+                Ok(())
+            }
+            Some(GraphNode::Passthrough(_)) => Err(GraphConstructionError::NotAProcessor(node)),
+            None => Err(GraphConstructionError::UnknownNode(node)),
+        }
+    }
+
+    /// Returns a [`BypassHandle`] for bypassing or muting the processor at the given [`NodeIndex`] while the graph is running.
+    pub fn bypass_handle(&self, node: NodeIndex) -> GraphConstructionResult<BypassHandle> {
+        match self.digraph.node_weight(node) {
+            Some(GraphNode::Processor(processor)) => Ok(processor.bypass_handle()),
+            Some(GraphNode::Passthrough(_)) => Err(GraphConstructionError::NotAProcessor(node)),
+            None => Err(GraphConstructionError::UnknownNode(node)),
+        }
+    }
+
     /// Connects two [`GraphNode`]s with a new [`Edge`].
     /// The signal will flow from the `source` [`GraphNode`]'s `source_output`-th output to the `target` [`GraphNode`]'s `target_input`-th input.
     ///
     /// Duplicate edges will not be recreated, and instead the existing one will be returned.
+    ///
+    /// If the ports carry different [`SignalKind`]s, a converting node is inserted in between where sensible (see [`Coercion`]),
+    /// and the returned edge is the one into `target`. Kinds that can't be converted are an error.
     pub fn connect(
         &mut self,
         source: NodeIndex,
@@ -154,15 +258,89 @@ impl Graph {
             return Err(GraphConstructionError::FeedbackLoop);
         }
 
-        // check if the edge already exists
+        let num_outputs = self
+            .digraph
+            .node_weight(source)
+            .ok_or(GraphConstructionError::UnknownNode(source))?
+            .outputs()
+            .len();
+        if source_output as usize >= num_outputs {
+            return Err(GraphConstructionError::PortOutOfRange {
+                node: source,
+                direction: PortDirection::Output,
+                index: source_output,
+                num_ports: num_outputs,
+            });
+        }
+
+        let num_inputs = self
+            .digraph
+            .node_weight(target)
+            .ok_or(GraphConstructionError::UnknownNode(target))?
+            .inputs()
+            .len();
+        if target_input as usize >= num_inputs {
+            return Err(GraphConstructionError::PortOutOfRange {
+                node: target,
+                direction: PortDirection::Input,
+                index: target_input,
+                num_ports: num_inputs,
+            });
+        }
+
+        let source_kind = self.digraph[source].output_spec()[source_output as usize].kind;
+        let target_kind = self.digraph[target].input_spec()[target_input as usize].kind;
+        let coercion = Coercion::between(source_kind, target_kind).ok_or(
+            GraphConstructionError::IncompatibleSignals {
+                source_node: source,
+                source_output,
+                source_kind,
+                target,
+                target_input,
+                target_kind,
+            },
+        )?;
+
+        let Some(converter) = coercion.processor() else {
+            return Ok(self.add_edge_once(source, source_output, target, target_input));
+        };
+
+        // reuse the converter inserted by an earlier identical connection
+        for edge in self.digraph.edges_directed(target, Direction::Incoming) {
+            let node = edge.source();
+            if edge.weight().target_input == target_input
+                && self.digraph[node].name() == converter.name()
+                && self
+                    .digraph
+                    .edges_directed(node, Direction::Incoming)
+                    .any(|input| {
+                        input.source() == source && input.weight().source_output == source_output
+                    })
+            {
+                return Ok(edge.id());
+            }
+        }
+
+        let converter = self.add_processor_object(converter);
+        self.add_edge_once(source, source_output, converter, 0);
+        Ok(self.add_edge_once(converter, 0, target, target_input))
+    }
+
+    /// Adds an edge unless the same one already exists, returning its index.
+    fn add_edge_once(
+        &mut self,
+        source: NodeIndex,
+        source_output: u32,
+        target: NodeIndex,
+        target_input: u32,
+    ) -> EdgeIndex {
         for edge in self.digraph.edges_directed(target, Direction::Incoming) {
             let weight = edge.weight();
             if edge.source() == source
                 && weight.source_output == source_output
                 && weight.target_input == target_input
             {
-                // edge already exists
-                return Ok(edge.id());
+                return edge.id();
             }
         }
 
@@ -170,10 +348,8 @@ impl Graph {
         self.needs_prepare = true;
         self.needs_visitor_alloc = true;
 
-        let edge = self
-            .digraph
-            .add_edge(source, target, Edge::new(source_output, target_input));
-        Ok(edge)
+        self.digraph
+            .add_edge(source, target, Edge::new(source_output, target_input))
     }
 
     /// Returns the number of input [`GraphNode`]s in the graph.
@@ -214,7 +390,7 @@ impl Graph {
 
     /// Copies the given data into the input [`Buffer`] of the input [`GraphNode`] at the given index.
     #[inline]
-    pub fn copy_input(&mut self, input_index: usize, data: &Buffer) {
+    pub fn copy_input(&mut self, input_index: usize, data: &[Sample]) {
         let input_index = self
             .input_nodes
             .get(input_index)
@@ -274,15 +450,7 @@ impl Graph {
 
     #[inline]
     fn reset_visitor(&mut self) {
-        self.visit_path.clear();
-        let mut visitor = DfsPostOrder::empty(&self.digraph);
-        for node in self.digraph.externals(Direction::Incoming) {
-            visitor.stack.push(node);
-        }
-        while let Some(node) = visitor.next(&self.digraph) {
-            self.visit_path.push(node);
-        }
-        self.visit_path.reverse();
+        visit_order(&self.digraph, &mut self.visit_path);
     }
 
     /// Visits each [`GraphNode`] in the graph in breadth-first order, calling the given closuure with a mutable reference to the graph alongside each [`NodeIndex`].
@@ -328,6 +496,8 @@ impl Graph {
             max_edges = max_edges.max(num_inputs);
         });
 
+        self.reset_edge_delays();
+
         // preallocate the edge cache used in `process()`
         // the number of edges per node is likely relatively small, so we round up the cache size just to be sure that no allocations happen in `process()`
         self.edge_cache = Vec::with_capacity((max_edges * 2).next_power_of_two());
@@ -345,6 +515,23 @@ impl Graph {
         self.needs_prepare = false;
     }
 
+    /// Clears the internal state of all [`GraphNode`]s (see [`Process::reset`]) and of the latency compensation delays, and silences all buffers.
+    ///
+    /// Unlike [`reset`](Graph::reset), this doesn't reallocate anything, and can be called at any time.
+    pub fn reset_state(&mut self) {
+        for node in self.digraph.node_weights_mut() {
+            if let GraphNode::Processor(processor) = node {
+                processor.reset();
+            }
+            for buffer in node.inputs_mut() {
+                buffer.fill(Sample::ZERO);
+            }
+        }
+        for delay in self.edge_delays.values_mut() {
+            delay.clear();
+        }
+    }
+
     /// Returns a mutable reference to the input [`Buffer`] of the [`GraphNode`] at the given [`NodeIndex`] and input index.
     #[inline]
     pub fn get_node_input_mut(&mut self, node: NodeIndex, input_index: usize) -> &mut Buffer {
@@ -398,9 +585,9 @@ impl Graph {
                 graph
                     .digraph
                     .edges_directed(node_id, Direction::Incoming)
-                    .map(|edge| (edge.source(), *edge.weight())),
+                    .map(|edge| (edge.id(), edge.source(), *edge.weight())),
             );
-            for (source_id, edge) in graph.edge_cache.drain(..) {
+            for (edge_id, source_id, edge) in graph.edge_cache.drain(..) {
                 let Edge {
                     source_output,
                     target_input,
@@ -408,16 +595,39 @@ impl Graph {
 
                 let (source, target) = graph.digraph.index_twice_mut(source_id, node_id);
 
-                let source_buffer = match source {
-                    GraphNode::Processor(processor) => processor.output(source_output as usize),
-                    GraphNode::Passthrough(buffer) => buffer,
+                let (source_buffer, source_timing, history, interpolate) = match source {
+                    GraphNode::Processor(processor) => {
+                        let output = source_output as usize;
+                        (
+                            processor.output(output),
+                            processor.output_timing(),
+                            processor.output_history(output),
+                            processor.interpolates(output),
+                        )
+                    }
+                    GraphNode::Passthrough(buffer) => {
+                        (&*buffer, Timing::AUDIO, [Sample::default(); 2], false)
+                    }
                 };
 
-                let target_buffer = match target {
-                    GraphNode::Processor(processor) => processor.input_mut(target_input as usize),
-                    GraphNode::Passthrough(buffer) => buffer,
+                let (target_buffer, timing) = match target {
+                    GraphNode::Processor(processor) => {
+                        let timing = processor.input_timing();
+                        (processor.input_mut(target_input as usize), timing)
+                    }
+                    GraphNode::Passthrough(buffer) => (buffer, Timing::AUDIO),
                 };
-                target_buffer.copy_from_slice(source_buffer);
+                target_buffer.copy_resampled(
+                    timing,
+                    source_buffer,
+                    source_timing,
+                    history,
+                    interpolate,
+                );
+
+                if let Some(delay) = graph.edge_delays.get_mut(&edge_id) {
+                    delay.process(target_buffer);
+                }
             }
 
             // process the node
@@ -430,3 +640,16 @@ impl Graph {
         write!(writer, "{:?}", petgraph::dot::Dot::new(&self.digraph))
     }
 }
+
+/// Fills `path` with the nodes of the graph in processing order: every node comes after the nodes feeding it, except along cycles.
+fn visit_order(digraph: &DiGraph, path: &mut Vec<NodeIndex>) {
+    path.clear();
+    let mut visitor = DfsPostOrder::empty(digraph);
+    for node in digraph.externals(Direction::Incoming) {
+        visitor.stack.push(node);
+    }
+    while let Some(node) = visitor.next(digraph) {
+        path.push(node);
+    }
+    path.reverse();
+}
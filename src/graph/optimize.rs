@@ -0,0 +1,387 @@
+//! Optimization passes that simplify a [`Graph`] without changing its output.
+
+use std::collections::{hash_map::Entry, HashMap, HashSet};
+
+use petgraph::{
+    prelude::{Direction, EdgeRef},
+    visit::{Dfs, Reversed},
+};
+
+use crate::{
+    builtins::{
+        expr::{BinaryOp, Expr, ExprProc, UnaryOp},
+        math::ConstantProc,
+    },
+    processor::{BypassState, Processor},
+};
+
+use super::{edge::Edge, node::GraphNode, Graph, NodeIndex};
+
+/// Identifies a node by its processor type, parameters and input connections (`(input, source, source output)`), for finding duplicates.
+type NodeKey = (String, Vec<(u32, NodeIndex, u32)>);
+
+/// The number of changes made by each pass of [`Graph::optimize`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OptimizeStats {
+    /// Math nodes fed only by constants that were replaced by a single constant.
+    pub folded: usize,
+    /// Nodes that didn't contribute to any graph output and were removed.
+    pub removed: usize,
+    /// Duplicate constants and math nodes that were merged into an identical node.
+    pub deduplicated: usize,
+    /// Math nodes that were fused into an [`ExprProc`] together with (some of) their inputs.
+    pub fused: usize,
+}
+
+impl OptimizeStats {
+    /// Returns `true` if the optimizer changed the graph.
+    pub fn changed(&self) -> bool {
+        *self != Self::default()
+    }
+}
+
+impl Graph {
+    /// Simplifies the graph without changing its output:
+    ///
+    /// - Math nodes whose inputs are all constants are folded into a single [`ConstantProc`].
+    /// - Identical constants, and identical math nodes with identical inputs, are merged.
+    /// - Chains of math nodes (and the constants feeding them) are fused into single [`ExprProc`] nodes.
+    /// - Nodes that don't contribute to any graph output are removed.
+    ///
+    /// Only the processors in [`builtins::math`](crate::builtins::math) and [`ExprProc`]s are treated as math nodes, since they're known to have no state.
+    /// Math nodes whose [`Rate`](crate::processor::Rate) has been changed, or that are bypassed or can be through a [`BypassHandle`](crate::processor::BypassHandle)
+    /// (or a clone of the graph), are left as they are, since the nodes replacing them wouldn't behave the same.
+    /// Graphs containing cycles are left unchanged.
+    pub fn optimize(&mut self) -> OptimizeStats {
+        let mut stats = OptimizeStats::default();
+        if petgraph::algo::is_cyclic_directed(&self.digraph) {
+            return stats;
+        }
+
+        stats.removed += self.remove_dead_nodes();
+        stats.folded += self.fold_constants();
+        stats.deduplicated += self.dedupe_nodes();
+        stats.fused += self.fuse_math_nodes();
+        stats.removed += self.remove_dead_nodes();
+
+        if stats.changed() {
+            self.needs_reset = true;
+            self.needs_prepare = true;
+            self.needs_visitor_alloc = true;
+        }
+
+        stats
+    }
+
+    /// Removes all processor nodes that don't contribute to any graph output, returning how many were removed.
+    pub fn remove_dead_nodes(&mut self) -> usize {
+        let mut live = HashSet::new();
+        let reversed = Reversed(&self.digraph);
+        for &output in self.output_nodes.iter() {
+            let mut dfs = Dfs::new(reversed, output);
+            while let Some(node) = dfs.next(reversed) {
+                live.insert(node);
+            }
+        }
+
+        let dead = self
+            .digraph
+            .node_indices()
+            .filter(|node| {
+                !live.contains(node) && matches!(self.digraph[*node], GraphNode::Processor(_))
+            })
+            .collect::<Vec<_>>();
+
+        for &node in dead.iter() {
+            self.digraph.remove_node(node);
+        }
+
+        if !dead.is_empty() {
+            self.needs_reset = true;
+            self.needs_prepare = true;
+            self.needs_visitor_alloc = true;
+        }
+
+        dead.len()
+    }
+
+    fn topological_order(&self) -> Vec<NodeIndex> {
+        // `optimize` checks for cycles up front
+        petgraph::algo::toposort(&self.digraph, None).unwrap_or_default()
+    }
+
+    /// Returns the single source feeding each input of the given node, `None` for unconnected inputs, or `None` overall if any input has several sources.
+    fn input_sources(&self, node: NodeIndex) -> Option<Vec<Option<(NodeIndex, u32)>>> {
+        let mut sources = vec![None; self.digraph[node].inputs().len()];
+        for edge in self.digraph.edges_directed(node, Direction::Incoming) {
+            let source = sources.get_mut(edge.weight().target_input as usize)?;
+            if source.is_some() {
+                return None;
+            }
+            *source = Some((edge.source(), edge.weight().source_output));
+        }
+        Some(sources)
+    }
+
+    /// Returns the default value of the given input of the given node.
+    fn input_default(&self, node: NodeIndex, input: usize) -> f64 {
+        self.digraph[node].input_spec()[input].default_value
+    }
+
+    fn fold_constants(&mut self) -> usize {
+        let mut folded = 0;
+
+        for node in self.topological_order() {
+            let Some(expr) = math_expr(&self.digraph[node]) else {
+                continue;
+            };
+            if matches!(expr, Expr::Const(_)) {
+                continue;
+            }
+            let Some(sources) = self.input_sources(node) else {
+                continue;
+            };
+
+            let values = sources
+                .iter()
+                .enumerate()
+                .map(|(input, source)| match source {
+                    Some((source, _)) => match math_expr(&self.digraph[*source]) {
+                        Some(Expr::Const(value)) => Some(value),
+                        _ => None,
+                    },
+                    None => Some(self.input_default(node, input)),
+                })
+                .collect::<Option<Vec<_>>>();
+            let Some(values) = values else {
+                continue;
+            };
+
+            let value = expr.eval(&values);
+            self.replace_node(node, Processor::new(ConstantProc::new(value)), &[]);
+            folded += 1;
+        }
+
+        folded
+    }
+
+    fn dedupe_nodes(&mut self) -> usize {
+        let mut seen: HashMap<NodeKey, NodeIndex> = HashMap::new();
+        let mut deduplicated = 0;
+
+        for node in self.topological_order() {
+            let GraphNode::Processor(processor) = &self.digraph[node] else {
+                continue;
+            };
+            if math_expr(&self.digraph[node]).is_none() {
+                continue;
+            }
+
+            let kind = format!("{}{:?}", processor.name(), processor.params());
+            let mut inputs = self
+                .digraph
+                .edges_directed(node, Direction::Incoming)
+                .map(|edge| {
+                    let weight = edge.weight();
+                    (weight.target_input, edge.source(), weight.source_output)
+                })
+                .collect::<Vec<_>>();
+            inputs.sort();
+
+            match seen.entry((kind, inputs)) {
+                Entry::Occupied(original) => {
+                    self.redirect_outputs(node, *original.get());
+                    self.digraph.remove_node(node);
+                    deduplicated += 1;
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(node);
+                }
+            }
+        }
+
+        deduplicated
+    }
+
+    fn fuse_math_nodes(&mut self) -> usize {
+        let mut fused = 0;
+        let mut removed = HashSet::new();
+
+        for node in self.topological_order().into_iter().rev() {
+            if removed.contains(&node) {
+                continue;
+            }
+            let Some(expr) = math_expr(&self.digraph[node]) else {
+                continue;
+            };
+            if matches!(expr, Expr::Const(_)) {
+                continue;
+            }
+
+            let mut fusion = Fusion::default();
+            let Some(expr) = fusion.inline(self, node, &expr) else {
+                continue;
+            };
+            if fusion.absorbed.is_empty() && !fusion.inlined_constants {
+                continue;
+            }
+
+            self.replace_node(node, Processor::new(ExprProc::new(expr)), &fusion.inputs);
+            for absorbed in fusion.absorbed {
+                self.digraph.remove_node(absorbed);
+                removed.insert(absorbed);
+            }
+            fused += 1;
+        }
+
+        fused
+    }
+
+    /// Replaces the processor of the given node, connecting the given sources to its inputs in order instead of its previous inputs.
+    fn replace_node(&mut self, node: NodeIndex, processor: Processor, inputs: &[(NodeIndex, u32)]) {
+        let incoming = self
+            .digraph
+            .edges_directed(node, Direction::Incoming)
+            .map(|edge| edge.id())
+            .collect::<Vec<_>>();
+        for edge in incoming {
+            self.digraph.remove_edge(edge);
+        }
+
+        self.digraph[node] = GraphNode::Processor(processor);
+
+        for (input, &(source, source_output)) in inputs.iter().enumerate() {
+            self.digraph
+                .add_edge(source, node, Edge::new(source_output, input as u32));
+        }
+    }
+
+    /// Connects everything the given node's outputs are connected to to the same outputs of `replacement` instead.
+    fn redirect_outputs(&mut self, node: NodeIndex, replacement: NodeIndex) {
+        let outgoing = self
+            .digraph
+            .edges_directed(node, Direction::Outgoing)
+            .map(|edge| (edge.target(), *edge.weight()))
+            .collect::<Vec<_>>();
+        for (target, weight) in outgoing {
+            let exists = self
+                .digraph
+                .edges_connecting(replacement, target)
+                .any(|edge| *edge.weight() == weight);
+            if !exists {
+                self.digraph.add_edge(replacement, target, weight);
+            }
+        }
+    }
+}
+
+/// State for fusing a math node with the math nodes feeding it.
+#[derive(Default)]
+struct Fusion {
+    /// The outside sources feeding the fused expression, in input order.
+    inputs: Vec<(NodeIndex, u32)>,
+    /// Math nodes that are now part of the fused expression.
+    absorbed: Vec<NodeIndex>,
+    inlined_constants: bool,
+}
+
+impl Fusion {
+    /// Returns the given node's expression with its inputs replaced by constants, the expressions of math nodes that only feed this node, or new inputs.
+    fn inline(&mut self, graph: &Graph, node: NodeIndex, expr: &Expr) -> Option<Expr> {
+        let sources = graph.input_sources(node)?;
+
+        let mut inputs = Vec::with_capacity(sources.len());
+        for (input, source) in sources.into_iter().enumerate() {
+            let Some((source, source_output)) = source else {
+                inputs.push(Expr::Const(graph.input_default(node, input)));
+                continue;
+            };
+
+            let source_expr = math_expr(&graph.digraph[source]);
+            let fan_out = graph
+                .digraph
+                .edges_directed(source, Direction::Outgoing)
+                .count();
+
+            let inlined = match source_expr {
+                Some(Expr::Const(value)) => {
+                    self.inlined_constants = true;
+                    Some(Expr::Const(value))
+                }
+                Some(source_expr) if fan_out == 1 => {
+                    let inlined = self.inline(graph, source, &source_expr);
+                    if inlined.is_some() {
+                        self.absorbed.push(source);
+                    }
+                    inlined
+                }
+                _ => None,
+            };
+
+            let input_expr = inlined.unwrap_or_else(|| {
+                let index = match self
+                    .inputs
+                    .iter()
+                    .position(|&i| i == (source, source_output))
+                {
+                    Some(index) => index,
+                    None => {
+                        self.inputs.push((source, source_output));
+                        self.inputs.len() - 1
+                    }
+                };
+                Expr::Input(index)
+            });
+            inputs.push(input_expr);
+        }
+
+        Some(substitute(expr, &inputs))
+    }
+}
+
+/// Replaces each [`Expr::Input`] in the expression with the corresponding expression from `inputs`.
+fn substitute(expr: &Expr, inputs: &[Expr]) -> Expr {
+    match expr {
+        Expr::Const(value) => Expr::Const(*value),
+        Expr::Input(index) => inputs[*index].clone(),
+        Expr::Unary(op, x) => Expr::Unary(*op, Box::new(substitute(x, inputs))),
+        Expr::Binary(op, a, b) => Expr::Binary(
+            *op,
+            Box::new(substitute(a, inputs)),
+            Box::new(substitute(b, inputs)),
+        ),
+    }
+}
+
+/// Returns what the given node computes as an expression over its inputs, if it's a stateless math node.
+fn math_expr(node: &GraphNode) -> Option<Expr> {
+    let GraphNode::Processor(processor) = node else {
+        return None;
+    };
+
+    // the nodes replacing math nodes run at their natural rate, and can't be bypassed the same way
+    if processor.rate() != processor.natural_rate()
+        || processor.bypass_state() != BypassState::Active
+        || processor.bypass_is_shared()
+    {
+        return None;
+    }
+
+    if let Some(constant) = processor.downcast_ref::<ConstantProc>() {
+        return Some(Expr::Const(constant.value()));
+    }
+    if let Some(expr) = processor.downcast_ref::<ExprProc>() {
+        return Some(expr.expr().clone());
+    }
+    if let Some(op) = UnaryOp::from_processor_name(processor.name()) {
+        return Some(Expr::Unary(op, Box::new(Expr::Input(0))));
+    }
+    if let Some(op) = BinaryOp::from_processor_name(processor.name()) {
+        return Some(Expr::Binary(
+            op,
+            Box::new(Expr::Input(0)),
+            Box::new(Expr::Input(1)),
+        ));
+    }
+    None
+}
@@ -0,0 +1,605 @@
+//! Running processors at a multiple of the graph's sample rate, to reduce aliasing from nonlinear processing.
+
+use crate::{
+    builder::typed_node::{SingleOutput, StaticPorts},
+    processor::{Param, Params, Process, SignalSpec},
+    registry::{ParamError, ParamsExt, ProcessorRegistry},
+    signal::{Buffer, Sample},
+};
+
+/// The number of non-zero taps on either side of the center tap of the first (steepest) halfband stage's filter.
+const FIRST_STAGE_TAPS: usize = 32;
+
+/// The number of non-zero taps on either side of the center tap of the filters of the later stages,
+/// which only need to separate the original signal band from its images, and can have a much wider transition band.
+const LATER_STAGE_TAPS: usize = 8;
+
+/// The Kaiser window parameter for the halfband filters, for a stopband attenuation of about 100 dB.
+const KAISER_BETA: f64 = 10.0;
+
+/// A [`Process`] wrapper that runs the wrapped processor (or [`SubGraph`](super::subgraph::SubGraph)) at 2, 4, 8 or 16 times the graph's sample rate.
+///
+/// Inputs are upsampled and outputs downsampled again by a cascade of 2x polyphase halfband FIR filters, so that the harmonics
+/// produced by nonlinear processing (e.g. [`TanhProc`](crate::builtins::math::TanhProc) distortion) above the original Nyquist frequency
+/// are filtered out instead of aliasing back into the audible range.
+///
+/// The filters are linear-phase, so all outputs are delayed by the same whole number of samples, which is reported as the node's
+/// [`latency`](Process::latency) together with the latency of the wrapped processor (as reported when the node is created).
+///
+/// # Inputs
+///
+/// The inputs of the wrapped processor.
+///
+/// # Outputs
+///
+/// The outputs of the wrapped processor.
+#[derive(Clone)]
+pub struct Oversample<P> {
+    processor: P,
+    factor: usize,
+    upsamplers: Vec<Vec<HalfbandUpsampler>>,
+    downsamplers: Vec<Vec<HalfbandDownsampler>>,
+    /// Delays for the outputs of the wrapped processor, rounding the total latency up to a whole number of samples at the graph's rate.
+    delays: Vec<DelayLine>,
+    latency: usize,
+    inner_inputs: Vec<Buffer>,
+    inner_outputs: Vec<Buffer>,
+    scratch: (Vec<f64>, Vec<f64>),
+}
+
+impl<P: Process> Oversample<P> {
+    /// The name this processor is saved and registered under, whatever processor it wraps.
+    pub const NAME: &'static str = concat!(module_path!(), "::Oversample");
+
+    /// Creates a new [`Oversample`] node running the given processor at `factor` times the graph's sample rate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` isn't 2, 4, 8 or 16.
+    pub fn new(processor: P, factor: usize) -> Self {
+        assert!(
+            matches!(factor, 2 | 4 | 8 | 16),
+            "Oversampling factor must be 2, 4, 8 or 16"
+        );
+        let num_stages = factor.trailing_zeros() as usize;
+        let stage_taps = |stage: usize| {
+            if stage == 0 {
+                FIRST_STAGE_TAPS
+            } else {
+                LATER_STAGE_TAPS
+            }
+        };
+
+        let num_inputs = processor.num_inputs();
+        let num_outputs = processor.num_outputs();
+
+        let upsamplers = (0..num_inputs)
+            .map(|_| {
+                (0..num_stages)
+                    .map(|stage| HalfbandUpsampler::new(stage_taps(stage)))
+                    .collect()
+            })
+            .collect();
+        let downsamplers = (0..num_outputs)
+            .map(|_| {
+                (0..num_stages)
+                    .map(|stage| HalfbandDownsampler::new(stage_taps(stage)))
+                    .collect()
+            })
+            .collect();
+
+        // each stage delays by `4 * taps - 2` samples at its higher rate, counting both the up- and downsampling filter
+        let inner_latency: usize = (0..num_stages)
+            .map(|stage| (4 * stage_taps(stage) - 2) << (num_stages - stage - 1))
+            .sum();
+        let inner_latency = inner_latency + processor.latency();
+        let padding = (factor - inner_latency % factor) % factor;
+
+        Self {
+            processor,
+            factor,
+            upsamplers,
+            downsamplers,
+            delays: (0..num_outputs).map(|_| DelayLine::new(padding)).collect(),
+            latency: (inner_latency + padding) / factor,
+            inner_inputs: vec![Buffer::zeros(0); num_inputs],
+            inner_outputs: vec![Buffer::zeros(0); num_outputs],
+            scratch: (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Returns the oversampling factor.
+    pub fn factor(&self) -> usize {
+        self.factor
+    }
+
+    /// Returns a reference to the wrapped processor.
+    pub fn inner(&self) -> &P {
+        &self.processor
+    }
+
+    /// Returns a mutable reference to the wrapped processor.
+    pub fn inner_mut(&mut self) -> &mut P {
+        &mut self.processor
+    }
+
+    /// Appends the state of all resampling filters and delays to `state`, in the order [`Process::load_state`] reads them back.
+    fn save_filters(&self, state: &mut Vec<f64>) {
+        for stage in self.upsamplers.iter().flatten() {
+            stage.save_state(state);
+        }
+        for stage in self.downsamplers.iter().flatten() {
+            stage.save_state(state);
+        }
+        for delay in self.delays.iter() {
+            delay.save_state(state);
+        }
+    }
+}
+
+impl Oversample<Box<dyn Process>> {
+    /// Constructs an [`Oversample`] node from its `factor` and `processor` parameters, as used by the [`ProcessorRegistry`].
+    ///
+    /// The `processor` parameter is a map with the wrapped processor's `type` name and (optionally) its `params`.
+    pub(crate) fn from_params(
+        params: &Params,
+        registry: &ProcessorRegistry,
+    ) -> Result<Self, ParamError> {
+        params.expect_only(&["factor", "processor"])?;
+
+        let factor = params.get_f64("factor")?.unwrap_or(2.0);
+        if !matches!(factor, 2.0 | 4.0 | 8.0 | 16.0) {
+            return Err(ParamError::new("factor", "expected 2, 4, 8 or 16"));
+        }
+
+        let Some(Param::Map(processor)) = params.get("processor") else {
+            return match params.get("processor") {
+                Some(_) => Err(ParamError::new(
+                    "processor",
+                    "expected a map with `type` and `params`",
+                )),
+                None => Err(ParamError::missing("processor")),
+            };
+        };
+        let kind = processor
+            .get_str("type")
+            .map_err(|err| ParamError::new("processor", err.to_string()))?
+            .ok_or_else(|| ParamError::new("processor", "missing processor `type`"))?;
+        let inner_params = match processor.get("params") {
+            Some(Param::Map(inner_params)) => inner_params.clone(),
+            Some(_) => {
+                return Err(ParamError::new(
+                    "processor",
+                    "expected processor `params` to be a map",
+                ))
+            }
+            None => Params::new(),
+        };
+        let inner = registry
+            .create(kind, &inner_params)
+            .map_err(|err| ParamError::new("processor", err.to_string()))?;
+
+        Ok(Self::new(inner.into_inner(), factor as usize))
+    }
+}
+
+impl<P: Process + Clone> Process for Oversample<P> {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.processor.input_spec()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        self.processor.output_spec()
+    }
+
+    fn is_serializable(&self) -> bool {
+        self.processor.is_serializable()
+    }
+
+    fn params(&self) -> Params {
+        let mut processor = Params::new();
+        processor.insert("type".to_owned(), Param::from(self.processor.name()));
+        let inner_params = self.processor.params();
+        if !inner_params.is_empty() {
+            processor.insert("params".to_owned(), Param::Map(inner_params));
+        }
+
+        let mut params = Params::new();
+        params.insert("factor".to_owned(), Param::from(self.factor as f64));
+        params.insert("processor".to_owned(), Param::Map(processor));
+        params
+    }
+
+    fn prepare(&mut self) {
+        self.processor.prepare();
+    }
+
+    fn reset(&mut self) {
+        self.processor.reset();
+        for stage in self.upsamplers.iter_mut().flatten() {
+            stage.reset();
+        }
+        for stage in self.downsamplers.iter_mut().flatten() {
+            stage.reset();
+        }
+        for delay in self.delays.iter_mut() {
+            delay.reset();
+        }
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        let mut filters = Vec::new();
+        self.save_filters(&mut filters);
+
+        let mut state = Params::new();
+        state.insert(
+            "filters".to_owned(),
+            Param::List(filters.into_iter().map(Param::from).collect()),
+        );
+        if let Some(processor) = self.processor.save_state() {
+            state.insert("processor".to_owned(), processor);
+        }
+        Some(Param::Map(state))
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("state", "expected a map"))?;
+
+        let mut expected = Vec::new();
+        self.save_filters(&mut expected);
+        let filters = state
+            .get("filters")
+            .ok_or_else(|| ParamError::missing("filters"))?
+            .as_list()
+            .and_then(|filters| {
+                filters
+                    .iter()
+                    .map(Param::as_f64)
+                    .collect::<Option<Vec<_>>>()
+            })
+            .filter(|filters| filters.len() == expected.len())
+            .ok_or_else(|| {
+                ParamError::new(
+                    "filters",
+                    format!("expected a list of {} numbers", expected.len()),
+                )
+            })?;
+
+        if let Some(processor) = state.get("processor") {
+            self.processor.load_state(processor)?;
+        }
+        let mut filters = filters.into_iter();
+        for stage in self.upsamplers.iter_mut().flatten() {
+            stage.load_state(&mut filters);
+        }
+        for stage in self.downsamplers.iter_mut().flatten() {
+            stage.load_state(&mut filters);
+        }
+        for delay in self.delays.iter_mut() {
+            delay.load_state(&mut filters);
+        }
+        Ok(())
+    }
+
+    fn tail_length(&self) -> usize {
+        self.processor.tail_length().div_ceil(self.factor)
+    }
+
+    fn latency(&self) -> usize {
+        self.latency
+    }
+
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        let inner_block_size = block_size * self.factor;
+
+        for (buffer, spec) in self
+            .inner_inputs
+            .iter_mut()
+            .zip(self.processor.input_spec())
+        {
+            buffer.resize(inner_block_size, spec.default_value.into());
+        }
+        for (buffer, spec) in self
+            .inner_outputs
+            .iter_mut()
+            .zip(self.processor.output_spec())
+        {
+            buffer.resize(inner_block_size, spec.default_value.into());
+        }
+        self.scratch.0.reserve(inner_block_size);
+        self.scratch.1.reserve(inner_block_size);
+
+        self.processor
+            .resize_buffers(sample_rate * self.factor as f64, inner_block_size);
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        let (a, b) = &mut self.scratch;
+
+        for ((input, inner_input), stages) in inputs
+            .iter()
+            .zip(self.inner_inputs.iter_mut())
+            .zip(self.upsamplers.iter_mut())
+        {
+            a.clear();
+            a.extend(input.iter().map(|sample| sample.value()));
+            for stage in stages.iter_mut() {
+                b.clear();
+                for &x in a.iter() {
+                    b.extend(stage.process(x));
+                }
+                std::mem::swap(a, b);
+            }
+            for (inner, &x) in inner_input.iter_mut().zip(a.iter()) {
+                *inner = Sample::new(x);
+            }
+        }
+
+        self.processor
+            .process(&self.inner_inputs, &mut self.inner_outputs);
+
+        for (((output, inner_output), stages), delay) in outputs
+            .iter_mut()
+            .zip(self.inner_outputs.iter())
+            .zip(self.downsamplers.iter_mut())
+            .zip(self.delays.iter_mut())
+        {
+            a.clear();
+            a.extend(
+                inner_output
+                    .iter()
+                    .map(|sample| delay.process(sample.value())),
+            );
+            // the last stage runs at the highest rate
+            for stage in stages.iter_mut().rev() {
+                b.clear();
+                for pair in a.chunks_exact(2) {
+                    b.push(stage.process(pair[0], pair[1]));
+                }
+                std::mem::swap(a, b);
+            }
+            for (out, &x) in output.iter_mut().zip(a.iter()) {
+                *out = Sample::new(x);
+            }
+        }
+    }
+}
+
+impl<P: StaticPorts + Clone> StaticPorts for Oversample<P> {
+    const NUM_INPUTS: usize = P::NUM_INPUTS;
+    const NUM_OUTPUTS: usize = P::NUM_OUTPUTS;
+}
+
+impl<P: SingleOutput + Clone> SingleOutput for Oversample<P> {}
+
+/// Returns the taps of a halfband lowpass filter with `taps` non-zero taps on either side of its center tap, that lie at even indices
+/// (the center tap, `0.5`, lies at the odd index `2 * taps - 1`, and all other taps at odd indices are zero).
+///
+/// The filter is a Kaiser-windowed sinc with its cutoff at a quarter of the sample rate, normalized for unity gain at DC.
+fn halfband_taps(taps: usize) -> Vec<f64> {
+    let center = 2 * taps - 1;
+    let len = 2 * center + 1;
+
+    let mut even_taps = (0..2 * taps)
+        .map(|i| {
+            let n = 2 * i;
+            let offset = n as f64 - center as f64;
+            let sinc =
+                (std::f64::consts::FRAC_PI_2 * offset).sin() / (std::f64::consts::PI * offset);
+            sinc * kaiser(n, len, KAISER_BETA)
+        })
+        .collect::<Vec<_>>();
+
+    let sum: f64 = even_taps.iter().sum();
+    for tap in even_taps.iter_mut() {
+        *tap *= 0.5 / sum;
+    }
+    even_taps
+}
+
+/// Returns the `n`th value of a Kaiser window of length `len`.
+fn kaiser(n: usize, len: usize, beta: f64) -> f64 {
+    let x = 2.0 * n as f64 / (len - 1) as f64 - 1.0;
+    bessel_i0(beta * (1.0 - x * x).max(0.0).sqrt()) / bessel_i0(beta)
+}
+
+/// The zeroth-order modified Bessel function of the first kind.
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let half_x = x / 2.0;
+    for k in 1..64 {
+        term *= half_x / k as f64;
+        let squared = term * term;
+        sum += squared;
+        if squared < sum * 1e-17 {
+            break;
+        }
+    }
+    sum
+}
+
+/// A fixed delay of a number of samples.
+#[derive(Clone)]
+struct DelayLine {
+    buffer: Box<[f64]>,
+    position: usize,
+}
+
+impl DelayLine {
+    fn new(len: usize) -> Self {
+        Self {
+            buffer: vec![0.0; len].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.position = 0;
+    }
+
+    /// Appends the samples in the delay line to `state`, from the oldest to the newest.
+    fn save_state(&self, state: &mut Vec<f64>) {
+        state.extend_from_slice(&self.buffer[self.position..]);
+        state.extend_from_slice(&self.buffer[..self.position]);
+    }
+
+    /// Refills the delay line with samples saved by [`DelayLine::save_state`].
+    fn load_state(&mut self, state: &mut impl Iterator<Item = f64>) {
+        for (sample, value) in self.buffer.iter_mut().zip(state) {
+            *sample = value;
+        }
+        self.position = 0;
+    }
+
+    /// Pushes a sample into the delay line, returning the one pushed `len` samples ago.
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        if self.buffer.is_empty() {
+            return x;
+        }
+        let y = std::mem::replace(&mut self.buffer[self.position], x);
+        self.position = (self.position + 1) % self.buffer.len();
+        y
+    }
+}
+
+/// An FIR filter over the samples pushed into it.
+#[derive(Clone)]
+struct Fir {
+    taps: Box<[f64]>,
+    /// The most recent samples, stored twice so that the last `taps.len()` of them are always contiguous.
+    history: Box<[f64]>,
+    position: usize,
+}
+
+impl Fir {
+    fn new(taps: Vec<f64>) -> Self {
+        let len = taps.len();
+        Self {
+            taps: taps.into_boxed_slice(),
+            history: vec![0.0; 2 * len].into_boxed_slice(),
+            position: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.position = 0;
+    }
+
+    /// Appends the filter's history to `state`, from the newest sample to the oldest.
+    fn save_state(&self, state: &mut Vec<f64>) {
+        let len = self.taps.len();
+        state.extend_from_slice(&self.history[self.position..self.position + len]);
+    }
+
+    /// Refills the filter's history with samples saved by [`Fir::save_state`].
+    fn load_state(&mut self, state: &mut impl Iterator<Item = f64>) {
+        let len = self.taps.len();
+        for (i, value) in state.take(len).enumerate() {
+            self.history[i] = value;
+            self.history[i + len] = value;
+        }
+        self.position = 0;
+    }
+
+    /// Pushes a sample into the filter, returning the filter's output with it as the most recent sample.
+    #[inline]
+    fn process(&mut self, x: f64) -> f64 {
+        let len = self.taps.len();
+        self.position = (self.position + len - 1) % len;
+        self.history[self.position] = x;
+        self.history[self.position + len] = x;
+
+        let recent = &self.history[self.position..self.position + len];
+        recent
+            .iter()
+            .zip(self.taps.iter())
+            .map(|(x, tap)| x * tap)
+            .sum()
+    }
+}
+
+/// Doubles the sample rate of a signal with a halfband filter, split into its two polyphase components:
+/// the even output samples are filtered by the non-zero side taps, and the odd ones only pass through the center tap, i.e. a delay.
+#[derive(Clone)]
+struct HalfbandUpsampler {
+    fir: Fir,
+    delay: DelayLine,
+}
+
+impl HalfbandUpsampler {
+    fn new(taps: usize) -> Self {
+        Self {
+            fir: Fir::new(halfband_taps(taps)),
+            delay: DelayLine::new(taps - 1),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.fir.reset();
+        self.delay.reset();
+    }
+
+    fn save_state(&self, state: &mut Vec<f64>) {
+        self.fir.save_state(state);
+        self.delay.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut impl Iterator<Item = f64>) {
+        self.fir.load_state(state);
+        self.delay.load_state(state);
+    }
+
+    /// Returns the two output samples for the given input sample.
+    #[inline]
+    fn process(&mut self, x: f64) -> [f64; 2] {
+        // the zero-stuffed signal is scaled by 2 to keep the gain
+        [2.0 * self.fir.process(x), self.delay.process(x)]
+    }
+}
+
+/// Halves the sample rate of a signal with a halfband filter, split into its two polyphase components like [`HalfbandUpsampler`].
+#[derive(Clone)]
+struct HalfbandDownsampler {
+    fir: Fir,
+    delay: DelayLine,
+}
+
+impl HalfbandDownsampler {
+    fn new(taps: usize) -> Self {
+        Self {
+            fir: Fir::new(halfband_taps(taps)),
+            delay: DelayLine::new(taps),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.fir.reset();
+        self.delay.reset();
+    }
+
+    fn save_state(&self, state: &mut Vec<f64>) {
+        self.fir.save_state(state);
+        self.delay.save_state(state);
+    }
+
+    fn load_state(&mut self, state: &mut impl Iterator<Item = f64>) {
+        self.fir.load_state(state);
+        self.delay.load_state(state);
+    }
+
+    /// Returns the output sample for the given pair of consecutive input samples.
+    #[inline]
+    fn process(&mut self, even: f64, odd: f64) -> f64 {
+        self.fir.process(even) + 0.5 * self.delay.process(odd)
+    }
+}
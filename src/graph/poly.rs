@@ -0,0 +1,552 @@
+//! Polyphonic instruments made of several copies of a voice [`SubGraph`].
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    patch::Patch,
+    processor::{Param, Params, Process, SignalSpec},
+    registry::{ParamError, ParamsExt, ProcessorRegistry},
+    signal::{Buffer, Sample},
+};
+
+use super::subgraph::SubGraph;
+
+/// A voice whose output peak stays below this level after its gate is released is considered finished.
+const SILENCE_THRESHOLD: f64 = 1e-4;
+
+/// The largest number of voices a [`Poly`] node can be given through its `voices` parameter.
+pub const MAX_VOICES: usize = 1024;
+
+/// A note event sent to a [`Poly`] node through a [`PolyHandle`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NoteEvent {
+    /// Starts playing the given MIDI note number with the given velocity (usually in `[0.0, 1.0]`).
+    NoteOn { note: u8, velocity: f64 },
+    /// Releases all voices playing the given MIDI note number.
+    NoteOff { note: u8 },
+    /// Releases all voices.
+    AllNotesOff,
+}
+
+/// How a [`Poly`] node picks a voice for a new note when all of its voices are busy.
+///
+/// Voices whose note has already been released are always stolen before voices whose note is still held.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum VoiceStealing {
+    /// Steal the voice that was started the longest time ago.
+    #[default]
+    Oldest,
+    /// Steal the voice with the lowest output level in the last block.
+    Quietest,
+    /// Retrigger the voice already playing the same note if there is one (even if other voices are free), otherwise steal the oldest voice.
+    SameNote,
+}
+
+impl VoiceStealing {
+    /// Returns the name of the strategy as used in [`Poly`]'s `stealing` parameter.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Oldest => "oldest",
+            Self::Quietest => "quietest",
+            Self::SameNote => "same-note",
+        }
+    }
+}
+
+impl std::str::FromStr for VoiceStealing {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(Self::Oldest),
+            "quietest" => Ok(Self::Quietest),
+            "same-note" => Ok(Self::SameNote),
+            _ => Err(format!(
+                "unknown voice stealing strategy `{s}`; expected `oldest`, `quietest` or `same-note`"
+            )),
+        }
+    }
+}
+
+/// A handle for sending [`NoteEvent`]s to a [`Poly`] node, e.g. from a MIDI thread while the graph is running.
+///
+/// Events are applied at the start of the next block the node processes.
+#[derive(Debug, Clone)]
+pub struct PolyHandle {
+    events: Arc<Mutex<VecDeque<NoteEvent>>>,
+}
+
+impl PolyHandle {
+    /// Queues the given event.
+    pub fn send(&self, event: NoteEvent) {
+        self.events.lock().unwrap().push_back(event);
+    }
+
+    /// Queues a [`NoteEvent::NoteOn`] event.
+    pub fn note_on(&self, note: u8, velocity: f64) {
+        self.send(NoteEvent::NoteOn { note, velocity });
+    }
+
+    /// Queues a [`NoteEvent::NoteOff`] event.
+    pub fn note_off(&self, note: u8) {
+        self.send(NoteEvent::NoteOff { note });
+    }
+
+    /// Queues a [`NoteEvent::AllNotesOff`] event.
+    pub fn all_notes_off(&self) {
+        self.send(NoteEvent::AllNotesOff);
+    }
+}
+
+/// Where a voice input gets its signal from.
+#[derive(Debug, Clone, Copy)]
+enum VoiceInput {
+    Gate,
+    Frequency,
+    Velocity,
+    /// The given input of the [`Poly`] node itself, shared by all voices.
+    Shared(usize),
+}
+
+#[derive(Clone)]
+struct Voice {
+    graph: SubGraph,
+    inputs: Vec<Buffer>,
+    outputs: Vec<Buffer>,
+    note: u8,
+    velocity: f64,
+    gate: bool,
+    /// Whether the voice is producing sound; idle voices aren't processed.
+    active: bool,
+    /// Whether the gate should drop to zero for the first sample of the next block, so that the voice sees a new note even if it was already gated.
+    retrigger: bool,
+    /// When the voice was last started, in note-on events received by the [`Poly`] node.
+    started: u64,
+    /// The peak absolute output level of the last processed block.
+    level: f64,
+}
+
+impl Voice {
+    fn save_state(&self) -> Param {
+        let mut state = Params::new();
+        state.insert("note".to_owned(), Param::from(self.note as f64));
+        state.insert("velocity".to_owned(), Param::from(self.velocity));
+        state.insert("gate".to_owned(), Param::from(self.gate));
+        state.insert("active".to_owned(), Param::from(self.active));
+        state.insert("retrigger".to_owned(), Param::from(self.retrigger));
+        state.insert("started".to_owned(), Param::from(self.started as f64));
+        state.insert("level".to_owned(), Param::from(self.level));
+        if let Some(graph) = self.graph.save_state() {
+            state.insert("graph".to_owned(), graph);
+        }
+        Param::Map(state)
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("voices", "expected a list of maps"))?;
+        let get_f64 = |name: &str| {
+            state
+                .get_f64(name)?
+                .ok_or_else(|| ParamError::missing(name))
+        };
+        let get_bool = |name: &str| {
+            state
+                .get_bool(name)?
+                .ok_or_else(|| ParamError::missing(name))
+        };
+
+        let note = get_f64("note")?;
+        let velocity = get_f64("velocity")?;
+        let gate = get_bool("gate")?;
+        let active = get_bool("active")?;
+        let retrigger = get_bool("retrigger")?;
+        let started = get_f64("started")?;
+        let level = get_f64("level")?;
+        if let Some(graph) = state.get("graph") {
+            self.graph.load_state(graph)?;
+        }
+
+        self.note = note as u8;
+        self.velocity = velocity;
+        self.gate = gate;
+        self.active = active;
+        self.retrigger = retrigger;
+        self.started = started as u64;
+        self.level = level;
+        Ok(())
+    }
+}
+
+/// A polyphonic instrument that plays several copies of a voice [`SubGraph`] at once, in response to [`NoteEvent`]s.
+///
+/// Voice inputs are routed by name:
+/// - `gate` is `1.0` while the voice's note is held and `0.0` after it's released.
+/// - `frequency` is the frequency of the voice's note in Hz (A4 = MIDI note 69 = 440 Hz).
+/// - `velocity` is the velocity the voice's note was started with.
+///
+/// All other voice inputs become inputs of the [`Poly`] node itself, and are fed to every voice.
+/// The node has the same outputs as the voice, each being the sum of that output over all voices.
+///
+/// A voice becomes idle once its gate has been released and its outputs have decayed to silence; idle voices aren't processed at all.
+/// When a note starts and no voice is idle, a voice is stolen according to the node's [`VoiceStealing`] strategy.
+///
+/// Notes are sent through a [`PolyHandle`] obtained from [`Poly::handle`].
+/// Clones of a [`Poly`] (including the one made when it's added to a graph) share the same event queue, so a handle keeps working after the node has been added.
+#[derive(Clone)]
+pub struct Poly {
+    voice: SubGraph,
+    voices: Vec<Voice>,
+    routing: Vec<VoiceInput>,
+    stealing: VoiceStealing,
+    events: Arc<Mutex<VecDeque<NoteEvent>>>,
+    note_counter: u64,
+}
+
+impl Poly {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::Poly");
+
+    /// Creates a new [`Poly`] node with the given number of copies of the given voice.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `num_voices` is zero.
+    pub fn new(voice: impl Into<SubGraph>, num_voices: usize) -> Self {
+        assert!(num_voices > 0, "A Poly node needs at least one voice");
+        let voice = voice.into();
+
+        let mut num_shared = 0;
+        let routing = voice
+            .input_names()
+            .iter()
+            .map(|name| match name.as_str() {
+                "gate" => VoiceInput::Gate,
+                "frequency" => VoiceInput::Frequency,
+                "velocity" => VoiceInput::Velocity,
+                _ => {
+                    num_shared += 1;
+                    VoiceInput::Shared(num_shared - 1)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let voices = (0..num_voices)
+            .map(|_| Voice {
+                graph: voice.clone(),
+                inputs: vec![Buffer::zeros(0); routing.len()],
+                outputs: vec![Buffer::zeros(0); voice.output_names().len()],
+                note: 0,
+                velocity: 0.0,
+                gate: false,
+                active: false,
+                retrigger: false,
+                started: 0,
+                level: 0.0,
+            })
+            .collect();
+
+        Self {
+            voice,
+            voices,
+            routing,
+            stealing: VoiceStealing::default(),
+            events: Arc::new(Mutex::new(VecDeque::new())),
+            note_counter: 0,
+        }
+    }
+
+    /// Sets the voice stealing strategy.
+    pub fn with_stealing(mut self, stealing: VoiceStealing) -> Self {
+        self.stealing = stealing;
+        self
+    }
+
+    /// Returns a [`PolyHandle`] for sending notes to this node.
+    pub fn handle(&self) -> PolyHandle {
+        PolyHandle {
+            events: self.events.clone(),
+        }
+    }
+
+    /// Returns the number of voices.
+    pub fn num_voices(&self) -> usize {
+        self.voices.len()
+    }
+
+    /// Returns the number of voices that are currently producing sound.
+    pub fn active_voices(&self) -> usize {
+        self.voices.iter().filter(|voice| voice.active).count()
+    }
+
+    /// Returns the voice stealing strategy.
+    pub fn stealing(&self) -> VoiceStealing {
+        self.stealing
+    }
+
+    /// Constructs a [`Poly`] node from its `voice`, `voices` and `stealing` parameters, as used by the [`ProcessorRegistry`].
+    ///
+    /// `voices` must be a whole number from 1 to [`MAX_VOICES`].
+    pub(crate) fn from_params(
+        params: &Params,
+        registry: &ProcessorRegistry,
+    ) -> Result<Self, ParamError> {
+        params.expect_only(&["voice", "voices", "stealing"])?;
+
+        let voice = params
+            .get("voice")
+            .ok_or_else(|| ParamError::missing("voice"))?;
+        let voice = Patch::from_param(voice)
+            .and_then(|patch| SubGraph::from_patch(&patch, registry))
+            .map_err(|err| ParamError::new("voice", err.to_string()))?;
+
+        let num_voices = params.get_f64("voices")?.unwrap_or(8.0);
+        if num_voices < 1.0 || num_voices.fract() != 0.0 {
+            return Err(ParamError::new("voices", "expected a positive integer"));
+        }
+        if num_voices > MAX_VOICES as f64 {
+            return Err(ParamError::new(
+                "voices",
+                format!("at most {MAX_VOICES} voices are supported"),
+            ));
+        }
+
+        let stealing = params
+            .get_str("stealing")?
+            .map(str::parse)
+            .transpose()
+            .map_err(|reason| ParamError::new("stealing", reason))?
+            .unwrap_or_default();
+
+        Ok(Self::new(voice, num_voices as usize).with_stealing(stealing))
+    }
+
+    fn handle_events(&mut self) {
+        let queue = self.events.clone();
+        // never block the audio thread; if the queue is busy, the events are handled next block
+        let Ok(mut events) = queue.try_lock() else {
+            return;
+        };
+
+        while let Some(event) = events.pop_front() {
+            match event {
+                NoteEvent::NoteOn { note, velocity } => self.note_on(note, velocity),
+                NoteEvent::NoteOff { note } => {
+                    for voice in self.voices.iter_mut() {
+                        if voice.gate && voice.note == note {
+                            voice.gate = false;
+                        }
+                    }
+                }
+                NoteEvent::AllNotesOff => {
+                    for voice in self.voices.iter_mut() {
+                        voice.gate = false;
+                    }
+                }
+            }
+        }
+    }
+
+    fn note_on(&mut self, note: u8, velocity: f64) {
+        let index = self.allocate_voice(note);
+        self.note_counter += 1;
+
+        let voice = &mut self.voices[index];
+        voice.retrigger = voice.active;
+        voice.note = note;
+        voice.velocity = velocity;
+        voice.gate = true;
+        voice.active = true;
+        voice.started = self.note_counter;
+    }
+
+    fn allocate_voice(&self, note: u8) -> usize {
+        if self.stealing == VoiceStealing::SameNote {
+            if let Some(index) = self
+                .voices
+                .iter()
+                .position(|voice| voice.active && voice.note == note)
+            {
+                return index;
+            }
+        }
+
+        if let Some(index) = self.voices.iter().position(|voice| !voice.active) {
+            return index;
+        }
+
+        // prefer released voices over held ones, then apply the stealing strategy
+        let (index, _) = self
+            .voices
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| {
+                a.gate.cmp(&b.gate).then_with(|| match self.stealing {
+                    VoiceStealing::Quietest => a.level.total_cmp(&b.level),
+                    VoiceStealing::Oldest | VoiceStealing::SameNote => a.started.cmp(&b.started),
+                })
+            })
+            .expect("Poly nodes always have at least one voice");
+        index
+    }
+}
+
+impl Process for Poly {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.voice
+            .input_names()
+            .iter()
+            .zip(self.routing.iter())
+            .filter(|(_, route)| matches!(route, VoiceInput::Shared(_)))
+            .map(|(name, _)| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        self.voice
+            .output_names()
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn is_serializable(&self) -> bool {
+        self.voice.is_serializable()
+    }
+
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        // unserializable voices are left out, `Patch::from_graph` refuses to save them anyway
+        if let Ok(voice) = self.voice.to_patch() {
+            params.insert("voice".to_owned(), voice.to_param());
+        }
+        params.insert("voices".to_owned(), Param::from(self.voices.len() as f64));
+        params.insert("stealing".to_owned(), Param::from(self.stealing.as_str()));
+        params
+    }
+
+    fn prepare(&mut self) {
+        for voice in self.voices.iter_mut() {
+            voice.graph.prepare();
+        }
+    }
+
+    fn reset(&mut self) {
+        for voice in self.voices.iter_mut() {
+            voice.graph.reset();
+            voice.gate = false;
+            voice.active = false;
+            voice.retrigger = false;
+            voice.level = 0.0;
+        }
+        self.note_counter = 0;
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        let mut state = Params::new();
+        state.insert(
+            "voices".to_owned(),
+            Param::List(self.voices.iter().map(Voice::save_state).collect()),
+        );
+        state.insert(
+            "note_counter".to_owned(),
+            Param::from(self.note_counter as f64),
+        );
+        Some(Param::Map(state))
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let state = state
+            .as_map()
+            .ok_or_else(|| ParamError::new("state", "expected a map"))?;
+        let voices = state
+            .get("voices")
+            .ok_or_else(|| ParamError::missing("voices"))?
+            .as_list()
+            .filter(|voices| voices.len() == self.voices.len())
+            .ok_or_else(|| {
+                ParamError::new(
+                    "voices",
+                    format!("expected a list of {} voices", self.voices.len()),
+                )
+            })?;
+        let note_counter = state
+            .get_f64("note_counter")?
+            .ok_or_else(|| ParamError::missing("note_counter"))?;
+
+        for (voice, state) in self.voices.iter_mut().zip(voices) {
+            voice.load_state(state)?;
+        }
+        self.note_counter = note_counter as u64;
+        Ok(())
+    }
+
+    fn tail_length(&self) -> usize {
+        self.voice.tail_length()
+    }
+
+    fn latency(&self) -> usize {
+        self.voice.latency()
+    }
+
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        for voice in self.voices.iter_mut() {
+            voice.graph.resize_buffers(sample_rate, block_size);
+            for buffer in voice.inputs.iter_mut().chain(voice.outputs.iter_mut()) {
+                buffer.resize(block_size, Sample::ZERO);
+            }
+        }
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        self.handle_events();
+
+        for output in outputs.iter_mut() {
+            output.fill(Sample::ZERO);
+        }
+
+        for voice in self.voices.iter_mut().filter(|voice| voice.active) {
+            let frequency = 440.0 * 2f64.powf((voice.note as f64 - 69.0) / 12.0);
+
+            for (buffer, route) in voice.inputs.iter_mut().zip(self.routing.iter()) {
+                match *route {
+                    VoiceInput::Gate => {
+                        buffer.fill(Sample::new(if voice.gate { 1.0 } else { 0.0 }));
+                        if voice.retrigger {
+                            if let Some(first) = buffer.first_mut() {
+                                *first = Sample::ZERO;
+                            }
+                        }
+                    }
+                    VoiceInput::Frequency => buffer.fill(Sample::new(frequency)),
+                    VoiceInput::Velocity => buffer.fill(Sample::new(voice.velocity)),
+                    VoiceInput::Shared(index) => buffer.copy_from_slice(&inputs[index]),
+                }
+            }
+            voice.retrigger = false;
+
+            voice.graph.process(&voice.inputs, &mut voice.outputs);
+
+            let mut level = 0.0f64;
+            for (output, voice_output) in outputs.iter_mut().zip(voice.outputs.iter()) {
+                for (out, sample) in output.iter_mut().zip(voice_output.iter()) {
+                    *out += *sample;
+                    level = level.max(sample.value().abs());
+                }
+            }
+            voice.level = level;
+
+            if !voice.gate && level < SILENCE_THRESHOLD {
+                voice.active = false;
+            }
+        }
+    }
+}
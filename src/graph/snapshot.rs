@@ -0,0 +1,311 @@
+//! Saving and restoring the state of all nodes in a [`Graph`], e.g. for presets, undo, or resuming a render.
+//!
+//! A [`GraphSnapshot`] holds the state of each processor node (see [`Process::save_state`](crate::processor::Process::save_state)),
+//! along with everything else needed for processing to continue exactly where it was saved: the node's [`BypassState`],
+//! the contents of its output buffers, and the contents of the graph's latency compensation delays.
+//!
+//! Snapshots refer to nodes by their [`NodeIndex`], so they can only be restored into the graph they were taken from, or a clone of it.
+//! They are serializable, so they can be stored alongside a [`Patch`](crate::patch::Patch) of the same graph.
+
+use std::collections::BTreeMap;
+
+use crate::{
+    processor::{BypassState, Param},
+    registry::ParamError,
+    signal::Sample,
+};
+
+use super::{node::GraphNode, EdgeIndex, Graph, GraphIx, NodeIndex};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum SnapshotError {
+    #[error("Node {0:?} does not exist in the graph")]
+    UnknownNode(NodeIndex),
+    #[error("Node {0:?} is a graph input or output, not a processor")]
+    NotAProcessor(NodeIndex),
+    #[error(
+        "Node {node:?} is a `{found}` processor, but the snapshot is of a `{expected}` processor"
+    )]
+    MismatchedProcessor {
+        node: NodeIndex,
+        expected: String,
+        found: String,
+    },
+    #[error("Invalid state for node {node:?}: {source}")]
+    InvalidState {
+        node: NodeIndex,
+        #[source]
+        source: ParamError,
+    },
+    #[error("Invalid snapshot format: {0}")]
+    Format(#[from] serde_json::Error),
+}
+
+pub type SnapshotResult<T> = Result<T, SnapshotError>;
+
+/// The saved state of a single processor node.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct NodeSnapshot {
+    /// The name of the node's processor, checked when restoring.
+    pub processor: String,
+    /// The processor's own state, as returned by [`Process::save_state`](crate::processor::Process::save_state).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<Param>,
+    /// The node's [`BypassState`] when the snapshot was taken, which it resumes in before crossfading to its current one.
+    #[serde(default)]
+    pub bypass: BypassState,
+    /// The contents of the node's output buffers, which nodes later in a cycle read before the node runs again.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "samples")]
+    pub outputs: Vec<Vec<f64>>,
+    /// The two samples each output produced before the last block, oldest first, which reduced-rate outputs are resampled from.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "samples")]
+    pub interpolation_starts: Vec<[f64; 2]>,
+    /// The last two samples of each output, oldest first, or `None` if the node hasn't run at a reduced rate yet.
+    #[serde(default, skip_serializing_if = "Option::is_none", with = "samples")]
+    pub last_outputs: Option<Vec<[f64; 2]>>,
+    /// The contents of the delays the node's inputs are passed through when bypassed, from the oldest sample to the newest.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "samples")]
+    pub thru_delays: Vec<Vec<f64>>,
+    /// The [`Rate::block_len`](crate::processor::Rate::block_len) phase of the node's output buffers and of the next block.
+    #[serde(default, deserialize_with = "deserialize_phase")]
+    pub phase: (usize, usize),
+}
+
+/// Deserializes a [`NodeSnapshot::phase`] from any whole numbers, since snapshots stored as [`Param`]s hold all numbers as floats.
+fn deserialize_phase<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<(usize, usize), D::Error> {
+    let to_usize = |value: f64| {
+        if value >= 0.0 && value.fract() == 0.0 && value < usize::MAX as f64 {
+            Ok(value as usize)
+        } else {
+            Err(serde::de::Error::custom(format!("Invalid phase: {value}")))
+        }
+    };
+    let (phase, next_phase) = <(f64, f64) as serde::Deserialize>::deserialize(deserializer)?;
+    Ok((to_usize(phase)?, to_usize(next_phase)?))
+}
+
+/// The saved state of all processor nodes in a [`Graph`], created by [`Graph::snapshot`] and restored by [`Graph::restore`].
+#[derive(Debug, Clone, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GraphSnapshot {
+    /// The state of each processor node, by node index.
+    pub nodes: BTreeMap<GraphIx, NodeSnapshot>,
+    /// The contents of each latency compensation delay, by edge index, from the oldest sample to the newest.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty", with = "samples")]
+    pub edge_delays: BTreeMap<GraphIx, Vec<f64>>,
+}
+
+impl GraphSnapshot {
+    /// Converts this snapshot into a [`Param`], e.g. for use as the state of a processor containing a graph.
+    ///
+    /// Fails if a processor's state can't be represented as a [`Param`], e.g. because it holds a NaN or infinite [`Param::Float`].
+    /// (The samples saved alongside the processors' states can be NaN or infinite.)
+    pub fn to_param(&self) -> SnapshotResult<Param> {
+        Ok(serde_json::to_value(self).and_then(serde_json::from_value)?)
+    }
+
+    /// Converts a [`Param`] created by [`GraphSnapshot::to_param`] back into a [`GraphSnapshot`].
+    pub fn from_param(param: &Param) -> SnapshotResult<Self> {
+        Ok(serde_json::to_value(param).and_then(serde_json::from_value)?)
+    }
+
+    /// Parses a [`GraphSnapshot`] from a JSON string.
+    pub fn from_json(json: &str) -> SnapshotResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes this [`GraphSnapshot`] to a JSON string.
+    pub fn to_json(&self) -> SnapshotResult<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+}
+
+impl Graph {
+    /// Saves the state of all processor nodes in the graph, along with the contents of its latency compensation delays.
+    pub fn snapshot(&self) -> GraphSnapshot {
+        let nodes = self
+            .digraph
+            .node_indices()
+            .filter_map(|node| match &self.digraph[node] {
+                GraphNode::Processor(processor) => {
+                    Some((node.index() as GraphIx, processor.snapshot()))
+                }
+                GraphNode::Passthrough(_) => None,
+            })
+            .collect();
+
+        let edge_delays = self
+            .edge_delays
+            .iter()
+            .map(|(edge, delay)| {
+                let samples = delay.samples().map(Sample::value).collect();
+                (edge.index() as GraphIx, samples)
+            })
+            .collect();
+
+        GraphSnapshot { nodes, edge_delays }
+    }
+
+    /// Restores a [`GraphSnapshot`] created by [`Graph::snapshot`] on this graph or a clone of it.
+    ///
+    /// This should be called after [`Graph::reset`], which reallocates the buffers and delays being restored.
+    /// Output buffers and delays whose length has changed since the snapshot was taken (e.g. due to a different block size) are left as they are.
+    /// Nodes missing from the snapshot are left as they are too, e.g. ones added since it was taken.
+    /// [`BypassState`]s aren't changed, since they're shared with the graph's clones and [`BypassHandle`](crate::processor::BypassHandle)s (see [`Processor::restore`](crate::processor::Processor::restore)).
+    ///
+    /// All nodes are checked against the snapshot before any of them are restored, but if a processor rejects its state,
+    /// the nodes restored before it keep their restored state.
+    pub fn restore(&mut self, snapshot: &GraphSnapshot) -> SnapshotResult<()> {
+        for (&index, node_snapshot) in snapshot.nodes.iter() {
+            let node = NodeIndex::new(index as usize);
+            match self.digraph.node_weight(node) {
+                Some(GraphNode::Processor(processor))
+                    if processor.name() == node_snapshot.processor => {}
+                Some(GraphNode::Processor(processor)) => {
+                    return Err(SnapshotError::MismatchedProcessor {
+                        node,
+                        expected: node_snapshot.processor.clone(),
+                        found: processor.name().to_owned(),
+                    })
+                }
+                Some(GraphNode::Passthrough(_)) => return Err(SnapshotError::NotAProcessor(node)),
+                None => return Err(SnapshotError::UnknownNode(node)),
+            }
+        }
+
+        for (&index, node_snapshot) in snapshot.nodes.iter() {
+            let node = NodeIndex::new(index as usize);
+            if let GraphNode::Processor(processor) = &mut self.digraph[node] {
+                processor
+                    .restore(node_snapshot)
+                    .map_err(|source| SnapshotError::InvalidState { node, source })?;
+            }
+        }
+
+        for (&index, samples) in snapshot.edge_delays.iter() {
+            let edge = EdgeIndex::new(index as usize);
+            if let Some(delay) = self.edge_delays.get_mut(&edge) {
+                delay.restore(samples);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Serializes the samples in a snapshot as numbers, or as the strings `"NaN"`, `"inf"` and `"-inf"` if they aren't finite,
+/// since JSON (and so [`Param`]) can't represent non-finite numbers.
+mod samples {
+    use std::collections::BTreeMap;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<T: Encode, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.encode().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, T: Encode, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T, D::Error> {
+        T::decode(T::Encoded::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+
+    #[derive(Serialize, Deserialize)]
+    #[serde(untagged)]
+    pub(super) enum EncodedSample {
+        Finite(f64),
+        NonFinite(String),
+    }
+
+    /// A value made of samples, which are encoded one by one.
+    pub(super) trait Encode: Sized {
+        type Encoded: Serialize + for<'de> Deserialize<'de>;
+
+        fn encode(&self) -> Self::Encoded;
+
+        fn decode(encoded: Self::Encoded) -> Result<Self, String>;
+    }
+
+    impl Encode for f64 {
+        type Encoded = EncodedSample;
+
+        fn encode(&self) -> EncodedSample {
+            match *self {
+                value if value.is_finite() => EncodedSample::Finite(value),
+                value if value.is_nan() => EncodedSample::NonFinite("NaN".to_owned()),
+                value if value > 0.0 => EncodedSample::NonFinite("inf".to_owned()),
+                _ => EncodedSample::NonFinite("-inf".to_owned()),
+            }
+        }
+
+        fn decode(encoded: EncodedSample) -> Result<Self, String> {
+            match encoded {
+                EncodedSample::Finite(value) => Ok(value),
+                EncodedSample::NonFinite(value) => match value.as_str() {
+                    "NaN" => Ok(f64::NAN),
+                    "inf" => Ok(f64::INFINITY),
+                    "-inf" => Ok(f64::NEG_INFINITY),
+                    _ => Err(format!("Invalid sample: {value:?}")),
+                },
+            }
+        }
+    }
+
+    impl<T: Encode> Encode for Vec<T> {
+        type Encoded = Vec<T::Encoded>;
+
+        fn encode(&self) -> Self::Encoded {
+            self.iter().map(T::encode).collect()
+        }
+
+        fn decode(encoded: Self::Encoded) -> Result<Self, String> {
+            encoded.into_iter().map(T::decode).collect()
+        }
+    }
+
+    impl<T: Encode> Encode for [T; 2] {
+        type Encoded = [T::Encoded; 2];
+
+        fn encode(&self) -> Self::Encoded {
+            [self[0].encode(), self[1].encode()]
+        }
+
+        fn decode([first, second]: Self::Encoded) -> Result<Self, String> {
+            Ok([T::decode(first)?, T::decode(second)?])
+        }
+    }
+
+    impl<T: Encode> Encode for Option<T> {
+        type Encoded = Option<T::Encoded>;
+
+        fn encode(&self) -> Self::Encoded {
+            self.as_ref().map(T::encode)
+        }
+
+        fn decode(encoded: Self::Encoded) -> Result<Self, String> {
+            encoded.map(T::decode).transpose()
+        }
+    }
+
+    impl<K: Ord + Clone + Serialize + for<'de> Deserialize<'de>, T: Encode> Encode for BTreeMap<K, T> {
+        type Encoded = BTreeMap<K, T::Encoded>;
+
+        fn encode(&self) -> Self::Encoded {
+            self.iter()
+                .map(|(key, value)| (key.clone(), value.encode()))
+                .collect()
+        }
+
+        fn decode(encoded: Self::Encoded) -> Result<Self, String> {
+            encoded
+                .into_iter()
+                .map(|(key, value)| Ok((key, T::decode(value)?)))
+                .collect()
+        }
+    }
+}
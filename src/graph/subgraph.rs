@@ -0,0 +1,276 @@
+//! Wrapping a whole [`Graph`] as a single [`Process`] node.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    patch::{Patch, PatchResult},
+    processor::{Param, Params, Process, SignalSpec},
+    registry::{ParamError, ParamsExt, ProcessorRegistry},
+    signal::Buffer,
+};
+
+use super::{node::GraphNode, snapshot::GraphSnapshot, Graph};
+
+/// A [`Process`] that runs an inner [`Graph`], so that a voice or effect can be built once and used as a single node in larger graphs.
+///
+/// The inner graph's input nodes (created with [`Graph::add_input`]) become the subgraph's inputs, and its output nodes become its outputs, in the same order.
+/// Inputs and outputs are named `in0`, `in1`, ... and `out0`, `out1`, ... unless given names with [`SubGraph::with_input_names`] and [`SubGraph::with_output_names`].
+///
+/// Cloning a [`SubGraph`] clones the whole inner graph, so the same subgraph can be instantiated any number of times.
+#[derive(Clone)]
+pub struct SubGraph {
+    graph: Graph,
+    input_names: Vec<String>,
+    output_names: Vec<String>,
+    /// The sample rate the inner graph was last reset for, and the largest block size it has been allocated for since, if any.
+    allocated_for: Option<(f64, usize)>,
+}
+
+impl SubGraph {
+    /// The name this processor is saved and registered under.
+    pub const NAME: &'static str = concat!(module_path!(), "::SubGraph");
+
+    /// Creates a new [`SubGraph`] wrapping the given [`Graph`].
+    pub fn new(graph: Graph) -> Self {
+        let input_names = (0..graph.num_inputs()).map(|i| format!("in{i}")).collect();
+        let output_names = (0..graph.num_outputs())
+            .map(|i| format!("out{i}"))
+            .collect();
+        Self {
+            graph,
+            input_names,
+            output_names,
+            allocated_for: None,
+        }
+    }
+
+    /// Sets the names of the subgraph's inputs, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of names doesn't match the number of inputs of the inner graph,
+    /// or if a name is given twice or is already the name of one of the subgraph's outputs.
+    pub fn with_input_names<S: Into<String>>(mut self, names: impl IntoIterator<Item = S>) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        assert_eq!(
+            names.len(),
+            self.graph.num_inputs(),
+            "Number of input names must match the number of graph inputs"
+        );
+        assert_unique_names(&names, &self.output_names);
+        self.input_names = names;
+        self
+    }
+
+    /// Sets the names of the subgraph's outputs, in order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of names doesn't match the number of outputs of the inner graph,
+    /// or if a name is given twice or is already the name of one of the subgraph's inputs.
+    pub fn with_output_names<S: Into<String>>(
+        mut self,
+        names: impl IntoIterator<Item = S>,
+    ) -> Self {
+        let names: Vec<String> = names.into_iter().map(Into::into).collect();
+        assert_eq!(
+            names.len(),
+            self.graph.num_outputs(),
+            "Number of output names must match the number of graph outputs"
+        );
+        assert_unique_names(&names, &self.input_names);
+        self.output_names = names;
+        self
+    }
+
+    /// Returns a reference to the inner [`Graph`].
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Returns the names of the subgraph's inputs.
+    pub fn input_names(&self) -> &[String] {
+        &self.input_names
+    }
+
+    /// Returns the names of the subgraph's outputs.
+    pub fn output_names(&self) -> &[String] {
+        &self.output_names
+    }
+
+    /// Describes this subgraph as a [`Patch`], with the graph inputs and outputs named after the subgraph's inputs and outputs.
+    ///
+    /// Returns [`PatchError::Unserializable`](crate::patch::PatchError::Unserializable) if the graph contains a processor that can't be saved, see [`Patch::from_graph`].
+    pub fn to_patch(&self) -> PatchResult<Patch> {
+        let mut patch = Patch::from_graph(&self.graph)?;
+
+        // all nodes are renamed at once, so that e.g. swapping `in0` and `in1` or naming an output `in0` works
+        let mut renames: HashMap<String, String> = patch
+            .inputs
+            .iter()
+            .cloned()
+            .zip(self.input_names.iter().cloned())
+            .chain(
+                patch
+                    .outputs
+                    .iter()
+                    .cloned()
+                    .zip(self.output_names.iter().cloned()),
+            )
+            .collect();
+
+        // processor nodes get generated names, which may clash with the input and output names
+        let port_names: HashSet<&String> = renames.values().collect();
+        let node_names: HashSet<&String> = patch.nodes.iter().map(|node| &node.name).collect();
+        let mut node_renames = HashMap::new();
+        for node in patch.nodes.iter() {
+            if port_names.contains(&node.name) {
+                let mut name = format!("{}_", node.name);
+                while port_names.contains(&name) || node_names.contains(&name) {
+                    name.push('_');
+                }
+                node_renames.insert(node.name.clone(), name);
+            }
+        }
+        renames.extend(node_renames);
+
+        patch
+            .rename_nodes(&renames)
+            .expect("subgraph inputs and outputs have unique names");
+        Ok(patch)
+    }
+
+    /// Creates a [`SubGraph`] from a [`Patch`], creating its processors with the given [`ProcessorRegistry`].
+    ///
+    /// The subgraph's inputs and outputs are named after the patch's inputs and outputs.
+    pub fn from_patch(patch: &Patch, registry: &ProcessorRegistry) -> PatchResult<Self> {
+        let graph = patch.to_graph_with(registry)?;
+        Ok(Self::new(graph)
+            .with_input_names(patch.inputs.iter().cloned())
+            .with_output_names(patch.outputs.iter().cloned()))
+    }
+
+    /// Constructs a [`SubGraph`] from its `patch` parameter, as used by the [`ProcessorRegistry`].
+    pub(crate) fn from_params(
+        params: &Params,
+        registry: &ProcessorRegistry,
+    ) -> Result<Self, ParamError> {
+        params.expect_only(&["patch"])?;
+        let patch = params
+            .get("patch")
+            .ok_or_else(|| ParamError::missing("patch"))?;
+        let patch =
+            Patch::from_param(patch).map_err(|err| ParamError::new("patch", err.to_string()))?;
+        Self::from_patch(&patch, registry).map_err(|err| ParamError::new("patch", err.to_string()))
+    }
+}
+
+/// Panics if `names` contains a name twice, or a name from `others`.
+fn assert_unique_names(names: &[String], others: &[String]) {
+    let mut seen = HashSet::new();
+    for name in names {
+        assert!(
+            seen.insert(name) && !others.contains(name),
+            "Duplicate subgraph port name `{name}`"
+        );
+    }
+}
+
+impl From<Graph> for SubGraph {
+    fn from(graph: Graph) -> Self {
+        Self::new(graph)
+    }
+}
+
+impl Process for SubGraph {
+    fn name(&self) -> &str {
+        Self::NAME
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.input_names
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        self.output_names
+            .iter()
+            .map(|name| SignalSpec::unbounded(name.clone(), 0.0))
+            .collect()
+    }
+
+    fn is_serializable(&self) -> bool {
+        self.graph.digraph().node_weights().all(|node| match node {
+            GraphNode::Processor(processor) => processor.is_serializable(),
+            GraphNode::Passthrough(_) => true,
+        })
+    }
+
+    fn params(&self) -> Params {
+        let mut params = Params::new();
+        // unserializable graphs are left out, `Patch::from_graph` refuses to save them anyway
+        if let Ok(patch) = self.to_patch() {
+            params.insert("patch".to_owned(), patch.to_param());
+        }
+        params
+    }
+
+    fn prepare(&mut self) {
+        self.graph.prepare_nodes();
+    }
+
+    fn reset(&mut self) {
+        self.graph.reset_state();
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        // a state that can't be saved is left out, rather than failing the whole save
+        self.graph.snapshot().to_param().ok()
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        let snapshot = GraphSnapshot::from_param(state)
+            .map_err(|err| ParamError::new("state", err.to_string()))?;
+        self.graph
+            .restore(&snapshot)
+            .map_err(|err| ParamError::new("state", err.to_string()))
+    }
+
+    fn tail_length(&self) -> usize {
+        self.graph.tail_length()
+    }
+
+    fn latency(&self) -> usize {
+        self.graph.latency()
+    }
+
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        // this is called before every block, possibly on the audio thread, so the inner graph is only reallocated
+        // when the sample rate changes or the block is larger than any before; smaller blocks reuse the buffers
+        match self.allocated_for {
+            Some((allocated_rate, max_block_size))
+                if allocated_rate == sample_rate && block_size <= max_block_size =>
+            {
+                self.graph.resize_buffers(sample_rate, block_size);
+            }
+            _ => {
+                self.graph.reset(sample_rate, block_size);
+                self.allocated_for = Some((sample_rate, block_size));
+            }
+        }
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (i, input) in inputs.iter().enumerate() {
+            self.graph.copy_input(i, input);
+        }
+
+        self.graph.process();
+
+        for (output, graph_output) in outputs.iter_mut().zip(self.graph.outputs()) {
+            output.copy_from_slice(graph_output);
+        }
+    }
+}
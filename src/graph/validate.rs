@@ -0,0 +1,230 @@
+//! Static checks for common mistakes in [`Graph`]s.
+
+use std::collections::HashSet;
+
+use petgraph::{
+    prelude::{Direction, EdgeRef},
+    visit::{Dfs, IntoEdgeReferences, Reversed},
+};
+
+use crate::{builtins::math::ConstantProc, graph::node::GraphNode};
+
+use super::{Graph, NodeIndex, PortDirection};
+
+/// How serious a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Probably intended, but worth knowing about.
+    Info,
+    /// The graph will run, but probably not as intended.
+    Warning,
+    /// The graph can't run correctly.
+    Error,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Info => f.write_str("info"),
+            Self::Warning => f.write_str("warning"),
+            Self::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A problem found by [`Graph::validate`].
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+#[non_exhaustive]
+pub enum Diagnostic {
+    #[error("Edge {source_node:?} -> {target:?} refers to {direction} {index} of node {node:?}, which only has {num_ports} {direction}(s)")]
+    PortOutOfRange {
+        source_node: NodeIndex,
+        target: NodeIndex,
+        node: NodeIndex,
+        direction: PortDirection,
+        index: u32,
+        num_ports: usize,
+    },
+    #[error("Nodes {0:?} form a feedback loop; the edge closing it carries the previous block's output, so the loop's delay depends on the block size")]
+    Cycle(Vec<NodeIndex>),
+    #[error("Graph output {0} is not connected to anything and will stay silent")]
+    UndrivenOutput(usize),
+    #[error("Input {input} of node {node:?} is connected to more than one source; only one of them will be used")]
+    MultipleSources { node: NodeIndex, input: u32 },
+    #[error("Node {node:?} ({name}) does not contribute to any graph output")]
+    UnreachableNode { node: NodeIndex, name: String },
+    #[error("Input `{name}` of node {node:?} is not connected and will use its default value {default_value}")]
+    UnconnectedInput {
+        node: NodeIndex,
+        input: u32,
+        name: String,
+        default_value: f64,
+    },
+    #[error("Constant {value} connected to input `{name}` of node {node:?} is outside of its range [{min}, {max}]")]
+    ConstantOutOfRange {
+        node: NodeIndex,
+        input: u32,
+        name: String,
+        value: f64,
+        min: f64,
+        max: f64,
+    },
+}
+
+impl Diagnostic {
+    /// Returns how serious this diagnostic is.
+    pub fn severity(&self) -> Severity {
+        match self {
+            Self::PortOutOfRange { .. } => Severity::Error,
+            Self::Cycle(_)
+            | Self::UndrivenOutput(_)
+            | Self::MultipleSources { .. }
+            | Self::UnreachableNode { .. }
+            | Self::ConstantOutOfRange { .. } => Severity::Warning,
+            Self::UnconnectedInput { .. } => Severity::Info,
+        }
+    }
+
+    /// Returns `true` if this diagnostic is an [`Error`](Severity::Error).
+    pub fn is_error(&self) -> bool {
+        self.severity() == Severity::Error
+    }
+}
+
+impl Graph {
+    /// Checks the graph for common mistakes, returning a list of [`Diagnostic`]s sorted from most to least severe.
+    ///
+    /// An empty list means no problems were found. Graphs with [`Error`](Severity::Error) diagnostics will not process correctly;
+    /// [`Runtime`](crate::runtime::Runtime) refuses to run them.
+    pub fn validate(&self) -> Vec<Diagnostic> {
+        let digraph = self.digraph();
+        let mut diagnostics = Vec::new();
+
+        for edge in digraph.edge_references() {
+            let weight = edge.weight();
+            let checks = [
+                (
+                    edge.source(),
+                    PortDirection::Output,
+                    weight.source_output,
+                    digraph[edge.source()].outputs().len(),
+                ),
+                (
+                    edge.target(),
+                    PortDirection::Input,
+                    weight.target_input,
+                    digraph[edge.target()].inputs().len(),
+                ),
+            ];
+            for (node, direction, index, num_ports) in checks {
+                if index as usize >= num_ports {
+                    diagnostics.push(Diagnostic::PortOutOfRange {
+                        source_node: edge.source(),
+                        target: edge.target(),
+                        node,
+                        direction,
+                        index,
+                        num_ports,
+                    });
+                }
+            }
+        }
+
+        for scc in petgraph::algo::tarjan_scc(digraph) {
+            if scc.len() > 1 {
+                let mut nodes = scc;
+                nodes.sort();
+                diagnostics.push(Diagnostic::Cycle(nodes));
+            }
+        }
+
+        for (i, &output) in self.output_indices().iter().enumerate() {
+            match digraph.edges_directed(output, Direction::Incoming).count() {
+                0 => diagnostics.push(Diagnostic::UndrivenOutput(i)),
+                1 => {}
+                _ => diagnostics.push(Diagnostic::MultipleSources {
+                    node: output,
+                    input: 0,
+                }),
+            }
+        }
+
+        // every node an output can be reached from
+        let mut contributing = HashSet::new();
+        let reversed = Reversed(digraph);
+        for &output in self.output_indices() {
+            let mut dfs = Dfs::new(reversed, output);
+            while let Some(node) = dfs.next(reversed) {
+                contributing.insert(node);
+            }
+        }
+
+        for node in digraph.node_indices() {
+            let GraphNode::Processor(processor) = &digraph[node] else {
+                continue;
+            };
+
+            if !contributing.contains(&node) {
+                diagnostics.push(Diagnostic::UnreachableNode {
+                    node,
+                    name: processor.name().to_owned(),
+                });
+            }
+
+            let input_spec = processor.input_spec();
+            let mut sources = vec![0usize; input_spec.len()];
+            for edge in digraph.edges_directed(node, Direction::Incoming) {
+                let input = edge.weight().target_input;
+                let Some(spec) = input_spec.get(input as usize) else {
+                    // already reported as out of range
+                    continue;
+                };
+                sources[input as usize] += 1;
+
+                if let Some(value) = constant_value(&digraph[edge.source()]) {
+                    if value < spec.min || value > spec.max {
+                        diagnostics.push(Diagnostic::ConstantOutOfRange {
+                            node,
+                            input,
+                            name: spec.name.to_string(),
+                            value,
+                            min: spec.min,
+                            max: spec.max,
+                        });
+                    }
+                }
+            }
+
+            for (input, (spec, count)) in input_spec.iter().zip(sources).enumerate() {
+                match count {
+                    0 => diagnostics.push(Diagnostic::UnconnectedInput {
+                        node,
+                        input: input as u32,
+                        name: spec.name.to_string(),
+                        default_value: spec.default_value,
+                    }),
+                    1 => {}
+                    _ => diagnostics.push(Diagnostic::MultipleSources {
+                        node,
+                        input: input as u32,
+                    }),
+                }
+            }
+        }
+
+        // stable, so diagnostics of the same severity stay in the order they were found
+        diagnostics.sort_by_key(|diagnostic| std::cmp::Reverse(diagnostic.severity()));
+        diagnostics
+    }
+}
+
+/// Returns the value of the given node if it's a [`ConstantProc`].
+fn constant_value(node: &GraphNode) -> Option<f64> {
+    match node {
+        GraphNode::Processor(processor) if processor.name() == ConstantProc::NAME => processor
+            .params()
+            .get("value")
+            .and_then(|value| value.as_f64()),
+        _ => None,
+    }
+}
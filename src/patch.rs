@@ -0,0 +1,387 @@
+//! A human-readable, serializable description of a [`Graph`].
+//!
+//! Patches describe every node by its processor type name and construction [`Params`], and every edge by the names of the ports it connects.
+//! Nodes running at a different [`Rate`] than their processor's natural rate also record it, e.g. `"rate": "block"` or `"rate": { "divided": 16 }`.
+//! They can be saved to and loaded from JSON, e.g. for version-controlling patches or exchanging them between tools.
+//!
+//! ```json
+//! {
+//!   "inputs": [],
+//!   "outputs": ["out0"],
+//!   "nodes": [
+//!     { "name": "n1", "type": "daprs::builtins::oscillators::SineOscillator" },
+//!     { "name": "n2", "type": "daprs::builtins::math::ConstantProc", "params": { "value": 440.0 } }
+//!   ],
+//!   "edges": [
+//!     { "from": "n2.out", "to": "n1.frequency" },
+//!     { "from": "n1.out", "to": "out0.in" }
+//!   ]
+//! }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+
+use crate::{
+    graph::{node::GraphNode, Graph, GraphConstructionError, NodeIndex},
+    processor::{Param, Params, Rate, SignalSpec},
+    registry::{ProcessorRegistry, RegistryError},
+};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum PatchError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid patch format: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("Registry error: {0}")]
+    Registry(#[from] RegistryError),
+    #[error("Duplicate node name `{0}`")]
+    DuplicateNode(String),
+    #[error("Unknown node `{0}`")]
+    UnknownNode(String),
+    #[error("Node `{node}` has no input named `{port}`")]
+    UnknownInput { node: String, port: String },
+    #[error("Node `{node}` has no output named `{port}`")]
+    UnknownOutput { node: String, port: String },
+    #[error("Invalid port reference `{0}`; expected `node.port`")]
+    InvalidPortRef(String),
+    #[error("Graph construction error: {0}")]
+    Graph(#[from] GraphConstructionError),
+    #[error("Node `{node}` is a `{processor}` processor, which can't be saved in a patch")]
+    Unserializable { node: String, processor: String },
+}
+
+pub type PatchResult<T> = Result<T, PatchError>;
+
+/// A reference to a named port of a named node, written as `node.port`.
+///
+/// The port can also be given by its index, which is used for ports without a name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PortRef {
+    pub node: String,
+    pub port: String,
+}
+
+impl PortRef {
+    pub fn new(node: impl Into<String>, port: impl Into<String>) -> Self {
+        Self {
+            node: node.into(),
+            port: port.into(),
+        }
+    }
+}
+
+impl TryFrom<String> for PortRef {
+    type Error = PatchError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.rsplit_once('.') {
+            Some((node, port)) if !node.is_empty() && !port.is_empty() => Ok(Self::new(node, port)),
+            _ => Err(PatchError::InvalidPortRef(value)),
+        }
+    }
+}
+
+impl From<PortRef> for String {
+    fn from(value: PortRef) -> Self {
+        format!("{}.{}", value.node, value.port)
+    }
+}
+
+impl std::fmt::Display for PortRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.node, self.port)
+    }
+}
+
+/// A processor node in a [`Patch`].
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct PatchNode {
+    /// The name other nodes and edges use to refer to this node.
+    pub name: String,
+    /// The [`Process::name`](crate::processor::Process::name) of the node's processor.
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// The parameters the node's processor is constructed with.
+    #[serde(default, skip_serializing_if = "Params::is_empty")]
+    pub params: Params,
+    /// The [`Rate`] the node runs at, if it differs from its processor's natural rate.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate: Option<Rate>,
+}
+
+/// A connection between two ports in a [`Patch`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PatchEdge {
+    pub from: PortRef,
+    pub to: PortRef,
+}
+
+/// A serializable description of a [`Graph`].
+///
+/// Graph inputs and outputs are named nodes with a single port each (`in` and `out`), and share a namespace with the processor nodes.
+#[derive(Debug, Default, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Patch {
+    /// The names of the graph's input nodes, in order.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// The names of the graph's output nodes, in order.
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// The graph's processor nodes.
+    #[serde(default)]
+    pub nodes: Vec<PatchNode>,
+    /// The connections between the nodes.
+    #[serde(default)]
+    pub edges: Vec<PatchEdge>,
+}
+
+impl Patch {
+    /// Creates a new, empty [`Patch`].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describes the given [`Graph`] as a [`Patch`].
+    ///
+    /// Returns [`PatchError::Unserializable`] if the graph contains a processor that can't be saved (see [`Process::is_serializable`](crate::processor::Process::is_serializable)),
+    /// such as the closure-based processors in [`builtins::func`](crate::builtins::func).
+    pub fn from_graph(graph: &Graph) -> PatchResult<Self> {
+        let digraph = graph.digraph();
+        let mut names = HashMap::new();
+        let mut patch = Patch::new();
+
+        for (i, &node) in graph.input_indices().iter().enumerate() {
+            let name = format!("in{i}");
+            names.insert(node, name.clone());
+            patch.inputs.push(name);
+        }
+
+        for (i, &node) in graph.output_indices().iter().enumerate() {
+            let name = format!("out{i}");
+            names.insert(node, name.clone());
+            patch.outputs.push(name);
+        }
+
+        for node in digraph.node_indices() {
+            if let GraphNode::Processor(processor) = &digraph[node] {
+                let name = format!("n{}", node.index());
+                if !processor.is_serializable() {
+                    return Err(PatchError::Unserializable {
+                        node: name,
+                        processor: processor.name().to_owned(),
+                    });
+                }
+                names.insert(node, name.clone());
+                patch.nodes.push(PatchNode {
+                    name,
+                    kind: processor.name().to_owned(),
+                    params: processor.params(),
+                    rate: (processor.rate() != processor.natural_rate()).then(|| processor.rate()),
+                });
+            }
+        }
+
+        for edge in digraph.edge_references() {
+            let weight = edge.weight();
+            let source_spec = digraph[edge.source()].output_spec();
+            let target_spec = digraph[edge.target()].input_spec();
+            patch.edges.push(PatchEdge {
+                from: PortRef::new(
+                    names[&edge.source()].clone(),
+                    port_name(&source_spec, weight.source_output),
+                ),
+                to: PortRef::new(
+                    names[&edge.target()].clone(),
+                    port_name(&target_spec, weight.target_input),
+                ),
+            });
+        }
+
+        Ok(patch)
+    }
+
+    /// Builds a [`Graph`] from this [`Patch`], constructing builtin processors by name.
+    pub fn to_graph(&self) -> PatchResult<Graph> {
+        self.to_graph_with(&ProcessorRegistry::default())
+    }
+
+    /// Builds a [`Graph`] from this [`Patch`], using the given [`ProcessorRegistry`] to create each node's processor from its type name and parameters.
+    pub fn to_graph_with(&self, registry: &ProcessorRegistry) -> PatchResult<Graph> {
+        let mut graph = Graph::new();
+        let mut nodes: HashMap<&str, NodeIndex> = HashMap::new();
+
+        for name in self.inputs.iter() {
+            insert_node(&mut nodes, name, graph.add_input())?;
+        }
+
+        for name in self.outputs.iter() {
+            insert_node(&mut nodes, name, graph.add_output())?;
+        }
+
+        for node in self.nodes.iter() {
+            let mut processor = registry.create(&node.kind, &node.params)?;
+            if let Some(rate) = node.rate {
+                processor.set_rate(rate);
+            }
+            insert_node(
+                &mut nodes,
+                &node.name,
+                graph.add_processor_object(processor),
+            )?;
+        }
+
+        let node_index = |name: &str| {
+            nodes
+                .get(name)
+                .copied()
+                .ok_or_else(|| PatchError::UnknownNode(name.to_owned()))
+        };
+
+        for edge in self.edges.iter() {
+            let source = node_index(&edge.from.node)?;
+            let target = node_index(&edge.to.node)?;
+
+            let source_output = port_index(&graph.digraph()[source].output_spec(), &edge.from.port)
+                .ok_or_else(|| PatchError::UnknownOutput {
+                    node: edge.from.node.clone(),
+                    port: edge.from.port.clone(),
+                })?;
+            let target_input = port_index(&graph.digraph()[target].input_spec(), &edge.to.port)
+                .ok_or_else(|| PatchError::UnknownInput {
+                    node: edge.to.node.clone(),
+                    port: edge.to.port.clone(),
+                })?;
+
+            graph.connect(source, source_output, target, target_input)?;
+        }
+
+        Ok(graph)
+    }
+
+    /// Renames the node (or graph input/output) with the given name, including all references to it in edges.
+    pub fn rename_node(&mut self, old: &str, new: &str) {
+        for name in self.inputs.iter_mut().chain(self.outputs.iter_mut()) {
+            if name == old {
+                *name = new.to_owned();
+            }
+        }
+        for node in self.nodes.iter_mut() {
+            if node.name == old {
+                node.name = new.to_owned();
+            }
+        }
+        for edge in self.edges.iter_mut() {
+            for port in [&mut edge.from, &mut edge.to] {
+                if port.node == old {
+                    port.node = new.to_owned();
+                }
+            }
+        }
+    }
+
+    /// Renames several nodes (or graph inputs/outputs) at once, from the keys of `renames` to their values, including all references to them in edges.
+    ///
+    /// Since all nodes are renamed in a single pass, names can be swapped, or reused by another node that is renamed at the same time.
+    /// Returns [`PatchError::DuplicateNode`] if two nodes would end up with the same name, in which case the patch is left as it was.
+    pub fn rename_nodes(&mut self, renames: &HashMap<String, String>) -> PatchResult<()> {
+        let rename = |name: &str| {
+            renames
+                .get(name)
+                .cloned()
+                .unwrap_or_else(|| name.to_owned())
+        };
+
+        let mut names = HashSet::new();
+        let all_names = self.inputs.iter().chain(self.outputs.iter());
+        for name in all_names.chain(self.nodes.iter().map(|node| &node.name)) {
+            let name = rename(name);
+            if !names.insert(name.clone()) {
+                return Err(PatchError::DuplicateNode(name));
+            }
+        }
+
+        for name in self.inputs.iter_mut().chain(self.outputs.iter_mut()) {
+            *name = rename(name);
+        }
+        for node in self.nodes.iter_mut() {
+            node.name = rename(&node.name);
+        }
+        for edge in self.edges.iter_mut() {
+            for port in [&mut edge.from, &mut edge.to] {
+                port.node = rename(&port.node);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Converts this [`Patch`] into a [`Param`], e.g. for storing it as a parameter of a processor that contains a graph.
+    pub fn to_param(&self) -> Param {
+        serde_json::to_value(self)
+            .and_then(serde_json::from_value)
+            .expect("patches are always representable as params")
+    }
+
+    /// Converts a [`Param`] created by [`Patch::to_param`] back into a [`Patch`].
+    pub fn from_param(param: &Param) -> PatchResult<Self> {
+        Ok(serde_json::to_value(param).and_then(serde_json::from_value)?)
+    }
+
+    /// Parses a [`Patch`] from a JSON string.
+    pub fn from_json(json: &str) -> PatchResult<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Serializes this [`Patch`] to a pretty-printed JSON string.
+    pub fn to_json(&self) -> PatchResult<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Loads a [`Patch`] from the JSON file at the given path.
+    pub fn load(file_path: impl AsRef<Path>) -> PatchResult<Self> {
+        Self::from_json(&std::fs::read_to_string(file_path)?)
+    }
+
+    /// Saves this [`Patch`] as JSON to the file at the given path.
+    pub fn save(&self, file_path: impl AsRef<Path>) -> PatchResult<()> {
+        std::fs::write(file_path, self.to_json()?)?;
+        Ok(())
+    }
+}
+
+fn insert_node<'a>(
+    nodes: &mut HashMap<&'a str, NodeIndex>,
+    name: &'a str,
+    index: NodeIndex,
+) -> PatchResult<()> {
+    if nodes.insert(name, index).is_some() {
+        return Err(PatchError::DuplicateNode(name.to_owned()));
+    }
+    Ok(())
+}
+
+/// Returns the name of the port at the given index, or the index itself if the port is unnamed.
+fn port_name(spec: &[SignalSpec], index: u32) -> String {
+    match spec.get(index as usize) {
+        Some(spec) if !spec.name.is_empty() => spec.name.to_string(),
+        _ => index.to_string(),
+    }
+}
+
+/// Returns the index of the port with the given name, falling back to parsing the name as an index.
+fn port_index(spec: &[SignalSpec], name: &str) -> Option<u32> {
+    if let Some(index) = spec.iter().position(|s| s.name == name) {
+        return Some(index as u32);
+    }
+    name.parse()
+        .ok()
+        .filter(|&index: &u32| (index as usize) < spec.len())
+}
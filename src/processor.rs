@@ -1,53 +1,274 @@
-use std::fmt::Debug;
+use std::{
+    borrow::Cow,
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Arc,
+    },
+};
 
-use crate::signal::Buffer;
+use crate::{
+    graph::{latency::EdgeDelay, snapshot::NodeSnapshot},
+    registry::ParamError,
+    signal::{Buffer, Sample, Timing},
+};
+
+/// The kind of signal an input or output carries.
+///
+/// Kinds are checked when connecting nodes, converting between them where sensible (see [`Coercion`](crate::builtins::coerce::Coercion)),
+/// and decide how often a processor runs: processors whose inputs and outputs are all [`Control`](SignalKind::Control) signals are processed once per block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SignalKind {
+    /// A signal with a new value every sample.
+    #[default]
+    Audio,
+    /// A slowly changing signal that only needs one value per block.
+    Control,
+    /// Single-sample impulses marking events: non-zero for one sample at each event, and zero otherwise.
+    Trigger,
+    /// An on/off signal: non-zero while on, and zero while off.
+    Gate,
+    /// Whole numbers, e.g. for selecting an item or a step.
+    Index,
+}
+
+impl std::fmt::Display for SignalKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Audio => f.write_str("audio"),
+            Self::Control => f.write_str("control"),
+            Self::Trigger => f.write_str("trigger"),
+            Self::Gate => f.write_str("gate"),
+            Self::Index => f.write_str("index"),
+        }
+    }
+}
+
+/// How often a [`Processor`] runs, relative to the audio rate of the graph it's in.
+///
+/// A processor running at a reduced rate computes fewer samples per block, as if running at a lower sample rate.
+/// Its outputs are interpolated linearly where they feed audio-rate inputs (and held, for [`Trigger`](SignalKind::Trigger),
+/// [`Gate`](SignalKind::Gate) and [`Index`](SignalKind::Index) outputs), and its inputs are decimated.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Rate {
+    /// One sample per audio sample.
+    #[default]
+    Audio,
+    /// One sample per block.
+    Block,
+    /// One sample per the given number of audio samples.
+    Divided(usize),
+}
+
+impl Rate {
+    /// Returns the number of samples a processor running at this rate computes in a block of the given size.
+    ///
+    /// For [`Rate::Divided`], this depends on the `phase`: the number of audio samples from the start of the block to the first sample computed in it,
+    /// which is carried over from the previous block (see [`Rate::next_phase`]) so that the samples stay evenly spaced when the divisor doesn't
+    /// divide the block size.
+    #[inline]
+    pub fn block_len(self, block_size: usize, phase: usize) -> usize {
+        match self {
+            Self::Audio => block_size,
+            Self::Block => block_size.min(1),
+            Self::Divided(divisor) => block_size
+                .checked_sub(phase)
+                .map_or(0, |len| len.div_ceil(divisor.max(1))),
+        }
+    }
+
+    /// Returns the phase of the block following one of the given size and phase, see [`Rate::block_len`].
+    #[inline]
+    pub fn next_phase(self, block_size: usize, phase: usize) -> usize {
+        match self {
+            Self::Audio | Self::Block => 0,
+            Self::Divided(divisor) => {
+                phase + self.block_len(block_size, phase) * divisor.max(1) - block_size
+            }
+        }
+    }
+
+    /// Returns the sample rate a processor running at this rate runs at, given the audio sample rate and block size.
+    #[inline]
+    pub fn sample_rate(self, sample_rate: f64, block_size: usize) -> f64 {
+        match self {
+            Self::Audio => sample_rate,
+            Self::Block if block_size == 0 => sample_rate,
+            Self::Block => sample_rate / block_size as f64,
+            Self::Divided(divisor) => sample_rate / divisor.max(1) as f64,
+        }
+    }
+
+    /// Returns the [`Timing`] of the samples a processor running at this rate computes in a block of the given size and phase.
+    #[inline]
+    pub fn timing(self, block_size: usize, phase: usize) -> Timing {
+        match self {
+            Self::Audio => Timing::AUDIO,
+            Self::Block => Timing {
+                step: block_size,
+                phase: 0,
+            },
+            Self::Divided(divisor) => Timing {
+                step: divisor.max(1),
+                phase,
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Rate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Audio => f.write_str("audio"),
+            Self::Block => f.write_str("block"),
+            Self::Divided(divisor) => write!(f, "audio / {divisor}"),
+        }
+    }
+}
 
 /// Information about an input/output of a [`Process`] implementor.
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct SignalSpec {
-    pub name: &'static str,
+    pub name: Cow<'static, str>,
     pub min: f64,
     pub max: f64,
     pub default_value: f64,
+    pub kind: SignalKind,
 }
 
 impl Default for SignalSpec {
     /// Creates a new unnamed and unbounded [`SignalSpec`] (min = [`f64::MIN`], max = [`f64::MAX`]).
     fn default() -> Self {
         Self {
-            name: "",
+            name: Cow::Borrowed(""),
             min: f64::MIN,
             max: f64::MAX,
             default_value: 0.0,
+            kind: SignalKind::Audio,
         }
     }
 }
 
 impl SignalSpec {
     /// Creates a new bounded [`SignalSpec`] with the given name, minimum and maximum values.
-    pub fn new(name: &'static str, min: f64, max: f64, default_value: f64) -> Self {
+    pub fn new(name: impl Into<Cow<'static, str>>, min: f64, max: f64, default_value: f64) -> Self {
         Self {
-            name,
+            name: name.into(),
             min,
             max,
             default_value,
+            kind: SignalKind::Audio,
         }
     }
 
     /// Creates a new unbounded [`SignalSpec`] with the given name.
-    pub fn unbounded(name: &'static str, default_value: f64) -> Self {
+    pub fn unbounded(name: impl Into<Cow<'static, str>>, default_value: f64) -> Self {
         Self {
-            name,
+            name: name.into(),
             default_value,
             ..Default::default()
         }
     }
+
+    /// Returns this [`SignalSpec`] with the given [`SignalKind`].
+    pub fn with_kind(mut self, kind: SignalKind) -> Self {
+        self.kind = kind;
+        self
+    }
+}
+
+/// A named collection of [`Param`]s, e.g. the arguments a [`Process`] was constructed with.
+pub type Params = BTreeMap<String, Param>;
+
+/// A serializable parameter value.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(untagged)]
+pub enum Param {
+    Bool(bool),
+    Float(f64),
+    String(String),
+    List(Vec<Param>),
+    Map(Params),
+}
+
+impl Param {
+    /// Returns the value as an [`f64`], if it is a number.
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Self::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a [`bool`], if it is a boolean.
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a string slice, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a slice of [`Param`]s, if it is a list.
+    pub fn as_list(&self) -> Option<&[Param]> {
+        match self {
+            Self::List(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as [`Params`], if it is a map.
+    pub fn as_map(&self) -> Option<&Params> {
+        match self {
+            Self::Map(values) => Some(values),
+            _ => None,
+        }
+    }
+}
+
+impl From<f64> for Param {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+
+impl From<bool> for Param {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<&str> for Param {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+
+impl From<String> for Param {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
 }
 
 /// A trait for processing audio or control signals.
 ///
 /// This is usually used as part of a [`Processor`], operating on its internal input/output buffers.
 pub trait Process: 'static + Send + Sync + ProcessClone {
+    /// Returns the name this [`Process`] is saved under in a [`Patch`](crate::patch::Patch), and registered under in a [`ProcessorRegistry`](crate::registry::ProcessorRegistry).
+    ///
+    /// This defaults to [`std::any::type_name`], which isn't guaranteed to stay the same across compiler versions,
+    /// so processors meant to be saved should return a fixed name instead, such as their path built with [`module_path!`].
     fn name(&self) -> &str {
         std::any::type_name::<Self>()
     }
@@ -58,6 +279,18 @@ pub trait Process: 'static + Send + Sync + ProcessClone {
     /// Returns information about the outputs this [`Process`] produces.
     fn output_spec(&self) -> Vec<SignalSpec>;
 
+    /// Returns the parameters needed to construct an equivalent [`Process`], used when saving a graph as a [`Patch`](crate::patch::Patch).
+    ///
+    /// Processors without any construction parameters don't need to override this.
+    fn params(&self) -> Params {
+        Params::new()
+    }
+
+    /// Returns `false` if this [`Process`] can't be saved as part of a [`Patch`](crate::patch::Patch), e.g. because it wraps a closure.
+    fn is_serializable(&self) -> bool {
+        true
+    }
+
     /// Returns the number of input buffers/channels this [`Process`] expects.
     fn num_inputs(&self) -> usize {
         self.input_spec().len()
@@ -75,6 +308,42 @@ pub trait Process: 'static + Send + Sync + ProcessClone {
     #[allow(unused)]
     fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {}
 
+    /// Clears the processor's internal state (e.g. delay lines, filter memory or oscillator phase), as if it had just been created.
+    ///
+    /// This is called by [`Runtime::reset`](crate::runtime::Runtime::reset), e.g. before each offline render.
+    fn reset(&mut self) {}
+
+    /// Returns the number of samples this [`Process`] keeps producing output for after its inputs fall silent, e.g. the decay of a reverb or delay.
+    ///
+    /// Offline rendering continues for this long past the requested duration. Processors with an endless tail (e.g. feedback at unity gain)
+    /// should return the length after which the rest can be cut off.
+    fn tail_length(&self) -> usize {
+        0
+    }
+
+    /// Returns the number of samples by which this [`Process`] delays its inputs at its outputs, e.g. due to lookahead or block-based processing.
+    ///
+    /// The graph delays parallel paths to compensate (see [`graph::latency`](crate::graph::latency)). Processors without latency don't need to override this.
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// Returns the processor's internal state (e.g. oscillator phase or filter memory) and any parameters changed since it was created,
+    /// as a serializable value that [`Process::load_state`] accepts. This is used by [`Graph::snapshot`](crate::graph::Graph::snapshot).
+    ///
+    /// Processors without any state return `None`, which is the default.
+    fn save_state(&self) -> Option<Param> {
+        None
+    }
+
+    /// Restores the state returned by an earlier [`Process::save_state`] call, so that processing continues exactly where it was saved.
+    ///
+    /// Processors without any state don't need to override this; the default ignores the given state.
+    #[allow(unused)]
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        Ok(())
+    }
+
     /// Processes the given input buffers and writes the results to the given output buffers.
     ///
     /// The number of input and output buffers must match the numbers returned by [`Process::num_inputs`] and [`Process::num_outputs`].
@@ -86,6 +355,54 @@ pub trait Process: 'static + Send + Sync + ProcessClone {
     }
 }
 
+/// The per-sample logic of a processor using [`#[derive(Process)]`](derive@Process).
+///
+/// The derived [`Process`] impl reads the current sample of each input into the struct's `#[input]` fields, calls [`SampleProcess::tick`],
+/// and writes the struct's `#[output]` fields to the outputs, once per sample. The other methods are forwarded from [`Process`].
+pub trait SampleProcess {
+    /// Computes the outputs for the current sample from the inputs.
+    fn tick(&mut self);
+
+    /// See [`Process::params`].
+    fn params(&self) -> Params {
+        Params::new()
+    }
+
+    /// See [`Process::prepare`].
+    fn prepare(&mut self) {}
+
+    /// See [`Process::resize_buffers`].
+    #[allow(unused)]
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {}
+
+    /// See [`Process::reset`].
+    fn reset(&mut self) {}
+
+    /// See [`Process::tail_length`].
+    fn tail_length(&self) -> usize {
+        0
+    }
+
+    /// See [`Process::latency`].
+    fn latency(&self) -> usize {
+        0
+    }
+
+    /// See [`Process::save_state`].
+    fn save_state(&self) -> Option<Param> {
+        None
+    }
+
+    /// See [`Process::load_state`].
+    #[allow(unused)]
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        Ok(())
+    }
+}
+
+/// Derives [`Process`] for a struct with `#[input]` and `#[output]` fields and a [`SampleProcess`] impl.
+pub use daprs_macros::Process;
+
 mod sealed {
     pub trait Sealed {}
     impl<T: Clone> Sealed for T {}
@@ -94,20 +411,95 @@ mod sealed {
 #[doc(hidden)]
 pub trait ProcessClone: sealed::Sealed {
     fn clone_boxed(&self) -> Box<dyn Process>;
+
+    fn as_any(&self) -> &dyn std::any::Any;
 }
 
-impl<T: ?Sized> ProcessClone for T
+impl<T> ProcessClone for T
 where
     T: Clone + Process,
 {
     fn clone_boxed(&self) -> Box<dyn Process> {
         Box::new(self.clone())
     }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
 }
 
 impl Clone for Box<dyn Process> {
     fn clone(&self) -> Self {
-        self.clone_boxed()
+        // clone the boxed processor itself, rather than boxing this box
+        (**self).clone_boxed()
+    }
+}
+
+/// Boxed processors can be used wherever a [`Process`] type is expected, e.g. to wrap a processor created by a
+/// [`ProcessorRegistry`](crate::registry::ProcessorRegistry) in another processor.
+impl Process for Box<dyn Process> {
+    fn name(&self) -> &str {
+        self.as_ref().name()
+    }
+
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        self.as_ref().input_spec()
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        self.as_ref().output_spec()
+    }
+
+    fn params(&self) -> Params {
+        self.as_ref().params()
+    }
+
+    fn is_serializable(&self) -> bool {
+        self.as_ref().is_serializable()
+    }
+
+    fn num_inputs(&self) -> usize {
+        self.as_ref().num_inputs()
+    }
+
+    fn num_outputs(&self) -> usize {
+        self.as_ref().num_outputs()
+    }
+
+    fn prepare(&mut self) {
+        self.as_mut().prepare()
+    }
+
+    fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        self.as_mut().resize_buffers(sample_rate, block_size)
+    }
+
+    fn reset(&mut self) {
+        self.as_mut().reset()
+    }
+
+    fn tail_length(&self) -> usize {
+        self.as_ref().tail_length()
+    }
+
+    fn latency(&self) -> usize {
+        self.as_ref().latency()
+    }
+
+    fn save_state(&self) -> Option<Param> {
+        self.as_ref().save_state()
+    }
+
+    fn load_state(&mut self, state: &Param) -> Result<(), ParamError> {
+        self.as_mut().load_state(state)
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        self.as_mut().process(inputs, outputs)
+    }
+
+    fn processor(&self) -> Processor {
+        Processor::new_from_boxed(self.as_ref().clone_boxed())
     }
 }
 
@@ -117,14 +509,128 @@ impl Debug for dyn Process {
     }
 }
 
+/// How long a [`Processor`] takes to crossfade between its [`BypassState`]s, in seconds.
+const BYPASS_FADE_TIME: f64 = 0.005;
+
+/// Whether a [`Processor`] is processing its inputs, or outputting something else in its place.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "snake_case")]
+#[repr(u8)]
+pub enum BypassState {
+    /// The processor runs normally.
+    #[default]
+    Active,
+    /// The processor isn't run, and each input is passed through to the output with the same index (outputs without a matching input are silent),
+    /// delayed by the processor's [`latency`](Process::latency) so that the signal stays aligned with the rest of the graph.
+    Bypassed,
+    /// The processor isn't run, and all outputs are silent.
+    Muted,
+}
+
+impl BypassState {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => Self::Bypassed,
+            2 => Self::Muted,
+            _ => Self::Active,
+        }
+    }
+
+    /// Returns the gains of the processed signal and of the passed-through inputs in this state.
+    fn gains(self) -> (f64, f64) {
+        match self {
+            Self::Active => (1.0, 0.0),
+            Self::Bypassed => (0.0, 1.0),
+            Self::Muted => (0.0, 0.0),
+        }
+    }
+}
+
+/// The [`BypassState`] of a [`Processor`], shared with its [`BypassHandle`]s but not with its clones.
+struct BypassCell(Arc<AtomicU8>);
+
+impl Clone for BypassCell {
+    fn clone(&self) -> Self {
+        Self(Arc::new(AtomicU8::new(self.0.load(Ordering::Relaxed))))
+    }
+}
+
+/// A handle for bypassing or muting a [`Processor`], e.g. from a UI thread while the graph is running.
+///
+/// Changes take effect at the start of the next block the processor runs, crossfading over a few milliseconds to avoid clicks.
+/// A handle only controls the processor it was created for: clones of the processor (e.g. in a cloned [`Graph`](crate::graph::Graph),
+/// or the voices of a [`Poly`](crate::graph::poly::Poly) node) start out in the same state, but can be bypassed independently.
+#[derive(Debug, Clone)]
+pub struct BypassHandle {
+    state: Arc<AtomicU8>,
+}
+
+impl BypassHandle {
+    /// Returns the current state.
+    pub fn get(&self) -> BypassState {
+        BypassState::from_u8(self.state.load(Ordering::Relaxed))
+    }
+
+    /// Sets the state.
+    pub fn set(&self, state: BypassState) {
+        self.state.store(state as u8, Ordering::Relaxed);
+    }
+
+    /// Makes the processor run normally again.
+    pub fn activate(&self) {
+        self.set(BypassState::Active);
+    }
+
+    /// Passes the processor's inputs through to its outputs instead of running it.
+    pub fn bypass(&self) {
+        self.set(BypassState::Bypassed);
+    }
+
+    /// Silences the processor's outputs instead of running it.
+    pub fn mute(&self) {
+        self.set(BypassState::Muted);
+    }
+}
+
 /// A node in the audio graph that processes signals.
 ///
 /// This is a wrapper around a [`Box<dyn Process>`](Process) that provides input and output buffers for the processor to use.
+///
+/// Processors run at the audio [`Rate`] by default, except those whose inputs and outputs are all [`Control`](SignalKind::Control) signals,
+/// which only run once per block, as if running at a sample rate of `sample_rate / block_size` with a block size of 1.
+/// The rate can be changed with [`Processor::set_rate`].
+///
+/// Processors can be bypassed or muted while running through a [`BypassHandle`], see [`Processor::bypass_handle`].
 #[derive(Clone)]
 pub struct Processor {
     processor: Box<dyn Process>,
     inputs: Box<[Buffer]>,
     outputs: Box<[Buffer]>,
+    natural_rate: Rate,
+    rate: Rate,
+    sample_rate: f64,
+    block_size: usize,
+    /// The number of samples the wrapped processor's buffers were last resized to.
+    len: usize,
+    /// The [`Rate::block_len`] phase of the samples in the output buffers, and of the next block.
+    phase: usize,
+    next_phase: usize,
+    /// The two samples each output produced before the current block, oldest first, when running at a reduced rate.
+    interpolation_starts: Box<[[Sample; 2]]>,
+    /// The last two samples each output has produced, oldest first, or `None` before the first block.
+    last_outputs: Option<Box<[[Sample; 2]]>>,
+    /// Whether each output is interpolated (rather than held) when running at a reduced rate.
+    interpolate: Box<[bool]>,
+    bypass: BypassCell,
+    /// The delay of each input passed through while bypassed, and its delayed samples, or nothing if the processor has no latency.
+    thru: Box<[(EdgeDelay, Buffer)]>,
+    /// The current gains of the processed signal and of the passed-through inputs, ramping towards those of the [`BypassState`].
+    wet_gain: f64,
+    thru_gain: f64,
+    /// How much the gains change per sample while crossfading.
+    fade_step: f64,
 }
 
 impl Debug for Processor {
@@ -141,12 +647,25 @@ impl Processor {
 
     /// Creates a new [`Processor`] from the given boxed [`Process`] object.
     pub fn new_from_boxed(processor: Box<dyn Process>) -> Self {
-        let mut input_buffers = Vec::with_capacity(processor.num_inputs());
-        for _spec in processor.input_spec() {
+        let input_spec = processor.input_spec();
+        let output_spec = processor.output_spec();
+        let control_rate = !output_spec.is_empty()
+            && input_spec
+                .iter()
+                .chain(output_spec.iter())
+                .all(|spec| spec.kind == SignalKind::Control);
+        let natural_rate = if control_rate {
+            Rate::Block
+        } else {
+            Rate::Audio
+        };
+
+        let mut input_buffers = Vec::with_capacity(input_spec.len());
+        for _spec in input_spec {
             input_buffers.push(Buffer::zeros(0));
         }
-        let mut output_buffers = Vec::with_capacity(processor.num_outputs());
-        for _spec in processor.output_spec() {
+        let mut output_buffers = Vec::with_capacity(output_spec.len());
+        for _spec in output_spec.iter() {
             output_buffers.push(Buffer::zeros(0));
         }
 
@@ -154,6 +673,27 @@ impl Processor {
             inputs: input_buffers.into_boxed_slice(),
             outputs: output_buffers.into_boxed_slice(),
             processor,
+            natural_rate,
+            rate: natural_rate,
+            sample_rate: 0.0,
+            block_size: 0,
+            len: 0,
+            phase: 0,
+            next_phase: 0,
+            interpolation_starts: output_spec
+                .iter()
+                .map(|spec| [Sample::new(spec.default_value); 2])
+                .collect(),
+            last_outputs: None,
+            bypass: BypassCell(Arc::new(AtomicU8::new(BypassState::Active as u8))),
+            thru: Box::new([]),
+            wet_gain: 1.0,
+            thru_gain: 0.0,
+            fade_step: 1.0,
+            interpolate: output_spec
+                .iter()
+                .map(|spec| matches!(spec.kind, SignalKind::Audio | SignalKind::Control))
+                .collect(),
         }
     }
 
@@ -161,6 +701,26 @@ impl Processor {
         self.processor.name()
     }
 
+    /// Returns the wrapped [`Process`], dropping this processor's buffers.
+    pub fn into_inner(self) -> Box<dyn Process> {
+        self.processor
+    }
+
+    /// Returns a reference to the wrapped [`Process`] if it is of type `P`.
+    pub fn downcast_ref<P: Process>(&self) -> Option<&P> {
+        self.processor.as_ref().as_any().downcast_ref()
+    }
+
+    /// Returns the parameters needed to construct an equivalent processor.
+    pub fn params(&self) -> Params {
+        self.processor.params()
+    }
+
+    /// Returns `false` if this processor can't be saved as part of a [`Patch`](crate::patch::Patch).
+    pub fn is_serializable(&self) -> bool {
+        self.processor.is_serializable()
+    }
+
     /// Returns information about the inputs this [`Processor`] expects.
     pub fn input_spec(&self) -> Vec<SignalSpec> {
         self.processor.input_spec()
@@ -171,17 +731,216 @@ impl Processor {
         self.processor.output_spec()
     }
 
+    /// Returns a handle for bypassing or muting this processor.
+    pub fn bypass_handle(&self) -> BypassHandle {
+        BypassHandle {
+            state: self.bypass.0.clone(),
+        }
+    }
+
+    /// Returns `true` if the processor's [`BypassState`] is shared with a [`BypassHandle`], so that it may change at any time.
+    pub(crate) fn bypass_is_shared(&self) -> bool {
+        Arc::strong_count(&self.bypass.0) > 1
+    }
+
+    /// Returns the processor's current [`BypassState`].
+    #[inline]
+    pub fn bypass_state(&self) -> BypassState {
+        BypassState::from_u8(self.bypass.0.load(Ordering::Relaxed))
+    }
+
+    /// Clears the processor's internal state with [`Process::reset`], along with the state used for interpolating its outputs.
+    pub fn reset(&mut self) {
+        self.processor.reset();
+        self.last_outputs = None;
+        (self.phase, self.next_phase) = (0, 0);
+        for (delay, _) in self.thru.iter_mut() {
+            delay.clear();
+        }
+        self.resize_inputs();
+    }
+
+    /// Saves the processor's state with [`Process::save_state`], along with its [`BypassState`] and everything needed to continue processing from here.
+    pub fn snapshot(&self) -> NodeSnapshot {
+        NodeSnapshot {
+            processor: self.name().to_owned(),
+            state: self.processor.save_state(),
+            bypass: self.bypass_state(),
+            outputs: self
+                .outputs
+                .iter()
+                .map(|output| output.iter().map(|sample| sample.value()).collect())
+                .collect(),
+            interpolation_starts: self
+                .last_outputs
+                .as_ref()
+                .map(|_| pair_values(&self.interpolation_starts))
+                .unwrap_or_default(),
+            last_outputs: self.last_outputs.as_deref().map(pair_values),
+            thru_delays: self
+                .thru
+                .iter()
+                .map(|(delay, _)| delay.samples().map(Sample::value).collect())
+                .collect(),
+            phase: (self.phase, self.next_phase),
+        }
+    }
+
+    /// Restores a state saved by [`Processor::snapshot`], loading the processor's own state with [`Process::load_state`].
+    ///
+    /// Output buffers and delays whose length doesn't match the snapshot are left as they are.
+    ///
+    /// The [`BypassState`] itself isn't restored, since it's shared with the processor's clones and [`BypassHandle`]s.
+    /// Instead, the processor resumes in the saved state, and crossfades to its current one if they differ.
+    pub fn restore(&mut self, snapshot: &NodeSnapshot) -> Result<(), ParamError> {
+        if let Some(state) = &snapshot.state {
+            self.processor.load_state(state)?;
+        }
+
+        (self.wet_gain, self.thru_gain) = snapshot.bypass.gains();
+
+        for (output, samples) in self.outputs.iter_mut().zip(snapshot.outputs.iter()) {
+            if output.len() == samples.len() {
+                for (sample, &value) in output.iter_mut().zip(samples) {
+                    *sample = Sample::new(value);
+                }
+            }
+        }
+
+        self.last_outputs = None;
+        if let Some(last_outputs) = &snapshot.last_outputs {
+            let starts = &snapshot.interpolation_starts;
+            if starts.len() == self.interpolation_starts.len()
+                && last_outputs.len() == self.interpolation_starts.len()
+            {
+                for (start, &values) in self.interpolation_starts.iter_mut().zip(starts) {
+                    *start = values.map(Sample::new);
+                }
+                self.last_outputs = Some(
+                    last_outputs
+                        .iter()
+                        .map(|values| values.map(Sample::new))
+                        .collect(),
+                );
+            }
+        }
+
+        for ((delay, _), samples) in self.thru.iter_mut().zip(&snapshot.thru_delays) {
+            delay.restore(samples);
+        }
+
+        (self.phase, self.next_phase) = snapshot.phase;
+        self.resize_inputs();
+
+        Ok(())
+    }
+
+    /// Returns the number of samples this processor keeps producing output for after its inputs fall silent, as reported by [`Process::tail_length`].
+    #[inline]
+    pub fn tail_length(&self) -> usize {
+        self.processor.tail_length()
+    }
+
+    /// Returns the number of samples by which this processor delays its inputs at its outputs, as reported by [`Process::latency`].
+    #[inline]
+    pub fn latency(&self) -> usize {
+        self.processor.latency()
+    }
+
+    /// Returns `true` if this processor runs at a lower rate than the audio rate.
+    #[inline]
+    pub fn is_control_rate(&self) -> bool {
+        self.rate != Rate::Audio
+    }
+
+    /// Returns the rate this processor runs at.
+    #[inline]
+    pub fn rate(&self) -> Rate {
+        self.rate
+    }
+
+    /// Returns the rate this processor runs at unless told otherwise: [`Rate::Block`] if all of its inputs and outputs are
+    /// [`Control`](SignalKind::Control) signals, and [`Rate::Audio`] otherwise.
+    #[inline]
+    pub fn natural_rate(&self) -> Rate {
+        self.natural_rate
+    }
+
+    /// Sets the rate this processor runs at. This takes effect the next time [`Processor::resize_buffers`] is called.
+    pub fn set_rate(&mut self, rate: Rate) {
+        self.rate = rate;
+    }
+
+    /// Returns the two samples the given output produced before the current block, oldest first,
+    /// which the start of the block is resampled from (see [`Buffer::copy_resampled`]).
+    #[inline]
+    pub fn output_history(&self, output: usize) -> [Sample; 2] {
+        self.interpolation_starts[output]
+    }
+
+    /// Returns `true` if the given output is interpolated (rather than held) where it's upsampled.
+    #[inline]
+    pub fn interpolates(&self, output: usize) -> bool {
+        self.interpolate[output] && self.rate != Rate::Audio
+    }
+
+    /// Returns the [`Timing`] of the samples in the output buffers.
+    #[inline]
+    pub fn output_timing(&self) -> Timing {
+        self.rate.timing(self.block_size, self.phase)
+    }
+
+    /// Returns the [`Timing`] of the samples the input buffers are filled with for the next block.
+    #[inline]
+    pub fn input_timing(&self) -> Timing {
+        self.rate.timing(self.block_size, self.next_phase)
+    }
+
     /// Resizes the input and output buffers to match the given sample rates and block size.
+    ///
+    /// Processors running at a reduced [`Rate`] get shorter buffers, and a correspondingly lower sample rate.
+    /// Those running at a [`Rate::Divided`] rate whose divisor doesn't divide the block size compute a varying number of samples per block,
+    /// so their buffers are resized again as needed while processing.
     pub fn resize_buffers(&mut self, sample_rate: f64, block_size: usize) {
+        self.sample_rate = sample_rate;
+        self.block_size = block_size;
+        let len = self.rate.block_len(block_size, self.next_phase);
+        // make room for the longest block up front, so that resizing while processing doesn't allocate
+        let max_len = self.rate.block_len(block_size, 0);
+
         let input_spec = self.input_spec();
         for (input, spec) in self.inputs.iter_mut().zip(input_spec) {
-            input.resize(block_size, spec.default_value.into());
+            input.resize(max_len, spec.default_value.into());
+            input.resize(len, spec.default_value.into());
         }
         let output_spec = self.output_spec();
         for (output, spec) in self.outputs.iter_mut().zip(output_spec) {
-            output.resize(block_size, spec.default_value.into());
+            output.resize(max_len, spec.default_value.into());
+            output.resize(len, spec.default_value.into());
+        }
+
+        let sample_rate = self.rate.sample_rate(sample_rate, block_size);
+        self.processor.resize_buffers(sample_rate, len);
+        self.len = len;
+        self.fade_step = (BYPASS_FADE_TIME * sample_rate).max(1.0).recip();
+
+        // the latency may depend on the sample rate, so the thru delays are only known now
+        let latency = self.processor.latency();
+        if latency == 0 {
+            self.thru = Box::new([]);
+        } else if self.thru.first().map(|(delay, _)| delay.len()) != Some(latency) {
+            self.thru = (0..self.inputs.len())
+                .map(|_| (EdgeDelay::new(latency), Buffer::zeros(max_len)))
+                .collect();
+        }
+    }
+
+    /// Resizes the input buffers to the length of the next block.
+    fn resize_inputs(&mut self) {
+        let len = self.rate.block_len(self.block_size, self.next_phase);
+        for (input, spec) in self.inputs.iter_mut().zip(self.processor.input_spec()) {
+            input.resize(len, spec.default_value.into());
         }
-        self.processor.resize_buffers(sample_rate, block_size);
     }
 
     /// Returns a slice of the input buffers.
@@ -239,6 +998,105 @@ impl Processor {
             self.processor.num_outputs(),
             "The number of outputs must match the number returned by Process::num_outputs()"
         );
-        self.processor.process(&self.inputs, &mut self.outputs);
+        if self.rate == Rate::Audio {
+            self.run();
+            return;
+        }
+
+        self.phase = self.next_phase;
+        let len = self.rate.block_len(self.block_size, self.phase);
+        for (output, spec) in self.outputs.iter_mut().zip(self.processor.output_spec()) {
+            output.resize(len, spec.default_value.into());
+        }
+        if len != self.len {
+            let sample_rate = self.rate.sample_rate(self.sample_rate, self.block_size);
+            self.processor.resize_buffers(sample_rate, len);
+            self.len = len;
+        }
+        if len > 0 {
+            self.run();
+        }
+
+        // interpolate from the end of the previous block, or hold the first sample at the very start
+        let last_outputs = self.last_outputs.get_or_insert_with(|| {
+            self.outputs
+                .iter()
+                .map(|output| [output.first().copied().unwrap_or_default(); 2])
+                .collect()
+        });
+        for ((start, last), output) in self
+            .interpolation_starts
+            .iter_mut()
+            .zip(last_outputs.iter_mut())
+            .zip(self.outputs.iter())
+        {
+            *start = *last;
+            for &sample in &output[output.len().saturating_sub(2)..] {
+                *last = [last[1], sample];
+            }
+        }
+
+        self.next_phase = self.rate.next_phase(self.block_size, self.phase);
+        self.resize_inputs();
+    }
+
+    /// Runs the wrapped processor on the current buffers, applying the [`BypassState`].
+    #[inline]
+    fn run(&mut self) {
+        // keep the delays running while active, so that bypassing doesn't pass through stale samples
+        for ((delay, thru), input) in self.thru.iter_mut().zip(self.inputs.iter()) {
+            thru.resize(input.len(), Sample::ZERO);
+            thru.copy_from_slice(input);
+            delay.process(thru);
+        }
+
+        let (wet_target, thru_target) = self.bypass_state().gains();
+        let processing = self.wet_gain > 0.0 || wet_target > 0.0;
+        if processing {
+            self.processor.process(&self.inputs, &mut self.outputs);
+        }
+        if (self.wet_gain, self.thru_gain, wet_target, thru_target) != (1.0, 0.0, 1.0, 0.0) {
+            self.apply_bypass(processing, wet_target, thru_target);
+        }
+    }
+
+    /// Mixes the processed outputs (if the processor ran) with the passed-through inputs, ramping the gains towards the given targets.
+    fn apply_bypass(&mut self, processing: bool, wet_target: f64, thru_target: f64) {
+        let len = self.outputs.first().map_or(0, |output| output.len());
+
+        for i in 0..len {
+            self.wet_gain = ramp(self.wet_gain, wet_target, self.fade_step);
+            self.thru_gain = ramp(self.thru_gain, thru_target, self.fade_step);
+
+            for (index, output) in self.outputs.iter_mut().enumerate() {
+                let wet = if processing {
+                    output[i].value() * self.wet_gain
+                } else {
+                    0.0
+                };
+                let input = if self.thru.is_empty() {
+                    self.inputs.get(index)
+                } else {
+                    self.thru.get(index).map(|(_, delayed)| delayed)
+                };
+                let thru = input.map_or(0.0, |input| input[i].value() * self.thru_gain);
+                output[i] = Sample::new(wet + thru);
+            }
+        }
+    }
+}
+
+/// Converts pairs of samples to pairs of values, for a [`NodeSnapshot`].
+fn pair_values(pairs: &[[Sample; 2]]) -> Vec<[f64; 2]> {
+    pairs.iter().map(|pair| pair.map(Sample::value)).collect()
+}
+
+/// Moves `value` towards `target` by at most `step`.
+#[inline]
+fn ramp(value: f64, target: f64, step: f64) -> f64 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
     }
 }
@@ -0,0 +1,256 @@
+//! Construction of processors from their type names and parameters.
+
+use std::collections::HashMap;
+
+use crate::processor::{Param, Params, Process, Processor};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum RegistryError {
+    #[error("Unknown processor type `{0}`")]
+    UnknownProcessor(String),
+    #[error("Processor name `{name}` is ambiguous; it could refer to any of {candidates:?}")]
+    AmbiguousName {
+        name: String,
+        candidates: Vec<String>,
+    },
+    #[error("Invalid parameter `{param}` for processor `{processor}`: {reason}")]
+    InvalidParam {
+        processor: String,
+        param: String,
+        reason: String,
+    },
+}
+
+pub type RegistryResult<T> = Result<T, RegistryError>;
+
+/// An error returned by a processor constructor when it is given a bad parameter.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Invalid parameter `{param}`: {reason}")]
+pub struct ParamError {
+    pub param: String,
+    pub reason: String,
+}
+
+impl ParamError {
+    pub fn new(param: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            param: param.into(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Creates an error for a required parameter that wasn't given.
+    pub fn missing(param: impl Into<String>) -> Self {
+        Self::new(param, "missing required parameter")
+    }
+
+    /// Creates an error for a parameter the processor doesn't accept.
+    pub fn unexpected(param: impl Into<String>) -> Self {
+        Self::new(param, "unexpected parameter")
+    }
+}
+
+/// Typed access to [`Params`] for use in processor constructors.
+pub trait ParamsExt {
+    /// Returns the parameter with the given name as an [`f64`], or `None` if it wasn't given.
+    fn get_f64(&self, name: &str) -> Result<Option<f64>, ParamError>;
+
+    /// Returns the parameter with the given name as a [`bool`], or `None` if it wasn't given.
+    fn get_bool(&self, name: &str) -> Result<Option<bool>, ParamError>;
+
+    /// Returns the parameter with the given name as a string slice, or `None` if it wasn't given.
+    fn get_str(&self, name: &str) -> Result<Option<&str>, ParamError>;
+
+    /// Returns an error if any parameter other than the given ones was given.
+    fn expect_only(&self, names: &[&str]) -> Result<(), ParamError>;
+}
+
+impl ParamsExt for Params {
+    fn get_f64(&self, name: &str) -> Result<Option<f64>, ParamError> {
+        self.get(name)
+            .map(|param| {
+                param
+                    .as_f64()
+                    .ok_or_else(|| ParamError::new(name, "expected a number"))
+            })
+            .transpose()
+    }
+
+    fn get_bool(&self, name: &str) -> Result<Option<bool>, ParamError> {
+        self.get(name)
+            .map(|param| {
+                param
+                    .as_bool()
+                    .ok_or_else(|| ParamError::new(name, "expected a boolean"))
+            })
+            .transpose()
+    }
+
+    fn get_str(&self, name: &str) -> Result<Option<&str>, ParamError> {
+        self.get(name)
+            .map(|param| {
+                param
+                    .as_str()
+                    .ok_or_else(|| ParamError::new(name, "expected a string"))
+            })
+            .transpose()
+    }
+
+    fn expect_only(&self, names: &[&str]) -> Result<(), ParamError> {
+        match self.keys().find(|key| !names.contains(&key.as_str())) {
+            Some(key) => Err(ParamError::unexpected(key.as_str())),
+            None => Ok(()),
+        }
+    }
+}
+
+type Constructor =
+    Box<dyn Fn(&Params, &ProcessorRegistry) -> Result<Processor, ParamError> + Send + Sync>;
+
+/// A registry mapping processor type names to constructors, used to create processors from strings (e.g. when loading a [`Patch`](crate::patch::Patch)).
+///
+/// Processors are registered under their full type name (as returned by [`Process::name`] by default), and can be looked up either by that name or, if it is unambiguous, by the last segment of its path (e.g. `SineOscillator`).
+///
+/// The [`Default`] registry is pre-populated with all processors in [`builtins`](crate::builtins).
+pub struct ProcessorRegistry {
+    constructors: HashMap<String, Constructor>,
+    // short names (last path segment) to the full names they could refer to
+    short_names: HashMap<String, Vec<String>>,
+}
+
+impl Default for ProcessorRegistry {
+    fn default() -> Self {
+        Self::with_builtins()
+    }
+}
+
+impl std::fmt::Debug for ProcessorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_set().entries(self.constructors.keys()).finish()
+    }
+}
+
+impl ProcessorRegistry {
+    /// Creates a new, empty [`ProcessorRegistry`].
+    pub fn new() -> Self {
+        Self {
+            constructors: HashMap::new(),
+            short_names: HashMap::new(),
+        }
+    }
+
+    /// Creates a new [`ProcessorRegistry`] containing all processors in [`builtins`](crate::builtins).
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        crate::builtins::register_builtins(&mut registry);
+        registry
+    }
+
+    /// Registers a constructor for the [`Process`] type `P` under the given name, replacing any previous constructor with that name.
+    ///
+    /// The name should match what `P`'s [`Process::name`] returns, so that graphs containing it can be saved and loaded again.
+    pub fn register<P, F>(&mut self, name: impl Into<String>, constructor: F)
+    where
+        P: Process,
+        F: Fn(&Params) -> Result<P, ParamError> + Send + Sync + 'static,
+    {
+        self.register_nested(name, move |params: &Params, _: &ProcessorRegistry| {
+            constructor(params)
+        });
+    }
+
+    /// Like [`register`](ProcessorRegistry::register), but the constructor is also given the registry it is called from.
+    ///
+    /// This is meant for processors that contain other processors (such as [`SubGraph`](crate::graph::subgraph::SubGraph)), so that their contents can be created from the same registry.
+    pub fn register_nested<P, F>(&mut self, name: impl Into<String>, constructor: F)
+    where
+        P: Process,
+        F: Fn(&Params, &ProcessorRegistry) -> Result<P, ParamError> + Send + Sync + 'static,
+    {
+        let name = name.into();
+        let short_name = short_name(&name).to_owned();
+
+        let candidates = self.short_names.entry(short_name).or_default();
+        if !candidates.contains(&name) {
+            candidates.push(name.clone());
+        }
+
+        self.constructors.insert(
+            name,
+            Box::new(move |params, registry| constructor(params, registry).map(|p| p.processor())),
+        );
+    }
+
+    /// Registers the [`Process`] type `P` under its [`Process::name`], constructing it with [`Default::default`] and accepting no parameters.
+    pub fn register_default<P>(&mut self)
+    where
+        P: Process + Default,
+    {
+        let name = P::default().name().to_owned();
+        self.register(name, |params: &Params| {
+            params.expect_only(&[])?;
+            Ok(P::default())
+        });
+    }
+
+    /// Returns `true` if a processor with the given name (or unambiguous short name) is registered.
+    pub fn contains(&self, name: &str) -> bool {
+        self.resolve(name).is_ok()
+    }
+
+    /// Returns an iterator over the full names of all registered processors.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    /// Returns the full name of the registered processor the given name refers to.
+    pub fn resolve<'a>(&'a self, name: &'a str) -> RegistryResult<&'a str> {
+        if self.constructors.contains_key(name) {
+            return Ok(name);
+        }
+
+        match self.short_names.get(name).map(Vec::as_slice) {
+            Some([full_name]) => Ok(full_name),
+            Some(candidates) if !candidates.is_empty() => Err(RegistryError::AmbiguousName {
+                name: name.to_owned(),
+                candidates: candidates.to_vec(),
+            }),
+            _ => Err(RegistryError::UnknownProcessor(name.to_owned())),
+        }
+    }
+
+    /// Creates a new [`Processor`] of the registered type with the given name from the given parameters.
+    pub fn create(&self, name: &str, params: &Params) -> RegistryResult<Processor> {
+        let full_name = self.resolve(name)?;
+        let constructor = &self.constructors[full_name];
+
+        constructor(params, self).map_err(|err| RegistryError::InvalidParam {
+            processor: full_name.to_owned(),
+            param: err.param,
+            reason: err.reason,
+        })
+    }
+
+    /// Creates a new [`Processor`] of the registered type with the given name from a list of named parameters.
+    pub fn create_with<'p>(
+        &self,
+        name: &str,
+        params: impl IntoIterator<Item = (&'p str, Param)>,
+    ) -> RegistryResult<Processor> {
+        let params = params
+            .into_iter()
+            .map(|(name, param)| (name.to_owned(), param))
+            .collect();
+        self.create(name, &params)
+    }
+}
+
+fn short_name(name: &str) -> &str {
+    // don't split inside generic arguments, e.g. `Foo<bar::Baz>`
+    let path = name.split('<').next().unwrap_or(name);
+    match path.rfind("::") {
+        Some(index) => &name[index + 2..],
+        None => name,
+    }
+}
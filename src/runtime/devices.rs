@@ -0,0 +1,188 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+
+use super::{Backend, RuntimeResult};
+
+/// Sample rates that are checked against a device's supported ranges when enumerating devices.
+const STANDARD_SAMPLE_RATES: &[u32] = &[
+    8_000, 11_025, 16_000, 22_050, 32_000, 44_100, 48_000, 88_200, 96_000, 176_400, 192_000,
+];
+
+/// Whether a device is being used for capturing or rendering audio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceDirection {
+    Input,
+    Output,
+}
+
+/// The range of buffer sizes (in frames) a device supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BufferSizeRange {
+    pub min: u32,
+    pub max: u32,
+}
+
+/// Information about an audio device, as returned by [`input_devices`] and [`output_devices`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    /// The name of the device as reported by the backend.
+    pub name: String,
+    /// The index of the device in the backend's list of input or output devices, suitable for [`Device::Index`](super::Device::Index).
+    pub index: usize,
+    /// Whether this is the backend's default device for the direction it was enumerated for.
+    pub is_default: bool,
+    /// The maximum number of input channels the device supports, or `0` if it can't capture audio.
+    pub input_channels: u16,
+    /// The maximum number of output channels the device supports, or `0` if it can't render audio.
+    pub output_channels: u16,
+    /// The standard sample rates the device supports in the direction it was enumerated for.
+    pub sample_rates: Vec<u32>,
+    /// The range of buffer sizes the device supports, if the backend reports it.
+    pub buffer_size: Option<BufferSizeRange>,
+    /// The sample formats the device supports in the direction it was enumerated for.
+    pub sample_formats: Vec<cpal::SampleFormat>,
+}
+
+/// Returns the backends that are available on this system.
+pub fn available_backends() -> Vec<Backend> {
+    let mut backends = vec![Backend::Default];
+    for host in cpal::available_hosts() {
+        match host {
+            #[cfg(all(target_os = "linux", feature = "jack"))]
+            cpal::HostId::Jack => {
+                backends.push(Backend::Jack);
+            }
+            #[cfg(target_os = "linux")]
+            cpal::HostId::Alsa => {
+                backends.push(Backend::Alsa);
+            }
+            #[cfg(target_os = "windows")]
+            cpal::HostId::Wasapi => {
+                backends.push(Backend::Wasapi);
+            }
+            #[allow(unreachable_patterns)]
+            _ => {}
+        }
+    }
+
+    backends
+}
+
+/// Returns information about every input (capture) device of the given backend.
+pub fn input_devices(backend: Backend) -> RuntimeResult<Vec<DeviceInfo>> {
+    enumerate_devices(backend, DeviceDirection::Input)
+}
+
+/// Returns information about every output (playback) device of the given backend.
+pub fn output_devices(backend: Backend) -> RuntimeResult<Vec<DeviceInfo>> {
+    enumerate_devices(backend, DeviceDirection::Output)
+}
+
+/// Returns information about every device of the given backend in the given direction.
+pub fn enumerate_devices(
+    backend: Backend,
+    direction: DeviceDirection,
+) -> RuntimeResult<Vec<DeviceInfo>> {
+    let host = backend.host()?;
+
+    let (default_device, devices) = match direction {
+        DeviceDirection::Input => (
+            host.default_input_device(),
+            host.input_devices()?.collect::<Vec<_>>(),
+        ),
+        DeviceDirection::Output => (
+            host.default_output_device(),
+            host.output_devices()?.collect::<Vec<_>>(),
+        ),
+    };
+
+    // a default device whose name can't be read can't be matched to an entry in the list either
+    let default_name = default_device.and_then(|device| device.name().ok());
+
+    let mut infos = Vec::with_capacity(devices.len());
+    for (index, device) in devices.into_iter().enumerate() {
+        // keep devices without a name in the list, so that the indices still match the host's
+        let (name, is_default) = match device.name() {
+            Ok(name) => {
+                let is_default = default_name.as_deref() == Some(name.as_str());
+                (name, is_default)
+            }
+            Err(err) => {
+                log::warn!("Failed to get the name of device {index}: {err}");
+                (format!("<unknown device {index}>"), false)
+            }
+        };
+        infos.push(DeviceInfo::new(&device, name, index, is_default, direction));
+    }
+
+    Ok(infos)
+}
+
+impl DeviceInfo {
+    fn new(
+        device: &cpal::Device,
+        name: String,
+        index: usize,
+        is_default: bool,
+        direction: DeviceDirection,
+    ) -> Self {
+        // devices commonly fail to report configs for the direction they don't support (or while busy), which just means they have none
+        let input_configs: Vec<_> = device
+            .supported_input_configs()
+            .map(Iterator::collect)
+            .unwrap_or_default();
+        let output_configs: Vec<_> = device
+            .supported_output_configs()
+            .map(Iterator::collect)
+            .unwrap_or_default();
+
+        let max_channels = |configs: &[cpal::SupportedStreamConfigRange]| {
+            configs.iter().map(|c| c.channels()).max().unwrap_or(0)
+        };
+        let input_channels = max_channels(&input_configs);
+        let output_channels = max_channels(&output_configs);
+
+        let configs = match direction {
+            DeviceDirection::Input => input_configs,
+            DeviceDirection::Output => output_configs,
+        };
+
+        let sample_rates = STANDARD_SAMPLE_RATES
+            .iter()
+            .copied()
+            .filter(|&rate| {
+                configs
+                    .iter()
+                    .any(|c| c.min_sample_rate().0 <= rate && rate <= c.max_sample_rate().0)
+            })
+            .collect();
+
+        let buffer_size = configs
+            .iter()
+            .filter_map(|c| match *c.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => Some(BufferSizeRange { min, max }),
+                cpal::SupportedBufferSize::Unknown => None,
+            })
+            .reduce(|a, b| BufferSizeRange {
+                min: a.min.min(b.min),
+                max: a.max.max(b.max),
+            });
+
+        let mut sample_formats = Vec::new();
+        for config in configs.iter() {
+            if !sample_formats.contains(&config.sample_format()) {
+                sample_formats.push(config.sample_format());
+            }
+        }
+
+        Self {
+            name,
+            index,
+            is_default,
+            input_channels,
+            output_channels,
+            sample_rates,
+            buffer_size,
+            sample_formats,
+        }
+    }
+}
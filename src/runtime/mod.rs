@@ -0,0 +1,749 @@
+use std::sync::mpsc;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use recorder::{RecordProducer, RecorderCommand, Recording};
+
+use crate::{
+    graph::{
+        validate::{Diagnostic, Severity},
+        Graph,
+    },
+    signal::{Buffer, Sample},
+};
+
+pub mod devices;
+pub mod recorder;
+
+pub use devices::{BufferSizeRange, DeviceDirection, DeviceInfo};
+pub use recorder::{RecordingStats, StreamInfo};
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+#[error("Runtime error: {0}")]
+pub enum RuntimeError {
+    StreamError(#[from] cpal::StreamError),
+    DevicesError(#[from] cpal::DevicesError),
+    Hound(#[from] hound::Error),
+    HostUnavailable(#[from] cpal::HostUnavailable),
+    #[error("Requested device is unavailable: {0:?}")]
+    DeviceUnavailable(Device),
+    DeviceNameError(#[from] cpal::DeviceNameError),
+    DefaultStreamConfigError(#[from] cpal::DefaultStreamConfigError),
+    BuildStreamError(#[from] cpal::BuildStreamError),
+    PlayStreamError(#[from] cpal::PlayStreamError),
+    #[error("Unsupported sample format: {0}")]
+    UnsupportedSampleFormat(cpal::SampleFormat),
+    #[error("Runtime is not running")]
+    NotRunning,
+    #[error("Runtime is already recording")]
+    AlreadyRecording,
+    #[error("Runtime is not recording")]
+    NotRecording,
+    #[error("Recording thread panicked")]
+    RecorderPanicked,
+    #[error("Audio thread panicked")]
+    AudioThreadPanicked,
+    #[error("Unknown backend: {0}")]
+    UnknownBackend(String),
+    #[error("Graph is invalid: {}", format_diagnostics(.0))]
+    InvalidGraph(Vec<Diagnostic>),
+    #[error("Graph has {expected} inputs but {actual} input channels were given")]
+    InputChannelMismatch {
+        expected: usize,
+        actual: usize,
+    },
+    #[error("Invalid block size {0}: it must be greater than zero")]
+    InvalidBlockSize(usize),
+    #[error("Invalid sample rate {0}: it must be positive and finite")]
+    InvalidSampleRate(f64),
+    #[error("Graph has {expected} outputs but the device has {actual} channels")]
+    OutputChannelMismatch {
+        expected: usize,
+        actual: usize,
+    },
+}
+
+pub type RuntimeResult<T> = Result<T, RuntimeError>;
+
+/// The longest tail rendered after the end of an offline render, in seconds, for graphs whose [`tail_length`](Graph::tail_length) is longer or endless.
+pub const MAX_TAIL_SECONDS: f64 = 60.0;
+
+fn format_diagnostics(diagnostics: &[Diagnostic]) -> String {
+    diagnostics
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// The audio backend to use for the runtime.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    #[default]
+    Default,
+    #[cfg(all(target_os = "linux", feature = "jack"))]
+    Jack,
+    #[cfg(target_os = "linux")]
+    Alsa,
+    #[cfg(target_os = "windows")]
+    Wasapi,
+}
+
+impl Backend {
+    /// Returns the [`cpal::Host`] for this backend, or an error if it isn't available on this system.
+    pub fn host(self) -> RuntimeResult<cpal::Host> {
+        let host_id = match self {
+            Backend::Default => return Ok(cpal::default_host()),
+            #[cfg(all(target_os = "linux", feature = "jack"))]
+            Backend::Jack => cpal::HostId::Jack,
+            #[cfg(target_os = "linux")]
+            Backend::Alsa => cpal::HostId::Alsa,
+            #[cfg(target_os = "windows")]
+            Backend::Wasapi => cpal::HostId::Wasapi,
+        };
+
+        #[allow(unreachable_code)]
+        if !cpal::available_hosts().contains(&host_id) {
+            return Err(RuntimeError::HostUnavailable(cpal::HostUnavailable));
+        }
+
+        Ok(cpal::host_from_id(host_id)?)
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = RuntimeError;
+
+    /// Parses a backend from its lowercase name, e.g. `"default"` or `"alsa"`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "default" => Ok(Backend::Default),
+            #[cfg(all(target_os = "linux", feature = "jack"))]
+            "jack" => Ok(Backend::Jack),
+            #[cfg(target_os = "linux")]
+            "alsa" => Ok(Backend::Alsa),
+            #[cfg(target_os = "windows")]
+            "wasapi" => Ok(Backend::Wasapi),
+            _ => Err(RuntimeError::UnknownBackend(s.to_owned())),
+        }
+    }
+}
+
+/// The audio device to use for the runtime.
+#[derive(Default, Debug, Clone)]
+pub enum Device {
+    /// Use the default audio device as returned by [`cpal::Host::default_output_device`].
+    #[default]
+    Default,
+    /// Use the audio device at the given index.
+    Index(usize),
+    /// Substring of the device name to match. The first device with a name containing this substring will be used.
+    Name(String),
+}
+
+impl std::str::FromStr for Device {
+    type Err = std::convert::Infallible;
+
+    /// Parses `"default"` as [`Device::Default`], a number as [`Device::Index`], and anything else as [`Device::Name`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("default") {
+            Ok(Device::Default)
+        } else if let Ok(index) = s.parse() {
+            Ok(Device::Index(index))
+        } else {
+            Ok(Device::Name(s.to_owned()))
+        }
+    }
+}
+
+/// The audio graph processing runtime.
+///
+/// The runtime is responsible for running the audio graph and rendering audio samples.
+/// It can run in real-time ([`run`](Runtime::run)) or offline ([`run_offline`](Runtime::run_offline)) mode.
+///
+/// In real-time mode, the runtime will render audio samples in real-time using a specified audio backend and device.
+///
+/// In offline mode, the runtime will render audio samples as fast as possible and return the rendered output channels.
+#[derive(Default)]
+pub struct Runtime {
+    graph: Graph,
+}
+
+impl Runtime {
+    /// Creates a new runtime with the given audio graph.
+    pub fn new(graph: Graph) -> Self {
+        Runtime { graph }
+    }
+
+    /// Resets the runtime with the given sample rate and block size.
+    /// This will reset the state of all nodes in the graph (see [`Process::reset`](crate::processor::Process::reset)) and potentially reallocate internal buffers.
+    pub fn reset(&mut self, sample_rate: f64, block_size: usize) {
+        self.graph.reset(sample_rate, block_size);
+        self.graph.reset_state();
+    }
+
+    /// Runs the preparation phase for every node in the graph.
+    pub fn prepare(&mut self) {
+        self.graph.prepare_nodes();
+    }
+
+    /// Checks the graph with [`Graph::validate`], logging any warnings.
+    ///
+    /// Returns [`RuntimeError::InvalidGraph`] with all error diagnostics if the graph can't run correctly.
+    /// This is called automatically before rendering or running the graph.
+    pub fn validate(&self) -> RuntimeResult<()> {
+        let (errors, others): (Vec<_>, Vec<_>) = self
+            .graph
+            .validate()
+            .into_iter()
+            .partition(Diagnostic::is_error);
+
+        for diagnostic in others {
+            match diagnostic.severity() {
+                Severity::Warning => log::warn!("{diagnostic}"),
+                _ => log::debug!("{diagnostic}"),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(RuntimeError::InvalidGraph(errors))
+        }
+    }
+
+    /// Returns a reference to the audio graph.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Returns the number of samples by which the graph delays its inputs at its outputs, as given by [`Graph::latency`].
+    pub fn latency(&self) -> usize {
+        self.graph.latency()
+    }
+
+    /// Returns a mutable reference to the audio graph.
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    /// Returns an iterator over the output channels of the runtime.
+    pub fn outputs(&mut self) -> impl Iterator<Item = &Buffer> + '_ {
+        let num_outputs = self.graph.num_outputs();
+        (0..num_outputs).map(|i| self.graph.get_output(i))
+    }
+
+    /// Renders the next block of audio and returns the rendered output channels.
+    #[inline]
+    pub fn next_buffer(&mut self) -> impl Iterator<Item = &Buffer> + '_ {
+        self.graph.process();
+
+        self.graph.outputs()
+    }
+
+    /// Runs the audio graph repeatedly for the given duration's worth of samples, and returns the rendered output channels.
+    ///
+    /// Rendering continues past the given duration for the graph's [`tail_length`](Graph::tail_length), so that reverbs and delays can decay.
+    /// Tails longer than [`MAX_TAIL_SECONDS`] are cut off.
+    pub fn run_offline(
+        &mut self,
+        duration: std::time::Duration,
+        sample_rate: f64,
+        block_size: usize,
+    ) -> RuntimeResult<Box<[Box<[Sample]>]>> {
+        let secs = duration.as_secs_f64();
+        let samples = (sample_rate * secs) as usize;
+
+        self.render_offline(samples, &[], sample_rate, block_size)
+    }
+
+    /// Runs the audio graph over the given input channels (one per graph input), and returns the rendered output channels.
+    ///
+    /// The output is as long as the longest input channel plus the graph's [`tail_length`](Graph::tail_length) (at most [`MAX_TAIL_SECONDS`]);
+    /// shorter input channels are padded with silence.
+    pub fn run_offline_with_inputs(
+        &mut self,
+        inputs: &[Box<[Sample]>],
+        sample_rate: f64,
+        block_size: usize,
+    ) -> RuntimeResult<Box<[Box<[Sample]>]>> {
+        if inputs.len() != self.graph.num_inputs() {
+            return Err(RuntimeError::InputChannelMismatch {
+                expected: self.graph.num_inputs(),
+                actual: inputs.len(),
+            });
+        }
+
+        let samples = inputs.iter().map(|input| input.len()).max().unwrap_or(0);
+
+        self.render_offline(samples, inputs, sample_rate, block_size)
+    }
+
+    pub(crate) fn render_offline(
+        &mut self,
+        samples: usize,
+        inputs: &[Box<[Sample]>],
+        sample_rate: f64,
+        block_size: usize,
+    ) -> RuntimeResult<Box<[Box<[Sample]>]>> {
+        if block_size == 0 {
+            return Err(RuntimeError::InvalidBlockSize(block_size));
+        }
+        if !(sample_rate.is_finite() && sample_rate > 0.0) {
+            return Err(RuntimeError::InvalidSampleRate(sample_rate));
+        }
+        self.validate()?;
+
+        self.reset(sample_rate, block_size);
+        self.prepare();
+
+        // the tail may depend on the sample rate, so it's only known after the reset
+        let max_tail = (MAX_TAIL_SECONDS * sample_rate) as usize;
+        let samples = samples.saturating_add(self.graph.tail_length().min(max_tail));
+
+        let num_outputs: usize = self.graph.num_outputs();
+
+        let mut outputs: Box<[Box<[Sample]>]> =
+            vec![vec![Sample::new(0.0); samples].into_boxed_slice(); num_outputs]
+                .into_boxed_slice();
+
+        let mut input_block = vec![Sample::new(0.0); block_size];
+
+        let mut sample_count = 0;
+
+        while sample_count < samples {
+            let actual_block_size = (samples - sample_count).min(block_size);
+            self.graph.resize_buffers(sample_rate, actual_block_size);
+
+            for (i, input) in inputs.iter().enumerate() {
+                let block = &mut input_block[..actual_block_size];
+                let available = input
+                    .len()
+                    .saturating_sub(sample_count)
+                    .min(actual_block_size);
+                // inputs may end before the rendering does, e.g. while rendering the graph's tail
+                let start = sample_count.min(input.len());
+                block[..available].copy_from_slice(&input[start..start + available]);
+                block[available..].fill(Sample::new(0.0));
+                self.graph.copy_input(i, block);
+            }
+
+            self.graph.process();
+
+            for (i, output) in outputs.iter_mut().enumerate() {
+                let buffer = self.graph.get_output(i);
+                output[sample_count..sample_count + actual_block_size].copy_from_slice(buffer);
+            }
+
+            sample_count += actual_block_size;
+        }
+
+        Ok(outputs)
+    }
+
+    pub fn run_offline_to_file(
+        &mut self,
+        file_path: impl AsRef<std::path::Path>,
+        duration: std::time::Duration,
+        sample_rate: f64,
+        block_size: usize,
+    ) -> RuntimeResult<()> {
+        let outputs = self.run_offline(duration, sample_rate, block_size)?;
+
+        write_wav(file_path, &outputs, sample_rate)
+    }
+
+    /// Runs the audio graph over the channels of the input WAV file (one per graph input), and writes the rendered output channels to the output WAV file.
+    ///
+    /// The graph runs at the sample rate of the input file.
+    pub fn process_file(
+        &mut self,
+        input_path: impl AsRef<std::path::Path>,
+        output_path: impl AsRef<std::path::Path>,
+        block_size: usize,
+    ) -> RuntimeResult<()> {
+        let (inputs, sample_rate) = read_wav(input_path)?;
+
+        let outputs = self.run_offline_with_inputs(&inputs, sample_rate, block_size)?;
+
+        write_wav(output_path, &outputs, sample_rate)
+    }
+
+    pub fn run_for(
+        &mut self,
+        duration: std::time::Duration,
+        backend: Backend,
+        device: Device,
+    ) -> RuntimeResult<()> {
+        let runtime = std::mem::take(self);
+        let mut handle = runtime.run(backend, device)?;
+        std::thread::sleep(duration);
+        *self = handle.stop()?;
+        Ok(())
+    }
+
+    pub fn run(mut self, backend: Backend, device: Device) -> RuntimeResult<RuntimeHandle> {
+        self.validate()?;
+
+        let (kill_tx, kill_rx) = mpsc::channel();
+        let (runtime_tx, runtime_rx) = mpsc::channel();
+        let (record_tx, record_rx) = mpsc::channel();
+        let (info_tx, info_rx) = mpsc::channel();
+
+        let host = backend.host()?;
+
+        log::info!("Using host: {:?}", host.id());
+
+        let cpal_device = match &device {
+            Device::Default => host.default_output_device(),
+            Device::Index(index) => host.output_devices()?.nth(*index),
+            Device::Name(name) => host
+                .output_devices()?
+                .find(|d| d.name().is_ok_and(|n| n.contains(name))),
+        };
+
+        let device = cpal_device.ok_or(RuntimeError::DeviceUnavailable(device))?;
+
+        log::info!("Using device: {}", device.name()?);
+
+        let config = device.default_output_config()?;
+
+        // checked here rather than on the audio thread, where the caller would never hear about it
+        let channels = config.channels();
+        if self.graph.num_outputs() != channels as usize {
+            return Err(RuntimeError::OutputChannelMismatch {
+                expected: self.graph.num_outputs(),
+                actual: channels as usize,
+            });
+        }
+
+        log::info!("Configuration: {:#?}", config);
+
+        let thread = std::thread::spawn(move || -> RuntimeResult<()> {
+            // the handle may have been dropped already, in which case nobody is interested in recording
+            info_tx
+                .send(StreamInfo {
+                    sample_rate: config.sample_rate().0,
+                    channels,
+                })
+                .ok();
+
+            let audio_rate = config.sample_rate().0 as f64;
+            let initial_block_size = audio_rate as usize / 100;
+
+            self.graph.reset(audio_rate, initial_block_size);
+
+            self.prepare();
+
+            match config.sample_format() {
+                cpal::SampleFormat::I8 => {
+                    self.run_inner::<i8>(&device, &config.config(), kill_rx, runtime_tx, record_rx)?
+                }
+                cpal::SampleFormat::I16 => self.run_inner::<i16>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::I32 => self.run_inner::<i32>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::I64 => self.run_inner::<i64>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::U8 => {
+                    self.run_inner::<u8>(&device, &config.config(), kill_rx, runtime_tx, record_rx)?
+                }
+                cpal::SampleFormat::U16 => self.run_inner::<u16>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::U32 => self.run_inner::<u32>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::U64 => self.run_inner::<u64>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::F32 => self.run_inner::<f32>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+                cpal::SampleFormat::F64 => self.run_inner::<f64>(
+                    &device,
+                    &config.config(),
+                    kill_rx,
+                    runtime_tx,
+                    record_rx,
+                )?,
+
+                sample_format => {
+                    return Err(RuntimeError::UnsupportedSampleFormat(sample_format));
+                }
+            }
+
+            Ok(())
+        });
+
+        Ok(RuntimeHandle {
+            kill_tx,
+            runtime_rx,
+            record_tx,
+            info_rx,
+            info: None,
+            recording: None,
+            thread: Some(thread),
+        })
+    }
+
+    fn run_inner<T>(
+        self,
+        device: &cpal::Device,
+        config: &cpal::StreamConfig,
+        kill_rx: mpsc::Receiver<()>,
+        runtime_tx: mpsc::Sender<Runtime>,
+        record_rx: mpsc::Receiver<RecorderCommand>,
+    ) -> RuntimeResult<()>
+    where
+        T: cpal::SizedSample + cpal::FromSample<f64>,
+    {
+        let channels = config.channels as usize;
+        let audio_rate = config.sample_rate.0 as f64;
+
+        let mut graph = self.graph.clone();
+        let mut recorder: Option<RecordProducer> = None;
+
+        let stream = device.build_output_stream(
+            config,
+            move |data: &mut [T], _info: &cpal::OutputCallbackInfo| {
+                graph.resize_buffers(audio_rate, data.len() / channels);
+                graph.process();
+                for (frame_idx, frame) in data.chunks_mut(channels).enumerate() {
+                    for (channel_idx, sample) in frame.iter_mut().enumerate() {
+                        let buffer = graph.get_output(channel_idx);
+                        let value = buffer[frame_idx];
+                        *sample = T::from_sample(*value);
+                    }
+                }
+
+                while let Ok(command) = record_rx.try_recv() {
+                    match command {
+                        RecorderCommand::Start(producer) => recorder = Some(producer),
+                        RecorderCommand::Stop => recorder = None,
+                    }
+                }
+
+                if let Some(recorder) = &recorder {
+                    recorder.push_block(data.len(), |i| {
+                        *graph.get_output(i % channels)[i / channels] as f32
+                    });
+                }
+            },
+            |err| eprintln!("an error occurred on output: {}", err),
+            None,
+        )?;
+
+        stream.play()?;
+
+        loop {
+            if kill_rx.try_recv().is_ok() {
+                drop(stream);
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        // the handle may have been dropped already, in which case nobody is waiting for the runtime
+        runtime_tx.send(self).ok();
+
+        Ok(())
+    }
+}
+
+/// A handle to a [`Runtime`] running on the audio thread, returned by [`Runtime::run`].
+pub struct RuntimeHandle {
+    kill_tx: mpsc::Sender<()>,
+    runtime_rx: mpsc::Receiver<Runtime>,
+    record_tx: mpsc::Sender<RecorderCommand>,
+    info_rx: mpsc::Receiver<StreamInfo>,
+    info: Option<StreamInfo>,
+    recording: Option<Recording>,
+    thread: Option<std::thread::JoinHandle<RuntimeResult<()>>>,
+}
+
+impl RuntimeHandle {
+    /// Stops the runtime and returns it, finishing any recording in progress.
+    ///
+    /// If the audio thread has already exited (e.g. because the output stream couldn't be opened), the runtime is lost,
+    /// and the error the thread exited with is returned instead.
+    pub fn stop(&mut self) -> RuntimeResult<Runtime> {
+        if self.recording.is_some() {
+            match self.stop_recording() {
+                Ok(stats) => log::info!("Recording finished: {:?}", stats),
+                Err(err) => log::error!("Failed to finish recording: {}", err),
+            }
+        }
+
+        // fails if the audio thread has exited already, in which case it also dropped the runtime
+        self.kill_tx.send(()).ok();
+        let runtime = self.runtime_rx.recv();
+
+        match (runtime, self.thread.take().map(|thread| thread.join())) {
+            (Ok(runtime), _) => Ok(runtime),
+            (Err(_), Some(Ok(Err(err)))) => Err(err),
+            (Err(_), Some(Err(_))) => Err(RuntimeError::AudioThreadPanicked),
+            (Err(_), _) => Err(RuntimeError::NotRunning),
+        }
+    }
+
+    /// Returns information about the output stream, waiting for the audio thread to open it if necessary.
+    pub fn stream_info(&mut self) -> RuntimeResult<StreamInfo> {
+        if let Some(info) = self.info {
+            return Ok(info);
+        }
+        let info = self.info_rx.recv().map_err(|_| RuntimeError::NotRunning)?;
+        self.info = Some(info);
+        Ok(info)
+    }
+
+    /// Starts recording everything sent to the output device to a 32-bit float WAV file at the given path.
+    ///
+    /// Blocks are handed from the audio callback to a disk-writer thread through a preallocated ring buffer.
+    /// If the disk can't keep up, whole blocks are dropped instead of stalling the audio thread; see [`RecordingStats::dropped_blocks`].
+    pub fn start_recording(&mut self, file_path: impl AsRef<std::path::Path>) -> RuntimeResult<()> {
+        if self.recording.is_some() {
+            return Err(RuntimeError::AlreadyRecording);
+        }
+
+        let info = self.stream_info()?;
+        let (recording, producer) = Recording::start(file_path, info)?;
+
+        // if the audio thread is gone, the producer is dropped here, which lets the writer finish immediately
+        let sent = self
+            .record_tx
+            .send(RecorderCommand::Start(producer))
+            .is_ok();
+
+        if !sent {
+            recording.finish()?;
+            return Err(RuntimeError::NotRunning);
+        }
+
+        self.recording = Some(recording);
+
+        Ok(())
+    }
+
+    /// Returns `true` if a recording is in progress.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Returns the number of blocks dropped by the recording in progress, if any.
+    pub fn dropped_blocks(&self) -> Option<u64> {
+        self.recording.as_ref().map(Recording::dropped_blocks)
+    }
+
+    /// Stops the recording in progress, waits for the remaining audio to be written and finalizes the file.
+    pub fn stop_recording(&mut self) -> RuntimeResult<RecordingStats> {
+        let recording = self.recording.take().ok_or(RuntimeError::NotRecording)?;
+
+        // if the audio thread is gone, the producer has already been dropped along with the channel
+        self.record_tx.send(RecorderCommand::Stop).ok();
+
+        recording.finish()
+    }
+}
+
+/// A set of rendered or loaded audio channels, each as long as the others.
+pub(crate) type Channels = Box<[Box<[Sample]>]>;
+
+/// Reads all channels of the WAV file at the given path, returning them along with the file's sample rate.
+pub(crate) fn read_wav(file_path: impl AsRef<std::path::Path>) -> RuntimeResult<(Channels, f64)> {
+    let mut reader = hound::WavReader::open(file_path)?;
+    let spec = reader.spec();
+    let num_channels = spec.channels as usize;
+
+    let interleaved: Vec<f64> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .map(|sample| sample.map(f64::from))
+            .collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = (1u64 << (spec.bits_per_sample - 1)) as f64;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f64 / scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+
+    let mut channels = vec![Vec::with_capacity(interleaved.len() / num_channels); num_channels];
+    for frame in interleaved.chunks(num_channels) {
+        for (channel, &sample) in channels.iter_mut().zip(frame) {
+            channel.push(Sample::new(sample));
+        }
+    }
+
+    let channels = channels.into_iter().map(Vec::into_boxed_slice).collect();
+
+    Ok((channels, spec.sample_rate as f64))
+}
+
+/// Writes the given channels to a 32-bit float WAV file at the given path.
+pub(crate) fn write_wav(
+    file_path: impl AsRef<std::path::Path>,
+    outputs: &[Box<[Sample]>],
+    sample_rate: f64,
+) -> RuntimeResult<()> {
+    let num_channels = outputs.len();
+
+    let num_samples = outputs.first().map_or(0, |output| output.len());
+
+    let mut samples = vec![0.0; num_samples * num_channels];
+
+    for (channel_index, output) in outputs.iter().enumerate() {
+        for (sample_index, sample) in output.iter().enumerate() {
+            samples[sample_index * num_channels + channel_index] = **sample;
+        }
+    }
+
+    let spec = hound::WavSpec {
+        channels: num_channels as u16,
+        sample_rate: sample_rate as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+
+    let mut writer = hound::WavWriter::create(file_path, spec)?;
+
+    for sample in samples {
+        writer.write_sample(sample as f32)?;
+    }
+
+    writer.finalize()?;
+
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use std::{
+    cell::UnsafeCell,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use super::{RuntimeError, RuntimeResult};
+
+/// How many seconds of audio the recording ring buffer can hold before blocks start being dropped.
+const RING_BUFFER_SECONDS: usize = 2;
+
+/// How long the disk-writer thread sleeps between polls of the ring buffer.
+const WRITER_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_millis(5);
+
+/// How long [`Recording::finish`] waits for the audio callback to release the recording before closing it anyway,
+/// e.g. because the device was lost or the stream paused and callbacks have stopped.
+const FINISH_TIMEOUT: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Information about the output stream a [`Runtime`](super::Runtime) is currently rendering to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Summary of a finished recording, returned by [`RuntimeHandle::stop_recording`](super::RuntimeHandle::stop_recording).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecordingStats {
+    /// The number of frames written to the file.
+    pub frames_written: u64,
+    /// The number of blocks that were dropped because the disk writer couldn't keep up with the audio thread.
+    pub dropped_blocks: u64,
+}
+
+/// A single-producer, single-consumer ring buffer of interleaved samples.
+///
+/// The audio thread pushes whole blocks at a time; if there isn't enough room for a block, the entire block is dropped and counted rather than blocking the audio thread.
+pub(crate) struct RecordRing {
+    buf: Box<[UnsafeCell<f32>]>,
+    // total number of samples ever written (only modified by the producer)
+    head: AtomicUsize,
+    // total number of samples ever read (only modified by the consumer)
+    tail: AtomicUsize,
+    closed: AtomicBool,
+    dropped_blocks: AtomicU64,
+}
+
+// SAFETY: the producer only writes to the region between `head` and `tail + capacity`, and the consumer only reads from the region between `tail` and `head`.
+// The regions are published to each other through the acquire/release pairs on `head` and `tail`, so they never alias.
+unsafe impl Sync for RecordRing {}
+
+impl RecordRing {
+    fn new(capacity: usize) -> Self {
+        Self {
+            buf: (0..capacity).map(|_| UnsafeCell::new(0.0)).collect(),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+            closed: AtomicBool::new(false),
+            dropped_blocks: AtomicU64::new(0),
+        }
+    }
+
+    #[inline]
+    fn capacity(&self) -> usize {
+        self.buf.len()
+    }
+
+    /// Pushes a block of `len` samples, calling `sample` for each interleaved sample index.
+    /// Must only be called from the producer side.
+    #[inline]
+    fn push_block(&self, len: usize, mut sample: impl FnMut(usize) -> f32) {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Acquire);
+        let free = self.capacity() - head.wrapping_sub(tail);
+
+        if len > free {
+            self.dropped_blocks.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+
+        for i in 0..len {
+            let slot = &self.buf[head.wrapping_add(i) % self.capacity()];
+            // SAFETY: see the `Sync` impl above; this slot is not readable by the consumer until `head` is published
+            unsafe { *slot.get() = sample(i) };
+        }
+
+        self.head.store(head.wrapping_add(len), Ordering::Release);
+    }
+
+    /// Moves all currently available samples into `out`, returning how many were read.
+    /// Must only be called from the consumer side.
+    fn pop_into(&self, out: &mut Vec<f32>) -> usize {
+        let tail = self.tail.load(Ordering::Relaxed);
+        let head = self.head.load(Ordering::Acquire);
+        let available = head.wrapping_sub(tail);
+
+        for i in 0..available {
+            let slot = &self.buf[tail.wrapping_add(i) % self.capacity()];
+            // SAFETY: see the `Sync` impl above; this slot is not writable by the producer until `tail` is published
+            out.push(unsafe { *slot.get() });
+        }
+
+        self.tail
+            .store(tail.wrapping_add(available), Ordering::Release);
+        available
+    }
+}
+
+/// The audio thread's end of a recording.
+///
+/// Dropping it marks the recording as closed, which lets the disk writer drain the remaining samples and finalize the file.
+pub(crate) struct RecordProducer {
+    ring: Arc<RecordRing>,
+}
+
+impl RecordProducer {
+    /// Pushes a block of `len` interleaved samples into the recording.
+    #[inline]
+    pub(crate) fn push_block(&self, len: usize, sample: impl FnMut(usize) -> f32) {
+        self.ring.push_block(len, sample);
+    }
+}
+
+impl Drop for RecordProducer {
+    fn drop(&mut self) {
+        self.ring.closed.store(true, Ordering::Release);
+    }
+}
+
+/// Messages sent from a [`RuntimeHandle`](super::RuntimeHandle) to the audio callback.
+pub(crate) enum RecorderCommand {
+    Start(RecordProducer),
+    Stop,
+}
+
+/// The control side of an in-progress recording, owned by the [`RuntimeHandle`](super::RuntimeHandle).
+pub(crate) struct Recording {
+    ring: Arc<RecordRing>,
+    writer: JoinHandle<RuntimeResult<u64>>,
+}
+
+impl Recording {
+    /// Creates the WAV file and spawns the disk-writer thread, returning the control side of the recording and the producer to hand to the audio callback.
+    pub(crate) fn start(
+        file_path: impl AsRef<std::path::Path>,
+        info: StreamInfo,
+    ) -> RuntimeResult<(Self, RecordProducer)> {
+        let spec = hound::WavSpec {
+            channels: info.channels,
+            sample_rate: info.sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+
+        let mut writer = hound::WavWriter::create(file_path, spec)?;
+
+        let capacity = info.sample_rate as usize * info.channels as usize * RING_BUFFER_SECONDS;
+        let ring = Arc::new(RecordRing::new(capacity));
+        let channels = info.channels as u64;
+
+        let writer_ring = ring.clone();
+        let writer = std::thread::spawn(move || -> RuntimeResult<u64> {
+            let mut scratch = Vec::with_capacity(writer_ring.capacity());
+            let mut samples_written = 0u64;
+            let mut last_dropped = 0;
+
+            loop {
+                // check before draining so that no samples pushed before closing are missed
+                let closed = writer_ring.closed.load(Ordering::Acquire);
+
+                scratch.clear();
+                writer_ring.pop_into(&mut scratch);
+                for &sample in scratch.iter() {
+                    writer.write_sample(sample)?;
+                }
+                samples_written += scratch.len() as u64;
+
+                let dropped = writer_ring.dropped_blocks.load(Ordering::Relaxed);
+                if dropped != last_dropped {
+                    log::warn!(
+                        "Recording dropped {} block(s); the disk can't keep up",
+                        dropped - last_dropped
+                    );
+                    last_dropped = dropped;
+                }
+
+                if closed {
+                    break;
+                }
+
+                std::thread::sleep(WRITER_POLL_INTERVAL);
+            }
+
+            writer.finalize()?;
+
+            Ok(samples_written / channels)
+        });
+
+        let producer = RecordProducer { ring: ring.clone() };
+
+        Ok((Self { ring, writer }, producer))
+    }
+
+    /// Returns the number of blocks dropped so far.
+    pub(crate) fn dropped_blocks(&self) -> u64 {
+        self.ring.dropped_blocks.load(Ordering::Relaxed)
+    }
+
+    /// Waits for the disk writer to drain the ring buffer and finalize the file.
+    ///
+    /// The audio callback should drop the producer soon after being told to stop. If it hasn't within [`FINISH_TIMEOUT`],
+    /// the recording is closed from this side instead, and anything the callback pushes afterwards is discarded.
+    pub(crate) fn finish(self) -> RuntimeResult<RecordingStats> {
+        let Self { ring, writer } = self;
+
+        let deadline = std::time::Instant::now() + FINISH_TIMEOUT;
+        while !ring.closed.load(Ordering::Acquire) && std::time::Instant::now() < deadline {
+            std::thread::sleep(WRITER_POLL_INTERVAL);
+        }
+        ring.closed.store(true, Ordering::Release);
+
+        let frames_written = writer
+            .join()
+            .map_err(|_| RuntimeError::RecorderPanicked)??;
+
+        Ok(RecordingStats {
+            frames_written,
+            // the recording is closed by the time the writer finishes, so nothing more is written
+            dropped_blocks: ring.dropped_blocks.load(Ordering::Relaxed),
+        })
+    }
+}
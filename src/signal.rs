@@ -229,6 +229,20 @@ impl Display for Sample {
     }
 }
 
+/// Where the samples of a [`Buffer`] lie within an audio block, for [`Buffer::copy_resampled`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Timing {
+    /// The number of audio samples between consecutive samples of the buffer.
+    pub step: usize,
+    /// The number of audio samples from the start of the block to the first sample of the buffer.
+    pub phase: usize,
+}
+
+impl Timing {
+    /// The timing of an audio-rate buffer.
+    pub const AUDIO: Self = Self { step: 1, phase: 0 };
+}
+
 /// An owning, fixed-length array of [`Sample`]s.
 /// This type implements [`Deref`] and [`DerefMut`], so it can be indexed and iterated over just like a normal slice.
 /// It can also be [`collected`](std::iter::Iterator::collect) from an iterator of [`Sample`]s.
@@ -282,6 +296,63 @@ impl Buffer {
         }
     }
 
+    /// Copies `source` into this buffer, resampling it if the buffers run at different rates, e.g. between reduced-rate and audio-rate buffers.
+    ///
+    /// `timing` and `source_timing` tell where the samples of each buffer lie within the audio block. `history` holds the two samples the
+    /// source produced before this block, oldest first, which are used for the stretch of this buffer before the source's first sample.
+    ///
+    /// A sparser source is upsampled: if `interpolate` is `true`, it's interpolated linearly so that each source sample is reached at the end
+    /// of the stretch of audio samples it covers, and otherwise each source sample is held. A denser source is decimated, taking the latest
+    /// source sample at or before each sample of this buffer.
+    #[inline]
+    pub fn copy_resampled(
+        &mut self,
+        timing: Timing,
+        source: &[Sample],
+        source_timing: Timing,
+        history: [Sample; 2],
+        interpolate: bool,
+    ) {
+        if timing == source_timing && self.buf.len() == source.len() {
+            self.buf.copy_from_slice(source);
+            return;
+        }
+
+        let step = source_timing.step.max(1);
+        // the source sample with the given index relative to this block, clamped to the samples it has
+        let source_sample = |k: isize| -> Sample {
+            match usize::try_from(k) {
+                Ok(k) => source
+                    .get(k)
+                    .or(source.last())
+                    .copied()
+                    .unwrap_or(history[1]),
+                Err(_) if k == -1 => history[1],
+                Err(_) => history[0],
+            }
+        };
+        let interpolate = interpolate && step > timing.step;
+        for (j, sample) in self.buf.iter_mut().enumerate() {
+            let position = timing.phase + j * timing.step;
+            // the latest source sample at or before this sample, and how far into its stretch this sample lies
+            let (k, offset) = if position >= source_timing.phase {
+                let distance = position - source_timing.phase;
+                ((distance / step) as isize, distance % step)
+            } else {
+                let distance = source_timing.phase - position;
+                let k = distance.div_ceil(step);
+                (-(k as isize), k * step - distance)
+            };
+            *sample = if interpolate {
+                let (start, end) = (source_sample(k - 1), source_sample(k));
+                let t = (offset + 1) as f64 / step as f64;
+                Sample::new(start.value() + (end.value() - start.value()) * t)
+            } else {
+                source_sample(k)
+            };
+        }
+    }
+
     #[inline]
     pub fn from_slice(value: &[Sample]) -> Self {
         Buffer {
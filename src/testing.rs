@@ -0,0 +1,463 @@
+//! Utilities for regression testing processors and graphs: deterministic offline rendering, and comparison against reference ("golden") WAV files.
+//!
+//! [`render`] runs a [`Graph`] offline with a fixed [`RenderConfig`], feeding each graph input a deterministic [`test_signal`].
+//! [`assert_golden`] compares rendered outputs to a stored WAV file, reporting the maximum error and the signal-to-noise ratio,
+//! and [`assert_golden_render`] does both, also checking that the output doesn't depend on the block size.
+//!
+//! [`assert_block_size_invariant`] checks that a graph's output is identical at several block sizes, reporting each [`Divergence`].
+//! This catches processors that e.g. keep per-block state, or assume the block size passed to [`Process::resize_buffers`] stays the same,
+//! since offline renders end with a shorter block. [`processor_graph`] wraps a single processor in a graph for such checks.
+//!
+//! Golden files are written instead of compared when the [`BLESS_ENV_VAR`] environment variable is set, e.g. `DAPRS_BLESS=1 cargo test`.
+//! They are stored as 32-bit float WAV files, so outputs are rounded to 32-bit floats before being compared to them.
+
+use std::path::Path;
+
+use crate::{
+    graph::Graph,
+    processor::{Process, Processor},
+    runtime::{read_wav, write_wav, Channels, Runtime, RuntimeError},
+    signal::{Buffer, Sample},
+};
+
+/// Setting this environment variable (to anything) makes [`assert_golden`] write golden files instead of comparing against them.
+pub const BLESS_ENV_VAR: &str = "DAPRS_BLESS";
+
+/// A selection of block sizes for [`assert_block_size_invariant`], including 1, odd sizes, and sizes that don't divide the rendered length.
+pub const BLOCK_SIZES: &[usize] = &[1, 2, 3, 7, 16, 17, 64, 127, 256, 1000];
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum TestingError {
+    #[error("Runtime error: {0}")]
+    Runtime(#[from] RuntimeError),
+    #[error("Expected {expected} channels, but got {actual}")]
+    ChannelMismatch { expected: usize, actual: usize },
+    #[error(
+        "Expected channel {channel} to be {expected} samples long, but it is {actual} samples long"
+    )]
+    LengthMismatch {
+        channel: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+pub type TestingResult<T> = Result<T, TestingError>;
+
+/// The settings for a deterministic offline render.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderConfig {
+    pub sample_rate: f64,
+    pub block_size: usize,
+    /// The number of samples to render, not counting the graph's [`tail_length`](Graph::tail_length).
+    pub num_samples: usize,
+    /// The seed of the [`test_signal`]s fed to the graph inputs; input `i` gets the signal for `seed + i`.
+    pub seed: u64,
+}
+
+impl Default for RenderConfig {
+    /// 0.1 seconds at 48 kHz, in blocks of 64 samples, with seed 1.
+    fn default() -> Self {
+        Self {
+            sample_rate: 48_000.0,
+            block_size: 64,
+            num_samples: 4_800,
+            seed: 1,
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Returns this [`RenderConfig`] with the given sample rate.
+    pub fn with_sample_rate(mut self, sample_rate: f64) -> Self {
+        self.sample_rate = sample_rate;
+        self
+    }
+
+    /// Returns this [`RenderConfig`] with the given block size.
+    pub fn with_block_size(mut self, block_size: usize) -> Self {
+        self.block_size = block_size;
+        self
+    }
+
+    /// Returns this [`RenderConfig`] rendering the given number of samples.
+    pub fn with_num_samples(mut self, num_samples: usize) -> Self {
+        self.num_samples = num_samples;
+        self
+    }
+
+    /// Returns this [`RenderConfig`] with the given input seed.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+/// The maximum differences between rendered and expected outputs that [`assert_golden`] accepts.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tolerance {
+    /// The largest accepted absolute difference of any sample.
+    pub max_error: f64,
+    /// The smallest accepted signal-to-noise ratio in dB, treating the differences as noise.
+    pub min_snr: f64,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Self {
+            max_error: 1e-6,
+            min_snr: 100.0,
+        }
+    }
+}
+
+/// How much two sets of channels differ, as returned by [`compare`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Comparison {
+    /// The largest absolute difference of any sample, or infinity if only one of two samples is NaN or infinite.
+    pub max_error: f64,
+    /// The channel and sample index of the largest difference, or `None` if the channels are identical.
+    pub max_error_at: Option<(usize, usize)>,
+    /// The ratio of the power of the expected channels to that of the differences in dB, or infinity if they're identical.
+    pub snr: f64,
+}
+
+impl Comparison {
+    /// Returns `true` if the differences are within the given [`Tolerance`].
+    pub fn is_within(&self, tolerance: &Tolerance) -> bool {
+        self.max_error <= tolerance.max_error && self.snr >= tolerance.min_snr
+    }
+}
+
+impl std::fmt::Display for Comparison {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.max_error_at {
+            Some((channel, index)) => write!(
+                f,
+                "max error {:e} (channel {channel}, sample {index}), SNR {:.1} dB",
+                self.max_error, self.snr
+            ),
+            None => f.write_str("identical"),
+        }
+    }
+}
+
+/// Returns a deterministic test signal for the given seed: a sine sweep from 20 Hz to 20 kHz (at 48 kHz) mixed with white noise,
+/// peaking just below `1.0`.
+pub fn test_signal(seed: u64, len: usize) -> Box<[Sample]> {
+    let mut noise = crate::builtins::oscillators::NoiseOscillator::new(seed);
+    let mut buffer = [Buffer::zeros(len)];
+    noise.process(&[], &mut buffer);
+
+    let mut phase = 0.0;
+    buffer[0]
+        .iter()
+        .enumerate()
+        .map(|(i, noise)| {
+            // exponential sweep over the whole signal, in cycles per sample
+            let frequency = 20.0 / 48_000.0 * 1000f64.powf(i as f64 / len.max(1) as f64);
+            let sample = 0.6 * (phase * std::f64::consts::TAU).sin() + 0.3 * noise.value();
+            phase = (phase + frequency).fract();
+            Sample::new(sample)
+        })
+        .collect()
+}
+
+/// Renders the given graph offline with the given settings, feeding each graph input a [`test_signal`].
+///
+/// The graph is cloned, so it can be rendered again with other settings. The outputs include the graph's [`tail_length`](Graph::tail_length).
+pub fn render(graph: &Graph, config: &RenderConfig) -> TestingResult<Channels> {
+    let inputs = (0..graph.num_inputs())
+        .map(|i| test_signal(config.seed.wrapping_add(i as u64), config.num_samples))
+        .collect::<Vec<_>>();
+
+    let mut runtime = Runtime::new(graph.clone());
+    Ok(runtime.render_offline(
+        config.num_samples,
+        &inputs,
+        config.sample_rate,
+        config.block_size,
+    )?)
+}
+
+/// Compares rendered channels to expected ones, sample by sample. Samples that are both NaN, or both the same infinity, count as equal.
+pub fn compare(actual: &[Box<[Sample]>], expected: &[Box<[Sample]>]) -> TestingResult<Comparison> {
+    if actual.len() != expected.len() {
+        return Err(TestingError::ChannelMismatch {
+            expected: expected.len(),
+            actual: actual.len(),
+        });
+    }
+
+    let mut max_error = 0.0;
+    let mut max_error_at = None;
+    let mut signal_power = 0.0;
+    let mut noise_power = 0.0;
+    for (channel, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+        if actual.len() != expected.len() {
+            return Err(TestingError::LengthMismatch {
+                channel,
+                expected: expected.len(),
+                actual: actual.len(),
+            });
+        }
+
+        for (index, (&actual, &expected)) in actual.iter().zip(expected.iter()).enumerate() {
+            let (actual, expected) = (actual.value(), expected.value());
+            let error = sample_error(actual, expected);
+
+            if expected.is_finite() {
+                signal_power += expected * expected;
+            }
+            noise_power += error * error;
+            if error > max_error {
+                max_error = error;
+                max_error_at = Some((channel, index));
+            }
+        }
+    }
+
+    let snr = if noise_power == 0.0 {
+        f64::INFINITY
+    } else {
+        10.0 * (signal_power / noise_power).log10()
+    };
+
+    Ok(Comparison {
+        max_error,
+        max_error_at,
+        snr,
+    })
+}
+
+/// Returns the absolute difference of two samples, counting two NaNs or the same infinity as equal, and any other non-finite difference as infinite.
+fn sample_error(actual: f64, expected: f64) -> f64 {
+    if actual == expected || (actual.is_nan() && expected.is_nan()) {
+        0.0
+    } else if actual.is_finite() && expected.is_finite() {
+        (actual - expected).abs()
+    } else {
+        f64::INFINITY
+    }
+}
+
+/// Compares the given outputs to the golden WAV file at the given path, panicking with a report of the differences if they aren't within the tolerance.
+///
+/// If the [`BLESS_ENV_VAR`] environment variable is set, the golden file is (re)written from the outputs instead.
+///
+/// # Panics
+///
+/// Panics if the outputs don't match the golden file, or if it can't be read or written.
+pub fn assert_golden(
+    path: impl AsRef<Path>,
+    outputs: &[Box<[Sample]>],
+    sample_rate: f64,
+    tolerance: &Tolerance,
+) {
+    let path = path.as_ref();
+
+    if std::env::var_os(BLESS_ENV_VAR).is_some() {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .unwrap_or_else(|err| panic!("Failed to create {}: {err}", parent.display()));
+        }
+        write_wav(path, outputs, sample_rate)
+            .unwrap_or_else(|err| panic!("Failed to write {}: {err}", path.display()));
+        return;
+    }
+
+    let (expected, expected_rate) = read_wav(path).unwrap_or_else(|err| {
+        panic!(
+            "Failed to read golden file {}: {err}; run with {BLESS_ENV_VAR}=1 to create it",
+            path.display()
+        )
+    });
+    assert_eq!(
+        expected_rate,
+        sample_rate,
+        "Golden file {} has a different sample rate",
+        path.display()
+    );
+
+    // the golden files only have the precision of 32-bit floats
+    let actual = outputs
+        .iter()
+        .map(|output| {
+            output
+                .iter()
+                .map(|&sample| Sample::new(sample.value() as f32 as f64))
+                .collect()
+        })
+        .collect::<Vec<Box<[Sample]>>>();
+
+    let comparison = compare(&actual, &expected)
+        .unwrap_or_else(|err| panic!("Output doesn't match {}: {err}", path.display()));
+    assert!(
+        comparison.is_within(tolerance),
+        "Output doesn't match {}: {comparison} (tolerance: max error {:e}, SNR {:.1} dB)",
+        path.display(),
+        tolerance.max_error,
+        tolerance.min_snr
+    );
+}
+
+/// Renders the given graph with [`render`] and compares the result to the golden WAV file at the given path with [`assert_golden`].
+///
+/// The graph is also checked with [`check_block_size_invariance`] for the block sizes in [`BLOCK_SIZES`] and the configured one.
+///
+/// # Panics
+///
+/// Panics if the graph can't be rendered, if the outputs don't match the golden file, or if they depend on the block size.
+pub fn assert_golden_render(
+    graph: &Graph,
+    config: &RenderConfig,
+    path: impl AsRef<Path>,
+    tolerance: &Tolerance,
+) {
+    let outputs = render(graph, config).unwrap_or_else(|err| panic!("Failed to render: {err}"));
+    assert_golden(path, &outputs, config.sample_rate, tolerance);
+
+    let mut block_sizes = BLOCK_SIZES.to_vec();
+    block_sizes.push(config.block_size);
+    assert_no_divergences(graph, config, &block_sizes);
+}
+
+/// Where the output of a graph rendered with some block size first differs from its output rendered one sample at a time.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Divergence {
+    /// The block size the graph was rendered with.
+    pub block_size: usize,
+    /// The output channel of the first different sample.
+    pub channel: usize,
+    /// The index of the first different sample.
+    pub sample: usize,
+    /// The first different sample when rendered one sample at a time.
+    pub expected: f64,
+    /// The first different sample when rendered with [`Divergence::block_size`].
+    pub actual: f64,
+    /// How much the whole outputs differ.
+    pub comparison: Comparison,
+}
+
+impl Divergence {
+    /// Returns the index of the block the first different sample is in, and its index within that block.
+    pub fn block(&self) -> (usize, usize) {
+        (self.sample / self.block_size, self.sample % self.block_size)
+    }
+}
+
+impl std::fmt::Display for Divergence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (block, offset) = self.block();
+        write!(
+            f,
+            "block size {}: channel {} first differs at sample {} (block {block}, offset {offset}), expected {} but got {}; {}",
+            self.block_size, self.channel, self.sample, self.expected, self.actual, self.comparison
+        )
+    }
+}
+
+/// Returns a graph containing just the given processor, with each of its inputs fed by a graph input and each of its outputs going to a graph output.
+///
+/// Useful for testing a single processor with [`render`], [`assert_golden_render`] or [`assert_block_size_invariant`].
+pub fn processor_graph(processor: Processor) -> Graph {
+    let mut graph = Graph::new();
+    let num_inputs = processor.inputs().len();
+    let num_outputs = processor.outputs().len();
+    let node = graph.add_processor_object(processor);
+    for i in 0..num_inputs {
+        let input = graph.add_input();
+        graph
+            .connect(input, 0, node, i as u32)
+            .expect("graph inputs can be connected to any input");
+    }
+    for i in 0..num_outputs {
+        let output = graph.add_output();
+        graph
+            .connect(node, i as u32, output, 0)
+            .expect("any output can be connected to a graph output");
+    }
+    graph
+}
+
+/// Renders the given graph with each of the given block sizes, and compares the outputs to those rendered with a block size of 1.
+///
+/// Returns a [`Divergence`] for each block size whose outputs differ at all, in the given order.
+///
+/// # Panics
+///
+/// Panics if any of the block sizes is 0.
+pub fn check_block_size_invariance(
+    graph: &Graph,
+    config: &RenderConfig,
+    block_sizes: &[usize],
+) -> TestingResult<Vec<Divergence>> {
+    assert!(
+        !block_sizes.contains(&0),
+        "Block sizes must be greater than zero"
+    );
+
+    let expected = render(graph, &config.with_block_size(1))?;
+
+    let mut divergences = Vec::new();
+    for &block_size in block_sizes.iter().filter(|&&block_size| block_size != 1) {
+        let actual = render(graph, &config.with_block_size(block_size))?;
+        let comparison = compare(&actual, &expected)?;
+        if comparison.max_error_at.is_none() {
+            continue;
+        }
+
+        // the earliest different sample in any channel
+        let (channel, sample) = actual
+            .iter()
+            .zip(expected.iter())
+            .enumerate()
+            .filter_map(|(channel, (actual, expected))| {
+                let sample =
+                    actual
+                        .iter()
+                        .zip(expected.iter())
+                        .position(|(actual, expected)| {
+                            sample_error(actual.value(), expected.value()) > 0.0
+                        })?;
+                Some((channel, sample))
+            })
+            .min_by_key(|&(_, sample)| sample)
+            .expect("differing outputs have a differing sample");
+
+        divergences.push(Divergence {
+            block_size,
+            channel,
+            sample,
+            expected: expected[channel][sample].value(),
+            actual: actual[channel][sample].value(),
+            comparison,
+        });
+    }
+
+    Ok(divergences)
+}
+
+/// Checks that the given graph's outputs are identical when rendered with each of the given block sizes (see [`check_block_size_invariance`]),
+/// with the default [`RenderConfig`]. [`BLOCK_SIZES`] is a good selection of block sizes to check.
+///
+/// # Panics
+///
+/// Panics with a report of where the outputs diverge, if they do for any of the block sizes, or if the graph can't be rendered.
+pub fn assert_block_size_invariant(graph: &Graph, block_sizes: &[usize]) {
+    assert_no_divergences(graph, &RenderConfig::default(), block_sizes);
+}
+
+fn assert_no_divergences(graph: &Graph, config: &RenderConfig, block_sizes: &[usize]) {
+    let divergences = check_block_size_invariance(graph, config, block_sizes)
+        .unwrap_or_else(|err| panic!("Failed to render: {err}"));
+    if !divergences.is_empty() {
+        let report = divergences
+            .iter()
+            .map(|divergence| format!("  {divergence}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        panic!("Output depends on the block size:\n{report}");
+    }
+}
@@ -0,0 +1,84 @@
+//! Tests for the block size invariance check, with user-defined processors.
+
+use daprs::{
+    prelude::*,
+    testing::{
+        assert_block_size_invariant, check_block_size_invariance, processor_graph, RenderConfig,
+        BLOCK_SIZES,
+    },
+};
+
+/// A one-pole lowpass filter, whose state carries over between blocks as it should.
+#[derive(Clone, Debug, Default, Process)]
+struct OnePole {
+    #[input]
+    input: f64,
+    #[output]
+    out: f64,
+}
+
+impl SampleProcess for OnePole {
+    fn tick(&mut self) {
+        self.out += 0.1 * (self.input - self.out);
+    }
+}
+
+/// A ramp that mistakenly advances once per block rather than once per sample.
+#[derive(Clone, Debug, Default)]
+struct BlockRamp {
+    value: f64,
+}
+
+impl Process for BlockRamp {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn process(&mut self, _inputs: &[Buffer], outputs: &mut [Buffer]) {
+        self.value += 0.001;
+        outputs[0].fill(Sample::new(self.value));
+    }
+}
+
+#[test]
+fn stateful_processor_is_block_size_invariant() {
+    assert_block_size_invariant(
+        &processor_graph(OnePole::default().processor()),
+        BLOCK_SIZES,
+    );
+}
+
+#[test]
+fn per_block_processor_diverges() {
+    let graph = processor_graph(BlockRamp::default().processor());
+    let divergences =
+        check_block_size_invariance(&graph, &RenderConfig::default(), &[1, 7, 64]).unwrap();
+
+    assert_eq!(divergences.len(), 2);
+    for divergence in divergences.iter() {
+        // the ramp holds its first value for the rest of the first block
+        assert_eq!(divergence.channel, 0);
+        assert_eq!(divergence.sample, 1);
+        assert_eq!(divergence.block(), (0, 1));
+        assert_eq!(divergence.expected, 0.002);
+        assert_eq!(divergence.actual, 0.001);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Output depends on the block size")]
+fn assert_reports_divergence() {
+    assert_block_size_invariant(&processor_graph(BlockRamp::default().processor()), &[1, 3]);
+}
+
+#[test]
+fn divided_rate_is_block_size_invariant() {
+    // the divisor divides none of the block sizes but 1, so the decimation phase has to carry over between blocks
+    let mut processor = OnePole::default().processor();
+    processor.set_rate(Rate::Divided(5));
+    assert_block_size_invariant(&processor_graph(processor), BLOCK_SIZES);
+}
@@ -0,0 +1,87 @@
+//! Tests for the errors returned by the graph builder's fallible `try_` methods.
+
+use daprs::{graph::GraphConstructionError, graph::NodeIndex, prelude::*};
+
+/// Emits a trigger on every sample.
+#[derive(Clone, Default, Process)]
+struct Ticks {
+    #[output(kind = Trigger)]
+    out: f64,
+}
+
+impl SampleProcess for Ticks {
+    fn tick(&mut self) {
+        self.out = 1.0;
+    }
+}
+
+/// Combines two gates.
+#[derive(Clone, Default, Process)]
+struct GateAnd {
+    #[input(kind = Gate)]
+    a: f64,
+    #[input(kind = Gate)]
+    b: f64,
+    #[output(kind = Gate)]
+    out: f64,
+}
+
+impl SampleProcess for GateAnd {
+    fn tick(&mut self) {
+        self.out = self.a.min(self.b);
+    }
+}
+
+#[test]
+fn named_ports_of_unknown_nodes_are_errors() {
+    let graph = GraphBuilder::new();
+    let constant = graph.add_constant(1.0);
+    let unknown = NodeIndex::new(100);
+
+    assert!(matches!(
+        constant.try_connect_output(0, unknown, "in"),
+        Err(GraphConstructionError::UnknownNode(node)) if node == unknown
+    ));
+    assert!(matches!(
+        constant.try_connect_input(unknown, "out", 0),
+        Err(GraphConstructionError::UnknownNode(node)) if node == unknown
+    ));
+}
+
+#[test]
+fn named_ports_are_looked_up() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output();
+    let sine = graph.add(SineOscillator::default());
+    sine.try_connect_input(440.0, 0, "frequency").unwrap();
+    sine.try_connect_output("out", out, 0).unwrap();
+
+    assert!(matches!(
+        sine.try_connect_input(440.0, 0, "pitch"),
+        Err(GraphConstructionError::UnknownPort { name, .. }) if name == "pitch"
+    ));
+}
+
+#[test]
+fn failed_binary_ops_leave_no_nodes_behind() {
+    let graph = GraphBuilder::new();
+    let audio = graph.add_constant(1.0);
+    let ticks = graph.add(Ticks::default());
+    let node_count = || graph.with_graph(|graph| graph.digraph().node_count());
+    let before = node_count();
+
+    // the first input gets a converter from audio to a gate, and the second can't be connected at all
+    let result = audio.try_binary("and", ticks, GateAnd::default());
+    assert!(matches!(
+        result,
+        Err(GraphConstructionError::IncompatibleSignals { .. })
+    ));
+    assert_eq!(node_count(), before);
+
+    // the graph is still usable afterwards, with a converter for each input
+    let gate = audio.try_binary("and", audio, GateAnd::default()).unwrap();
+    assert_eq!(node_count(), before + 3);
+    let out = graph.add_output();
+    gate.connect_output(0, out, 0);
+    graph.build();
+}
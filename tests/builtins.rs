@@ -0,0 +1,229 @@
+//! Golden-file regression tests for every builtin processor.
+//!
+//! Each processor is rendered with its inputs fed by test signals, and compared to `tests/golden/<Processor>.wav`.
+//! Run with `DAPRS_BLESS=1` to regenerate the golden files after an intended change in output.
+
+use std::path::PathBuf;
+
+use daprs::{
+    prelude::*,
+    testing::{
+        assert_golden, assert_golden_render, compare, processor_graph, render, RenderConfig,
+        Tolerance, BLOCK_SIZES,
+    },
+};
+
+fn golden_path(name: &str) -> PathBuf {
+    let name = name.rsplit("::").next().unwrap();
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.wav"))
+}
+
+/// The patch of a small voice: a sine at the `frequency` input, gated by the `gate` input.
+fn voice_patch() -> Param {
+    let graph = GraphBuilder::new();
+    let frequency = graph.add_input();
+    let gate = graph.add_input();
+    let out = graph.add_output();
+    let sine = graph.add(SineOscillator::default());
+    frequency.connect_output(0, sine, 0);
+    (sine * gate).connect_output(0, out, 0);
+
+    SubGraph::new(graph.build())
+        .with_input_names(["frequency", "gate"])
+        .to_patch()
+        .unwrap()
+        .to_param()
+}
+
+/// The patch of a small effect: a soft clipper mixed with a sine.
+fn effect_patch() -> Param {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let sine = graph.add(SineOscillator::default());
+    sine.connect_input(330.0, 0, "frequency");
+    let clip = graph.add(ExprProc::parse("tanh(a * 3) * 0.5").unwrap());
+    input.connect_output(0, clip, 0);
+    (clip + sine * 0.5).connect_output(0, out, 0);
+
+    SubGraph::new(graph.build()).to_patch().unwrap().to_param()
+}
+
+/// Returns the parameters each builtin is tested with.
+fn params_for(name: &str) -> Params {
+    let params: Vec<(&str, Param)> = match name.rsplit("::").next().unwrap() {
+        "ConstantProc" => vec![("value", Param::from(0.5))],
+        "NoiseOscillator" => vec![("seed", Param::from(42.0))],
+        "ExprProc" => vec![("expr", Param::from("a * sin(b * 10) + 0.5"))],
+        "SubGraph" => vec![("patch", effect_patch())],
+        "Poly" => vec![("voice", voice_patch()), ("voices", Param::from(4.0))],
+        "Oversample" => vec![
+            ("factor", Param::from(4.0)),
+            (
+                "processor",
+                Param::Map(Params::from([(
+                    "type".to_owned(),
+                    Param::from("daprs::builtins::math::TanhProc"),
+                )])),
+            ),
+        ],
+        _ => vec![],
+    };
+    params
+        .into_iter()
+        .map(|(name, param)| (name.to_owned(), param))
+        .collect()
+}
+
+#[test]
+fn builtins_match_golden_files() {
+    let registry = ProcessorRegistry::with_builtins();
+    let config = RenderConfig::default();
+    let tolerance = Tolerance::default();
+
+    for name in registry.names() {
+        // voices are only started by note events, see `poly_matches_golden_file`
+        if name.ends_with("::Poly") {
+            continue;
+        }
+
+        let processor = registry.create(name, &params_for(name)).unwrap();
+        assert_golden_render(
+            &processor_graph(processor),
+            &config,
+            golden_path(name),
+            &tolerance,
+        );
+    }
+}
+
+#[test]
+fn poly_matches_golden_file() {
+    let registry = ProcessorRegistry::with_builtins();
+    let name = Poly::NAME;
+    let processor = registry.create(name, &params_for(name)).unwrap();
+    let handle = processor.downcast_ref::<Poly>().unwrap().handle();
+    let graph = processor_graph(processor);
+    let config = RenderConfig::default();
+
+    // the graph's clones share the event queue, so the notes are played by the next render
+    let render_chord = |config: &RenderConfig| {
+        handle.note_on(60, 1.0);
+        handle.note_on(64, 0.8);
+        handle.note_on(67, 0.6);
+        render(&graph, config).unwrap()
+    };
+
+    let outputs = render_chord(&config);
+    assert_golden(
+        golden_path(name),
+        &outputs,
+        config.sample_rate,
+        &Tolerance::default(),
+    );
+
+    for &block_size in BLOCK_SIZES {
+        let other = render_chord(&config.with_block_size(block_size));
+        let comparison = compare(&other, &outputs).unwrap();
+        assert!(
+            comparison.max_error_at.is_none(),
+            "Output at block size {block_size} differs: {comparison}"
+        );
+    }
+}
+
+#[test]
+fn builtins_are_registered_under_their_names() {
+    let registry = ProcessorRegistry::with_builtins();
+    for name in registry.names() {
+        let processor = registry.create(name, &params_for(name)).unwrap();
+        assert_eq!(processor.name(), name);
+    }
+
+    assert_eq!(SineOscillator::default().name(), SineOscillator::NAME);
+    assert_eq!(
+        SineOscillator::NAME,
+        "daprs::builtins::oscillators::SineOscillator"
+    );
+}
+
+#[test]
+fn noise_seeds_survive_patch_round_trips() {
+    let registry = ProcessorRegistry::with_builtins();
+    let config = RenderConfig::default();
+
+    let zero = render(
+        &processor_graph(NoiseOscillator::new(0).processor()),
+        &config,
+    )
+    .unwrap();
+    let one = render(
+        &processor_graph(NoiseOscillator::new(1).processor()),
+        &config,
+    )
+    .unwrap();
+    assert_ne!(zero, one);
+
+    let seed = u64::MAX - 1;
+    let params = NoiseOscillator::new(seed).params();
+    let processor = registry.create(NoiseOscillator::NAME, &params).unwrap();
+    let noise = processor.downcast_ref::<NoiseOscillator>().unwrap();
+    assert_eq!(noise.seed(), seed);
+}
+
+#[test]
+fn subgraph_port_names_survive_patch_round_trips() {
+    let graph = GraphBuilder::new();
+    let a = graph.add_input();
+    let b = graph.add_input();
+    let out = graph.add_output();
+    (a - b).connect_output(0, out, 0);
+
+    // swapped default names, an output named like a default input, and an input named like a processor node
+    let subgraph = SubGraph::new(graph.build())
+        .with_input_names(["in1", "n3"])
+        .with_output_names(["in0"]);
+    let patch = subgraph.to_patch().unwrap();
+    assert_eq!(patch.inputs, ["in1", "n3"]);
+    assert_eq!(patch.outputs, ["in0"]);
+
+    let registry = ProcessorRegistry::with_builtins();
+    let loaded = SubGraph::from_patch(&patch, &registry).unwrap();
+    assert_eq!(loaded.input_names(), subgraph.input_names());
+    assert_eq!(loaded.output_names(), subgraph.output_names());
+
+    let config = RenderConfig::default();
+    let expected = render(subgraph.graph(), &config).unwrap();
+    let actual = render(loaded.graph(), &config).unwrap();
+    assert!(compare(&actual, &expected).unwrap().max_error_at.is_none());
+}
+
+#[test]
+#[should_panic(expected = "Duplicate subgraph port name")]
+fn subgraph_rejects_duplicate_port_names() {
+    let graph = GraphBuilder::new();
+    graph.add_input();
+    graph.add_input();
+    SubGraph::new(graph.build()).with_input_names(["a", "a"]);
+}
+
+#[test]
+fn closures_cannot_be_saved_in_patches() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    input.map(|x| x * 2.0).connect_output(0, out, 0);
+    let graph = graph.build();
+    assert!(Patch::from_graph(&graph).is_err());
+
+    // also when nested in a subgraph
+    let outer = GraphBuilder::new();
+    let input = outer.add_input();
+    let out = outer.add_output();
+    let inner = outer.add(SubGraph::new(graph));
+    inner.connect_input(input, 0, 0);
+    inner.connect_output(0, out, 0);
+    assert!(Patch::from_graph(&outer.build()).is_err());
+}
@@ -0,0 +1,163 @@
+//! Tests for multichannel [`Bus`] signals: broadcasting, mixing, splitting and panning.
+
+use std::f64::consts::FRAC_1_SQRT_2;
+
+use daprs::{
+    builder::bus::IntoBus,
+    graph::GraphConstructionError,
+    prelude::*,
+    testing::{render, RenderConfig},
+};
+
+/// Renders a graph without inputs, returning the first sample of each output.
+fn first_samples(graph: &Graph) -> Vec<f64> {
+    let config = RenderConfig {
+        num_samples: 16,
+        ..Default::default()
+    };
+    render(graph, &config)
+        .unwrap()
+        .iter()
+        .map(|channel| channel[0].value())
+        .collect()
+}
+
+fn assert_close(actual: &[f64], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?} != {expected:?}");
+    for (a, e) in actual.iter().zip(expected) {
+        assert!((a - e).abs() < 1e-12, "{actual:?} != {expected:?}");
+    }
+}
+
+#[test]
+fn smaller_buses_are_repeated_cyclically() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(3);
+    let bus = [1.0, 2.0, 3.0].into_bus(&graph);
+    (bus + [10.0, 20.0]).connect_to(&out);
+
+    assert_close(&first_samples(&graph.build()), &[11.0, 22.0, 13.0]);
+}
+
+#[test]
+fn mono_signals_are_broadcast() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(2);
+    let bus = [1.0, 2.0].into_bus(&graph);
+    let mono = graph.add_constant(3.0);
+    ((bus * mono) - 1.0).connect_to(&out);
+
+    assert_close(&first_samples(&graph.build()), &[2.0, 5.0]);
+}
+
+#[test]
+fn mix_sums_all_channels() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output();
+    [1.0, 2.0, 4.0]
+        .into_bus(&graph)
+        .mix()
+        .connect_output(0, out, 0);
+
+    assert_close(&first_samples(&graph.build()), &[7.0]);
+}
+
+#[test]
+fn mix_to_sums_every_nth_channel_and_upmixes_cyclically() {
+    let graph = GraphBuilder::new();
+    let down_out = graph.add_output_bus(2);
+    let up_out = graph.add_output_bus(4);
+    let bus = [1.0, 2.0, 4.0].into_bus(&graph);
+    bus.mix_to(2).connect_to(&down_out);
+    bus.mix_to(4).connect_to(&up_out);
+
+    assert_close(
+        &first_samples(&graph.build()),
+        &[5.0, 2.0, 1.0, 2.0, 4.0, 1.0],
+    );
+}
+
+#[test]
+fn connecting_to_fewer_channels_mixes_down() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(2);
+    [1.0, 2.0, 4.0].into_bus(&graph).connect_to(&out);
+
+    assert_close(&first_samples(&graph.build()), &[5.0, 2.0]);
+}
+
+#[test]
+fn split_makes_a_channel_per_output() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(2);
+    let node = graph.add_fn(0, 2, |_, _, outputs| {
+        outputs[0].fill(Sample::new(1.0));
+        outputs[1].fill(Sample::new(2.0));
+    });
+    let bus = node.split();
+    assert_eq!(bus.num_channels(), 2);
+    (bus + 1.0).connect_to(&out);
+
+    assert_close(&first_samples(&graph.build()), &[2.0, 3.0]);
+}
+
+#[test]
+fn split_keeps_single_output_nodes_as_they_are() {
+    let graph = GraphBuilder::new();
+    let constant = graph.add_constant(1.0);
+    let bus = constant.split();
+    assert_eq!(bus.num_channels(), 1);
+    assert_eq!(bus.channel(0).id(), constant.id());
+}
+
+#[test]
+fn pan_uses_an_equal_power_law() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(6);
+    let one = graph.add_constant(1.0);
+    let left = one.pan(-1.0);
+    let center = one.pan(0.0);
+    let right = one.pan(1.0);
+    for (i, stereo) in [left, center, right].iter().enumerate() {
+        stereo.connect_to(&Bus::new(out.channels()[2 * i..2 * i + 2].to_vec()));
+    }
+
+    assert_close(
+        &first_samples(&graph.build()),
+        &[1.0, 0.0, FRAC_1_SQRT_2, FRAC_1_SQRT_2, 0.0, 1.0],
+    );
+}
+
+#[test]
+fn bus_pan_sums_each_channel_at_its_own_position() {
+    let graph = GraphBuilder::new();
+    let out = graph.add_output_bus(2);
+    let bus = [1.0, 2.0].into_bus(&graph);
+    bus.pan([-1.0, 1.0]).connect_to(&out);
+
+    assert_close(&first_samples(&graph.build()), &[1.0, 2.0]);
+}
+
+#[test]
+fn empty_buses_are_errors() {
+    let graph = GraphBuilder::new();
+    assert!(matches!(
+        graph.try_add_input_bus(0),
+        Err(GraphConstructionError::EmptyBus)
+    ));
+    assert!(matches!(
+        graph.try_add_output_bus(0),
+        Err(GraphConstructionError::EmptyBus)
+    ));
+    assert!(matches!(
+        Bus::try_new(vec![]),
+        Err(GraphConstructionError::EmptyBus)
+    ));
+
+    let sink = graph.add_fn(1, 0, |_, _, _| {});
+    assert!(matches!(
+        sink.try_split(),
+        Err(GraphConstructionError::EmptyBus)
+    ));
+    assert_eq!(graph.build().num_inputs(), 0);
+}
@@ -0,0 +1,99 @@
+//! Tests for bypassing and muting processors.
+
+use std::collections::VecDeque;
+
+use daprs::{
+    prelude::*,
+    testing::{compare, processor_graph, render, RenderConfig, Tolerance},
+};
+
+/// A delay standing in for a processor with lookahead, which reports its delay as its latency.
+#[derive(Clone, Debug)]
+struct Lookahead {
+    delay: VecDeque<f64>,
+}
+
+impl Lookahead {
+    const LATENCY: usize = 3;
+
+    fn new() -> Self {
+        Self {
+            delay: vec![0.0; Self::LATENCY].into(),
+        }
+    }
+}
+
+impl Process for Lookahead {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn latency(&self) -> usize {
+        Self::LATENCY
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (input, output) in inputs[0].iter().zip(outputs[0].iter_mut()) {
+            self.delay.push_back(input.value());
+            *output = Sample::new(self.delay.pop_front().unwrap());
+        }
+    }
+}
+
+#[test]
+fn bypassed_inputs_are_delayed_by_the_latency() {
+    let processor = Lookahead::new().processor();
+    let handle = processor.bypass_handle();
+    let graph = processor_graph(processor);
+    let config = RenderConfig::default();
+    let expected = render(&graph, &config).unwrap();
+
+    // the processor's output is its delayed input, so bypassing it mustn't change anything
+    handle.bypass();
+    let actual = render(&graph, &config).unwrap();
+
+    let comparison = compare(&actual, &expected).unwrap();
+    assert!(
+        comparison.is_within(&Tolerance::default()),
+        "Bypassed output differs: {comparison}"
+    );
+}
+
+#[test]
+fn restoring_a_snapshot_leaves_the_bypass_state_alone() {
+    let processor = Lookahead::new().processor();
+    let handle = processor.bypass_handle();
+    let graph = processor_graph(processor);
+
+    handle.bypass();
+    let snapshot = graph.snapshot();
+    handle.activate();
+
+    // the clone shares its bypass state with the original graph, so restoring it mustn't bypass the original again
+    let mut clone = graph.clone();
+    clone.reset(48_000.0, 64);
+    clone.restore(&snapshot).unwrap();
+    assert_eq!(handle.get(), BypassState::Active);
+}
+
+#[test]
+fn clones_are_bypassed_independently() {
+    let processor = Lookahead::new().processor();
+    let handle = processor.bypass_handle();
+    handle.mute();
+
+    let clone = processor.clone();
+    assert_eq!(clone.bypass_state(), BypassState::Muted);
+
+    handle.activate();
+    assert_eq!(processor.bypass_state(), BypassState::Active);
+    assert_eq!(clone.bypass_state(), BypassState::Muted);
+}
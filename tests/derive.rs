@@ -0,0 +1,78 @@
+//! Tests for `#[derive(Process)]` and the arguments of its `#[input]` and `#[output]` attributes.
+
+use daprs::{
+    builder::typed_node::StaticPorts,
+    prelude::*,
+    testing::{processor_graph, render, test_signal, RenderConfig},
+};
+
+#[derive(Clone, Debug, Default, Process)]
+struct Ports {
+    #[input]
+    plain: f64,
+    #[input(name = "renamed")]
+    named: f64,
+    #[input(default = 0.5, min = -1.0, max = 1)]
+    bounded: f64,
+    #[input(kind = Gate)]
+    gate: f64,
+    #[output(name = "sum", kind = Control)]
+    sum: f64,
+    #[output(min = 0.0)]
+    positive: f64,
+}
+
+impl SampleProcess for Ports {
+    fn tick(&mut self) {
+        self.sum = self.plain + self.named;
+        self.positive = self.bounded.max(0.0);
+    }
+}
+
+#[test]
+fn attribute_arguments_end_up_in_the_signal_specs() {
+    let ports = Ports::default();
+    assert_eq!(
+        ports.input_spec(),
+        vec![
+            SignalSpec::unbounded("plain", 0.0),
+            SignalSpec::unbounded("renamed", 0.0),
+            SignalSpec::new("bounded", -1.0, 1.0, 0.5),
+            SignalSpec::unbounded("gate", 0.0).with_kind(SignalKind::Gate),
+        ]
+    );
+    assert_eq!(
+        ports.output_spec(),
+        vec![
+            SignalSpec::unbounded("sum", 0.0).with_kind(SignalKind::Control),
+            SignalSpec::new("positive", 0.0, f64::MAX, 0.0),
+        ]
+    );
+}
+
+#[test]
+fn ports_are_counted_and_named() {
+    assert_eq!(Ports::NUM_INPUTS, 4);
+    assert_eq!(Ports::NUM_OUTPUTS, 2);
+    assert_eq!(Ports::default().name(), concat!(module_path!(), "::Ports"));
+
+    // the constants are named after the fields, not the port names
+    let graph = GraphBuilder::new();
+    let node = graph.add_typed(Ports::default());
+    node.connect_input(Ports::NAMED, 1.0)
+        .connect_input(Ports::PLAIN, 2.0);
+    let _ = node.output(Ports::SUM);
+}
+
+#[test]
+fn inputs_are_read_and_outputs_written_per_sample() {
+    let config = RenderConfig::default();
+    let graph = processor_graph(Ports::default().processor());
+    let output = render(&graph, &config).unwrap();
+
+    let bounded = test_signal(config.seed + 2, config.num_samples);
+    assert_eq!(output[1].len(), bounded.len());
+    for (out, input) in output[1].iter().zip(bounded.iter()) {
+        assert_eq!(out.value(), input.value().max(0.0));
+    }
+}
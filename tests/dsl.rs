@@ -0,0 +1,196 @@
+//! Tests for compiling patch programs with the [`dsl`](daprs::dsl) module.
+
+use daprs::{
+    dsl::{
+        self,
+        lexer::{tokenize, TokenKind},
+        parser::{ExprKind, Statement, MAX_INDEX},
+        Span,
+    },
+    prelude::*,
+    registry::ProcessorRegistry,
+};
+
+/// Returns the error compiling the given source, with the source text its span covers.
+fn compile_error(source: &str) -> (String, &str) {
+    let Err(err) = dsl::compile(source) else {
+        panic!("`{source}` should fail to compile");
+    };
+    (err.message, &source[err.span.start..err.span.end])
+}
+
+#[test]
+fn module_example_compiles() {
+    let source = "
+# comments start with `#` or `//`
+let lfo = sine(0.5) * 0.5 + 0.5
+out[0] = sine(440) * lfo + noise{seed: 42}() * 0.1
+out[1] = in[0] * 0.5
+";
+    let graph = dsl::compile(source).unwrap();
+    assert_eq!(graph.num_inputs(), 1);
+    assert_eq!(graph.num_outputs(), 2);
+}
+
+#[test]
+fn tokens_have_spans_and_newlines_in_brackets_are_skipped() {
+    let source = "let x = sine(\n  1e-3, // comment\n)\nout[0] = x";
+    let tokens = tokenize(source).unwrap();
+    let kinds: Vec<_> = tokens.iter().map(|token| token.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::Let,
+            TokenKind::Ident("x".to_owned()),
+            TokenKind::Eq,
+            TokenKind::Ident("sine".to_owned()),
+            TokenKind::LParen,
+            TokenKind::Number(1e-3),
+            TokenKind::Comma,
+            TokenKind::RParen,
+            TokenKind::Separator,
+            TokenKind::Ident("out".to_owned()),
+            TokenKind::LBracket,
+            TokenKind::Number(0.0),
+            TokenKind::RBracket,
+            TokenKind::Eq,
+            TokenKind::Ident("x".to_owned()),
+            TokenKind::Eof,
+        ]
+    );
+    assert_eq!(tokens[5].span, Span::new(16, 20));
+    assert_eq!(&source[tokens[5].span.start..tokens[5].span.end], "1e-3");
+}
+
+#[test]
+fn unknown_functions_are_reported_with_their_span() {
+    let source = "let x = 1\nout[0] = sinee(440)";
+    let Err(err) = dsl::compile(source) else {
+        panic!("`sinee` isn't a function");
+    };
+    assert_eq!(err.message, "unknown function `sinee`");
+    assert_eq!(err.span, Span::new(19, 24));
+    assert_eq!((err.line, err.column), (2, 10));
+    assert_eq!(
+        err.render(source),
+        "error: unknown function `sinee`\n --> 2:10\n  |\n2 | out[0] = sinee(440)\n  |          ^^^^^"
+    );
+}
+
+#[test]
+fn named_arguments_and_params_are_parsed() {
+    let program = dsl::parse("out[0] = noise{seed: 42, kind: \"white\"}(x: in[0], 1)").unwrap();
+    let [Statement::Output { value, .. }] = &program.statements[..] else {
+        panic!("expected a single output statement");
+    };
+    let ExprKind::Call {
+        function,
+        params,
+        args,
+    } = &value.kind
+    else {
+        panic!("expected a call");
+    };
+
+    assert_eq!(function.name, "noise");
+    assert_eq!(params.len(), 2);
+    assert_eq!(params[0].name.name, "seed");
+    assert_eq!(params[0].value, Param::Float(42.0));
+    assert_eq!(params[1].value, Param::String("white".to_owned()));
+    assert_eq!(args.len(), 2);
+    assert_eq!(
+        args[0].name.as_ref().map(|name| name.name.as_str()),
+        Some("x")
+    );
+    assert_eq!(args[0].value.kind, ExprKind::Input(0));
+    assert!(args[1].name.is_none());
+}
+
+#[test]
+fn named_arguments_and_params_are_lowered() {
+    let graph = dsl::compile("out[0] = sine(frequency: 440) + noise{seed: 42}()").unwrap();
+    assert_eq!(graph.num_outputs(), 1);
+
+    let (message, spanned) = compile_error("out[0] = sine(pitch: 440)");
+    assert_eq!(message, "`sine` has no input named `pitch`");
+    assert_eq!(spanned, "pitch");
+
+    let (message, spanned) = compile_error("out[0] = noise{seed: 1, seed: 2}()");
+    assert_eq!(message, "parameter `seed` is given more than once");
+    assert_eq!(spanned, "seed: 2");
+
+    let (message, spanned) = compile_error("out[0] = noise{seed: -1}()");
+    assert!(
+        message.starts_with("invalid parameter `seed` for `noise`"),
+        "{message}"
+    );
+    assert_eq!(spanned, "seed: -1");
+}
+
+#[test]
+fn out_of_range_indices_are_rejected() {
+    for source in ["out[1e300] = 1", "out[0] = in[1e300]", "out[100000000] = 1"] {
+        let (message, spanned) = compile_error(source);
+        assert!(message.contains("out of range"), "{source}: {message}");
+        assert!(spanned.starts_with("1"), "{source}: {spanned}");
+    }
+
+    let source = format!("out[{MAX_INDEX}] = in[{MAX_INDEX}]");
+    let graph = dsl::compile(&source).unwrap();
+    assert_eq!(graph.num_outputs(), MAX_INDEX + 1);
+    assert_eq!(graph.num_inputs(), MAX_INDEX + 1);
+}
+
+/// A processor with a trigger output.
+#[derive(Clone, Debug, Default)]
+struct Clock;
+
+impl Process for Clock {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("tick", 0.0).with_kind(SignalKind::Trigger)]
+    }
+
+    fn process(&mut self, _inputs: &[Buffer], outputs: &mut [Buffer]) {
+        outputs[0].fill(Sample::ZERO);
+    }
+}
+
+/// A processor with a gate input.
+#[derive(Clone, Debug, Default)]
+struct Envelope;
+
+impl Process for Envelope {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("gate", 0.0).with_kind(SignalKind::Gate)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        outputs[0].copy_from_slice(&inputs[0]);
+    }
+}
+
+#[test]
+fn incompatible_signals_are_reported() {
+    let mut registry = ProcessorRegistry::default();
+    registry.register("Clock", |_: &Params| Ok(Clock));
+    registry.register("Envelope", |_: &Params| Ok(Envelope));
+
+    let source = "out[0] = Envelope(Clock())";
+    let Err(err) = dsl::compile_with(source, &registry) else {
+        panic!("a trigger can't be connected to a gate");
+    };
+    assert!(
+        err.message.contains("input `gate` of `Envelope`"),
+        "{}",
+        err.message
+    );
+    assert_eq!(&source[err.span.start..err.span.end], "Clock()");
+}
@@ -0,0 +1,152 @@
+//! Tests for latency compensation between parallel paths through a graph.
+
+use std::collections::VecDeque;
+
+use daprs::{
+    graph::{EdgeIndex, NodeIndex},
+    prelude::*,
+    testing::{render, test_signal, RenderConfig},
+};
+use petgraph::prelude::{Direction, EdgeRef};
+
+/// A delay standing in for a processor with lookahead, which reports its delay as its latency.
+#[derive(Clone, Debug)]
+struct Lookahead {
+    delay: VecDeque<f64>,
+}
+
+impl Lookahead {
+    fn new(latency: usize) -> Self {
+        Self {
+            delay: vec![0.0; latency].into(),
+        }
+    }
+}
+
+impl Process for Lookahead {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn latency(&self) -> usize {
+        self.delay.len()
+    }
+
+    fn reset(&mut self) {
+        self.delay.iter_mut().for_each(|sample| *sample = 0.0);
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        for (input, output) in inputs[0].iter().zip(outputs[0].iter_mut()) {
+            self.delay.push_back(input.value());
+            *output = Sample::new(self.delay.pop_front().unwrap());
+        }
+    }
+}
+
+/// Returns the edge from `source` into `target`.
+fn edge_between(graph: &Graph, source: NodeIndex, target: NodeIndex) -> EdgeIndex {
+    graph
+        .digraph()
+        .edges_directed(target, Direction::Incoming)
+        .find(|edge| edge.source() == source)
+        .expect("the nodes are connected")
+        .id()
+}
+
+/// Returns the test signal fed to the first graph input, delayed by the given number of samples.
+fn delayed_input(config: &RenderConfig, len: usize, delay: usize) -> Vec<f64> {
+    let input = test_signal(config.seed, config.num_samples);
+    (0..len)
+        .map(|i| {
+            i.checked_sub(delay)
+                .and_then(|i| input.get(i))
+                .map_or(0.0, |sample| sample.value())
+        })
+        .collect()
+}
+
+fn assert_close(actual: &[Sample], expected: &[f64]) {
+    assert_eq!(actual.len(), expected.len());
+    for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+        assert!(
+            (a.value() - e).abs() < 1e-12,
+            "sample {i}: {} != {e}",
+            a.value()
+        );
+    }
+}
+
+#[test]
+fn dry_paths_are_delayed_to_match_latent_paths() {
+    let builder = GraphBuilder::new();
+    let input = builder.add_input();
+    let out = builder.add_output();
+    let wet = builder.add(Lookahead::new(16)).connect_input(input, 0, 0);
+    let sum = wet + input;
+    sum.connect_output(0, out, 0);
+
+    let mut graph = builder.build();
+    assert_eq!(graph.latency(), 16);
+
+    graph.reset(48_000.0, 64);
+    assert_eq!(
+        graph.edge_delay(edge_between(&graph, input.id(), sum.id())),
+        16
+    );
+    assert_eq!(
+        graph.edge_delay(edge_between(&graph, wet.id(), sum.id())),
+        0
+    );
+
+    // both paths arrive at the sum together, so the output is the input doubled and delayed
+    let config = RenderConfig::default();
+    let output = render(&graph, &config).unwrap();
+    let expected = delayed_input(&config, output[0].len(), 16)
+        .into_iter()
+        .map(|sample| 2.0 * sample)
+        .collect::<Vec<_>>();
+    assert_close(&output[0], &expected);
+}
+
+#[test]
+fn graph_outputs_are_aligned() {
+    let builder = GraphBuilder::new();
+    let input = builder.add_input();
+    let dry_out = builder.add_output();
+    let short_out = builder.add_output();
+    let long_out = builder.add_output();
+    input.connect_output(0, dry_out, 0);
+    let short = builder
+        .add(Lookahead::new(4))
+        .connect_input(input, 0, 0)
+        .connect_output(0, short_out, 0);
+    builder
+        .add(Lookahead::new(10))
+        .connect_input(input, 0, 0)
+        .connect_output(0, long_out, 0);
+
+    let mut graph = builder.build();
+    assert_eq!(graph.latency(), 10);
+
+    graph.reset(48_000.0, 64);
+    assert_eq!(
+        graph.edge_delay(edge_between(&graph, input.id(), dry_out.id())),
+        10
+    );
+    assert_eq!(
+        graph.edge_delay(edge_between(&graph, short.id(), short_out.id())),
+        6
+    );
+
+    let config = RenderConfig::default();
+    let output = render(&graph, &config).unwrap();
+    let expected = delayed_input(&config, output[0].len(), 10);
+    for channel in output.iter() {
+        assert_close(channel, &expected);
+    }
+}
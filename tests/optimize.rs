@@ -0,0 +1,105 @@
+//! Tests that [`Graph::optimize`] doesn't change what a graph outputs.
+
+use daprs::{
+    graph::optimize::OptimizeStats,
+    prelude::*,
+    testing::{compare, render, RenderConfig, Tolerance},
+};
+
+/// Renders the graph before and after optimizing it, and asserts that the outputs match. Returns what the optimizer did.
+fn assert_optimize_preserves_output(graph: Graph) -> OptimizeStats {
+    let config = RenderConfig::default();
+    let expected = render(&graph, &config).unwrap();
+
+    let mut optimized = graph;
+    let stats = optimized.optimize();
+    let actual = render(&optimized, &config).unwrap();
+
+    let comparison = compare(&actual, &expected).unwrap();
+    assert!(
+        comparison.is_within(&Tolerance::default()),
+        "Optimized output differs: {comparison}"
+    );
+    stats
+}
+
+#[test]
+fn optimize_preserves_math_chains() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let gain = graph.add(ConstantProc::new(2.0)) * 0.25;
+    ((input * gain + 1.0).sin() - (input * gain + 1.0).sin() * 0.5).connect_output(0, out, 0);
+
+    let stats = assert_optimize_preserves_output(graph.build());
+    assert!(stats.folded > 0);
+    assert!(stats.deduplicated > 0);
+    assert!(stats.fused > 0);
+}
+
+#[test]
+fn optimize_preserves_reduced_rate_nodes() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let held = (input * 0.5).with_rate(Rate::Divided(4));
+    let block = (input.abs() + 0.25).with_rate(Rate::Block);
+    (held + block).connect_output(0, out, 0);
+
+    assert_optimize_preserves_output(graph.build());
+}
+
+#[test]
+fn optimize_preserves_bypassed_nodes() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let bypassed = input * 3.0;
+    let muted = input.sin();
+    (bypassed + muted).connect_output(0, out, 0);
+    bypassed.bypass_handle().bypass();
+    muted.bypass_handle().mute();
+
+    assert_optimize_preserves_output(graph.build());
+}
+
+#[test]
+fn optimize_keeps_nodes_with_bypass_handles() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let gain = input * 3.0;
+    (gain + 1.0).connect_output(0, out, 0);
+    let handle = gain.bypass_handle();
+
+    let mut graph = graph.build();
+    let unoptimized = graph.clone();
+    graph.optimize();
+
+    // the handle must still control the node after optimizing
+    handle.bypass();
+    unoptimized.bypass_handle(gain.id()).unwrap().bypass();
+    let config = RenderConfig::default();
+    let expected = render(&unoptimized, &config).unwrap();
+    let actual = render(&graph, &config).unwrap();
+    let comparison = compare(&actual, &expected).unwrap();
+    assert!(
+        comparison.is_within(&Tolerance::default()),
+        "Optimized output differs: {comparison}"
+    );
+}
+
+#[test]
+fn optimize_works_on_cloned_graphs() {
+    let graph = GraphBuilder::new();
+    let input = graph.add_input();
+    let out = graph.add_output();
+    let gain = graph.add(ConstantProc::new(2.0)) * 0.25;
+    (input * gain).connect_output(0, out, 0);
+    let _handle = gain.bypass_handle();
+
+    // clones don't share the original's bypass handles, so they can be optimized fully
+    let graph = graph.build();
+    let stats = assert_optimize_preserves_output(graph.clone());
+    assert!(stats.folded > 0);
+}
@@ -0,0 +1,176 @@
+//! Tests for voice allocation and stealing in [`Poly`] nodes.
+
+use daprs::{graph::poly::MAX_VOICES, prelude::*, registry::RegistryError};
+
+const BLOCK_SIZE: usize = 16;
+
+/// Returns a voice whose output is its velocity, scaled by its gate if `gated` is set.
+///
+/// Voices that aren't gated keep sounding after their note is released, so they never go idle on their own.
+fn voice(gated: bool) -> SubGraph {
+    let graph = GraphBuilder::new();
+    let gate = graph.add_input();
+    let _frequency = graph.add_input();
+    let velocity = graph.add_input();
+    let out = graph.add_output();
+    let level = if gated {
+        velocity * gate
+    } else {
+        velocity + 0.0
+    };
+    level.connect_output(0, out, 0);
+    SubGraph::from(graph.build()).with_input_names(["gate", "frequency", "velocity"])
+}
+
+fn poly(num_voices: usize, stealing: VoiceStealing, gated: bool) -> Poly {
+    let mut poly = Poly::new(voice(gated), num_voices).with_stealing(stealing);
+    poly.resize_buffers(48_000.0, BLOCK_SIZE);
+    poly.prepare();
+    poly
+}
+
+/// Processes one block, applying the queued note events.
+fn process(poly: &mut Poly) {
+    let mut outputs = [Buffer::zeros(BLOCK_SIZE)];
+    poly.process(&[], &mut outputs);
+}
+
+/// Returns the notes of the active voices, in voice order, with whether each is still held.
+fn active_notes(poly: &Poly) -> Vec<(u8, bool)> {
+    let state = poly.save_state().unwrap();
+    state.as_map().unwrap()["voices"]
+        .as_list()
+        .unwrap()
+        .iter()
+        .map(|voice| voice.as_map().unwrap())
+        .filter(|voice| voice["active"].as_bool().unwrap())
+        .map(|voice| {
+            (
+                voice["note"].as_f64().unwrap() as u8,
+                voice["gate"].as_bool().unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn oldest_steals_the_earliest_started_voice() {
+    let mut poly = poly(2, VoiceStealing::Oldest, false);
+    let handle = poly.handle();
+    handle.note_on(60, 0.5);
+    handle.note_on(62, 0.5);
+    handle.note_on(64, 0.5);
+    process(&mut poly);
+
+    assert_eq!(active_notes(&poly), [(64, true), (62, true)]);
+}
+
+#[test]
+fn quietest_steals_the_lowest_voice() {
+    let mut poly = poly(3, VoiceStealing::Quietest, false);
+    let handle = poly.handle();
+    handle.note_on(60, 0.2);
+    handle.note_on(62, 0.9);
+    handle.note_on(64, 0.1);
+    process(&mut poly);
+
+    // levels are measured while processing, so the new note has to wait for the next block
+    handle.note_on(67, 0.5);
+    process(&mut poly);
+    assert_eq!(active_notes(&poly), [(60, true), (62, true), (67, true)]);
+}
+
+#[test]
+fn same_note_retriggers_the_voice_playing_it() {
+    let mut poly = poly(3, VoiceStealing::SameNote, false);
+    let handle = poly.handle();
+    handle.note_on(60, 0.5);
+    handle.note_on(62, 0.5);
+    // a voice is still free, but the note is already playing
+    handle.note_on(60, 0.8);
+    process(&mut poly);
+    assert_eq!(active_notes(&poly), [(60, true), (62, true)]);
+
+    // without a voice playing the same note, the oldest one is stolen
+    handle.note_on(64, 0.5);
+    handle.note_on(65, 0.5);
+    process(&mut poly);
+    assert_eq!(active_notes(&poly), [(60, true), (65, true), (64, true)]);
+}
+
+#[test]
+fn released_voices_are_stolen_before_held_ones() {
+    for stealing in [
+        VoiceStealing::Oldest,
+        VoiceStealing::Quietest,
+        VoiceStealing::SameNote,
+    ] {
+        let mut poly = poly(2, stealing, false);
+        let handle = poly.handle();
+        handle.note_on(60, 0.1);
+        handle.note_on(62, 0.9);
+        process(&mut poly);
+
+        // the released voice is both the newest and the loudest
+        handle.note_off(62);
+        handle.note_on(64, 0.5);
+        process(&mut poly);
+        assert_eq!(
+            active_notes(&poly),
+            [(60, true), (64, true)],
+            "{stealing:?}"
+        );
+    }
+}
+
+#[test]
+fn released_voices_go_idle_once_silent() {
+    let mut poly = poly(2, VoiceStealing::Oldest, true);
+    let handle = poly.handle();
+    handle.note_on(60, 0.5);
+    handle.note_on(62, 0.5);
+    process(&mut poly);
+    assert_eq!(poly.active_voices(), 2);
+
+    handle.note_off(60);
+    process(&mut poly);
+    assert_eq!(active_notes(&poly), [(62, true)]);
+
+    handle.all_notes_off();
+    process(&mut poly);
+    assert_eq!(poly.active_voices(), 0);
+
+    // idle voices are reused before any is stolen
+    handle.note_on(64, 0.5);
+    process(&mut poly);
+    assert_eq!(active_notes(&poly), [(64, true)]);
+}
+
+#[test]
+fn voice_counts_are_bounded() {
+    let registry = ProcessorRegistry::default();
+    let voice = Patch::from_graph(voice(true).graph()).unwrap().to_param();
+    let create = |voices: f64| {
+        registry.create_with(
+            "Poly",
+            [("voice", voice.clone()), ("voices", Param::from(voices))],
+        )
+    };
+
+    assert!(create(4.0).is_ok());
+    assert!(create(MAX_VOICES as f64).is_ok());
+    for voices in [
+        0.0,
+        2.5,
+        MAX_VOICES as f64 + 1.0,
+        1e12,
+        f64::INFINITY,
+        f64::NAN,
+    ] {
+        match create(voices) {
+            Err(RegistryError::InvalidParam { param, .. }) => assert_eq!(param, "voices"),
+            Err(err) => panic!("unexpected error for {voices} voices: {err}"),
+            Ok(_) => panic!("{voices} voices were accepted"),
+        }
+    }
+}
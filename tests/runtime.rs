@@ -0,0 +1,52 @@
+//! Tests for offline rendering with the [`Runtime`].
+
+use daprs::{
+    prelude::*,
+    runtime::{RuntimeError, MAX_TAIL_SECONDS},
+    testing::{processor_graph, render, RenderConfig},
+};
+
+/// A feedback loop at unity gain, whose tail never ends.
+#[derive(Clone, Debug, Default)]
+struct EndlessTail;
+
+impl Process for EndlessTail {
+    fn input_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("in", 0.0)]
+    }
+
+    fn output_spec(&self) -> Vec<SignalSpec> {
+        vec![SignalSpec::unbounded("out", 0.0)]
+    }
+
+    fn tail_length(&self) -> usize {
+        usize::MAX
+    }
+
+    fn process(&mut self, inputs: &[Buffer], outputs: &mut [Buffer]) {
+        outputs[0].copy_from_slice(&inputs[0]);
+    }
+}
+
+#[test]
+fn endless_tails_are_cut_off() {
+    let config = RenderConfig::default()
+        .with_sample_rate(100.0)
+        .with_num_samples(100);
+    let outputs = render(&processor_graph(EndlessTail.processor()), &config).unwrap();
+
+    assert_eq!(outputs[0].len(), 100 + (MAX_TAIL_SECONDS * 100.0) as usize);
+}
+
+#[test]
+fn invalid_render_settings_are_rejected() {
+    let mut runtime = Runtime::new(processor_graph(EndlessTail.processor()));
+    let duration = std::time::Duration::from_millis(10);
+
+    let result = runtime.run_offline(duration, 48_000.0, 0);
+    assert!(matches!(result, Err(RuntimeError::InvalidBlockSize(0))));
+    for sample_rate in [0.0, -48_000.0, f64::NAN, f64::INFINITY] {
+        let result = runtime.run_offline(duration, sample_rate, 64);
+        assert!(matches!(result, Err(RuntimeError::InvalidSampleRate(_))));
+    }
+}
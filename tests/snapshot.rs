@@ -0,0 +1,102 @@
+//! Tests for saving and restoring graph state with [`GraphSnapshot`]s.
+
+use std::time::Duration;
+
+use daprs::prelude::*;
+
+#[test]
+fn divided_rate_phases_survive_param_round_trips() {
+    let inner = GraphBuilder::new();
+    let input = inner.add_input();
+    (input * 0.5)
+        .with_rate(Rate::Divided(3))
+        .connect_output(0, inner.add_output(), 0);
+
+    let graph = GraphBuilder::new();
+    let subgraph = graph.add_subgraph(inner.build());
+    subgraph.connect_output(0, graph.add_output(), 0);
+    let mut runtime = Runtime::new(graph.build());
+    // 48 samples in blocks of 16, which 3 doesn't divide
+    runtime
+        .run_offline(Duration::from_millis(1), 48_000.0, 16)
+        .unwrap();
+
+    // the subgraph saves the snapshot of its inner graph as its state, which holds numbers as floats
+    let snapshot = runtime.graph().snapshot();
+    let state = snapshot
+        .nodes
+        .values()
+        .find_map(|node| node.state.as_ref())
+        .expect("the subgraph saves its state");
+    let inner = GraphSnapshot::from_param(state).unwrap();
+    assert!(inner.nodes.values().any(|node| node.phase != (0, 0)));
+}
+
+/// Returns all samples saved in the snapshot.
+fn samples(snapshot: &GraphSnapshot) -> Vec<f64> {
+    let nodes = snapshot.nodes.values().flat_map(|node| {
+        let outputs = node.outputs.iter().flatten().copied();
+        let interpolation = node
+            .interpolation_starts
+            .iter()
+            .chain(node.last_outputs.iter().flatten())
+            .flatten()
+            .copied();
+        outputs.chain(interpolation)
+    });
+    let delays = snapshot.edge_delays.values().flatten().copied();
+    nodes.chain(delays).collect()
+}
+
+/// Asserts that the snapshots hold the same samples, treating all NaNs as equal.
+fn assert_same_samples(actual: &GraphSnapshot, expected: &GraphSnapshot) {
+    let (actual, expected) = (samples(actual), samples(expected));
+    assert_eq!(actual.len(), expected.len());
+    for (actual, expected) in actual.into_iter().zip(expected) {
+        assert!(
+            actual == expected || actual.is_nan() && expected.is_nan(),
+            "expected {expected}, got {actual}"
+        );
+    }
+}
+
+#[test]
+fn non_finite_samples_survive_round_trips() {
+    let inner = GraphBuilder::new();
+    for value in [f64::INFINITY, f64::NEG_INFINITY, f64::NAN] {
+        inner
+            .add(ConstantProc::new(value))
+            .connect_output(0, inner.add_output(), 0);
+    }
+
+    let graph = GraphBuilder::new();
+    let subgraph = graph.add_subgraph(inner.build());
+    for output in 0..3 {
+        subgraph.connect_output(output, graph.add_output(), 0);
+    }
+    let mut runtime = Runtime::new(graph.build());
+    runtime
+        .run_offline(Duration::from_millis(1), 48_000.0, 16)
+        .unwrap();
+
+    // the subgraph saves the snapshot of its inner graph as its state, with the infinite and NaN samples in its output buffers
+    let snapshot = runtime.graph().snapshot();
+    let inner = snapshot
+        .nodes
+        .values()
+        .find_map(|node| node.state.as_ref())
+        .expect("the subgraph saves its state");
+    let inner = GraphSnapshot::from_param(inner).unwrap();
+    let saved = samples(&inner);
+    assert!(saved.iter().any(|sample| sample.is_nan()));
+    assert!(saved.contains(&f64::INFINITY));
+    assert!(saved.contains(&f64::NEG_INFINITY));
+
+    let param = inner.to_param().unwrap();
+    let from_param = GraphSnapshot::from_param(&param).unwrap();
+    assert_same_samples(&from_param, &inner);
+
+    let json = snapshot.to_json().unwrap();
+    let from_json = GraphSnapshot::from_json(&json).unwrap();
+    assert_same_samples(&from_json, &snapshot);
+}
@@ -0,0 +1,201 @@
+//! Tests for the static checks of [`Graph::validate`], with one small graph per kind of [`Diagnostic`].
+
+use std::time::Duration;
+
+use daprs::{
+    graph::{
+        validate::{Diagnostic, Severity},
+        PortDirection,
+    },
+    prelude::*,
+    runtime::RuntimeError,
+};
+
+/// A gain whose input is limited to `[0.0, 1.0]`.
+#[derive(Clone, Default, Process)]
+struct Gain {
+    #[input]
+    input: f64,
+    #[input(default = 1.0, min = 0.0, max = 1.0)]
+    gain: f64,
+    #[output]
+    out: f64,
+}
+
+impl SampleProcess for Gain {
+    fn tick(&mut self) {
+        self.out = self.input * self.gain;
+    }
+}
+
+#[test]
+fn connected_graphs_have_no_diagnostics() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let gain = graph.add_processor(Gain::default());
+    let level = graph.add_processor(ConstantProc::new(0.5));
+    graph.connect(input, 0, gain, 0).unwrap();
+    graph.connect(level, 0, gain, 1).unwrap();
+    graph.connect(gain, 0, output, 0).unwrap();
+
+    assert_eq!(graph.validate(), []);
+}
+
+#[test]
+fn edges_to_missing_ports_are_errors() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let node = graph.add_processor(AddProc);
+    graph.connect(input, 0, node, 0).unwrap();
+    graph.connect(input, 0, node, 1).unwrap();
+    graph.connect(node, 0, output, 0).unwrap();
+    // the replacement only has one input, leaving the edge into the second one dangling
+    graph.replace_processor(node, NegProc);
+
+    let diagnostics = graph.validate();
+    assert_eq!(
+        diagnostics[0],
+        Diagnostic::PortOutOfRange {
+            source_node: input,
+            target: node,
+            node,
+            direction: PortDirection::Input,
+            index: 1,
+            num_ports: 1,
+        }
+    );
+    assert!(diagnostics[0].is_error());
+    assert!(diagnostics[1..]
+        .iter()
+        .all(|diagnostic| !diagnostic.is_error()));
+
+    // the runtime refuses to render it
+    let mut runtime = Runtime::new(graph);
+    match runtime.run_offline(Duration::from_millis(10), 48_000.0, 64) {
+        Err(RuntimeError::InvalidGraph(errors)) => assert_eq!(errors, diagnostics[..1]),
+        Err(err) => panic!("unexpected error: {err}"),
+        Ok(_) => panic!("a graph with errors was rendered"),
+    }
+}
+
+#[test]
+fn feedback_loops_are_reported() {
+    let mut graph = Graph::new();
+    let output = graph.add_output();
+    let a = graph.add_processor(AddProc);
+    let b = graph.add_processor(MulProc);
+    graph.connect(a, 0, b, 0).unwrap();
+    graph.connect(b, 0, a, 0).unwrap();
+    graph.connect(b, 0, output, 0).unwrap();
+
+    let mut nodes = vec![a, b];
+    nodes.sort();
+    let diagnostics = graph.validate();
+    assert!(diagnostics.contains(&Diagnostic::Cycle(nodes)));
+    assert!(diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.severity() <= Severity::Warning));
+}
+
+#[test]
+fn undriven_outputs_are_reported() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let first = graph.add_output();
+    graph.add_output();
+    graph.connect(input, 0, first, 0).unwrap();
+
+    let diagnostics = graph.validate();
+    assert_eq!(diagnostics, [Diagnostic::UndrivenOutput(1)]);
+    assert_eq!(diagnostics[0].severity(), Severity::Warning);
+}
+
+#[test]
+fn inputs_with_several_sources_are_reported() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let constant = graph.add_processor(ConstantProc::new(1.0));
+    graph.connect(input, 0, output, 0).unwrap();
+    graph.connect(constant, 0, output, 0).unwrap();
+
+    assert_eq!(
+        graph.validate(),
+        [Diagnostic::MultipleSources {
+            node: output,
+            input: 0
+        }]
+    );
+}
+
+#[test]
+fn nodes_not_reaching_an_output_are_reported() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let dead = graph.add_processor(NegProc);
+    graph.connect(input, 0, output, 0).unwrap();
+    graph.connect(input, 0, dead, 0).unwrap();
+
+    assert_eq!(
+        graph.validate(),
+        [Diagnostic::UnreachableNode {
+            node: dead,
+            name: NegProc::NAME.to_owned(),
+        }]
+    );
+}
+
+#[test]
+fn unconnected_inputs_are_reported_as_info() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let gain = graph.add_processor(Gain::default());
+    graph.connect(input, 0, gain, 0).unwrap();
+    graph.connect(gain, 0, output, 0).unwrap();
+
+    let diagnostics = graph.validate();
+    assert_eq!(
+        diagnostics,
+        [Diagnostic::UnconnectedInput {
+            node: gain,
+            input: 1,
+            name: "gain".to_owned(),
+            default_value: 1.0,
+        }]
+    );
+    assert_eq!(diagnostics[0].severity(), Severity::Info);
+}
+
+#[test]
+fn constants_outside_of_input_ranges_are_reported() {
+    let mut graph = Graph::new();
+    let input = graph.add_input();
+    let output = graph.add_output();
+    let gain = graph.add_processor(Gain::default());
+    let level = graph.add_processor(ConstantProc::new(2.0));
+    graph.connect(input, 0, gain, 0).unwrap();
+    graph.connect(level, 0, gain, 1).unwrap();
+    graph.connect(gain, 0, output, 0).unwrap();
+
+    assert_eq!(
+        graph.validate(),
+        [Diagnostic::ConstantOutOfRange {
+            node: gain,
+            input: 1,
+            name: "gain".to_owned(),
+            value: 2.0,
+            min: 0.0,
+            max: 1.0,
+        }]
+    );
+
+    // warnings don't stop the runtime
+    let mut runtime = Runtime::new(graph);
+    runtime
+        .run_offline(Duration::from_millis(10), 48_000.0, 64)
+        .unwrap();
+}